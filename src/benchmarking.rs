@@ -5,10 +5,12 @@ use cozy_chess::{
 };
 use log;
 
+use crate::pallet::{MatchMoves, MatchmakingQueue, PositionHistory};
 #[allow(unused)]
 use crate::Pallet as Chess;
 //use crate::mock::*;
-use frame_benchmarking::{account, benchmarks, vec, Vec};
+use frame_benchmarking::{account, benchmarks, vec, BenchmarkError, Vec};
+use frame_support::BoundedVec;
 use frame_system::{Pallet as System, RawOrigin};
 //use pallet_assets::Pallet as Assets;
 use frame_system::pallet_prelude::BlockNumberFor;
@@ -18,6 +20,8 @@ use sp_runtime::SaturatedConversion;
 
 const MOVES_PER_POSITION: u32 = 52;
 const INITIAL_BOARD: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+// custom start positions are decoded during create_match, which is the worst case
+const QUEEN_ODDS_BOARD: &str = "rnb1kbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
 const POSITIONS: &[&str] = &[
     "Q7/5Q2/8/8/3k4/6P1/6BP/7K b - - 0 67",
     "r4rk1/p4ppp/1q2p3/2n1P3/2p5/3bRNP1/1P3PBP/R2Q2K1 b - - 0 24",
@@ -46,6 +50,21 @@ const POSITIONS: &[&str] = &[
     "8/1p2k3/4rp2/p2R3Q/2q2B2/6P1/5P1P/6K1 b - - 14 73",
 ];
 
+// fills the move history of a match with `m` moves, and its position history with as many
+// positions as it can hold, so that make_move works on the longest histories
+fn fill_histories<T: Config>(match_id: T::Hash, m: u32) {
+    let filler = CompactMove::from_move("e2e4".parse().unwrap());
+    MatchMoves::<T>::insert(
+        match_id,
+        BoundedVec::truncate_from(vec![filler; m as usize]),
+    );
+    let positions = m.min(T::MaxPositionHistory::get()) as u64;
+    PositionHistory::<T>::insert(
+        match_id,
+        BoundedVec::truncate_from((0..positions).collect::<Vec<_>>()),
+    );
+}
+
 fn generate_moves(board_fen: &str) -> Vec<String> {
     let board_vec: Vec<Board> = vec![board_fen.parse().unwrap()];
     let promos: &Vec<Option<Piece>> = &Piece::ALL.into_iter().map(Some).chain([None]).collect();
@@ -217,15 +236,16 @@ benchmarks! {
         let opponent: T::AccountId = account("Bob", 0, 1);
         let bet_asset_id = ASSET_ID;
         let bet_amount = ASSET_MIN_BALANCE * 10;
-    }: _(RawOrigin::Signed(challenger.clone()), opponent.clone(), MatchStyle::Bullet, bet_asset_id.into(), bet_amount.into())
+    }: _(RawOrigin::Signed(challenger.clone()), Some(opponent.clone()), MatchStyle::Bullet, bet_asset_id.into(), bet_amount.into(), MatchOptions { start_fen: Some(QUEEN_ODDS_BOARD.into()), rated: false, ..Default::default() })
     verify {
         let match_id = Chess::<T>::chess_match_id_from_nonce(0).unwrap();
         let chess_match = Chess::<T>::chess_matches(match_id).unwrap();
         assert_eq!(chess_match.challenger, challenger);
-        assert_eq!(chess_match.opponent, opponent);
-        assert_eq!(chess_match.board, INITIAL_BOARD.as_bytes().to_vec());
+        assert_eq!(chess_match.opponent, Some(opponent));
+        assert_eq!(chess_match.board, QUEEN_ODDS_BOARD.as_bytes().to_vec());
         assert_eq!(chess_match.state, MatchState::AwaitingOpponent);
         assert_eq!(chess_match.nonce, 0);
+        assert!(!chess_match.rated);
     }
 
     abort_match {
@@ -233,7 +253,7 @@ benchmarks! {
         let opponent: T::AccountId = account("Bob", 0, 1);
         let bet_asset_id = ASSET_ID;
         let bet_amount = ASSET_MIN_BALANCE * 10;
-        Chess::<T>::create_match(RawOrigin::Signed(challenger.clone()).into(), opponent.clone(), MatchStyle::Bullet, bet_asset_id.into(), bet_amount.into()).unwrap();
+        Chess::<T>::create_match(RawOrigin::Signed(challenger.clone()).into(), Some(opponent.clone()), MatchStyle::Bullet, bet_asset_id.into(), bet_amount.into(), MatchOptions::default()).unwrap();
         let match_id = Chess::<T>::chess_match_id_from_nonce(0).unwrap();
     }: _(RawOrigin::Signed(challenger), match_id)
     verify {
@@ -246,7 +266,8 @@ benchmarks! {
         let opponent: T::AccountId = account("Bob", 0, 1);
         let bet_asset_id = ASSET_ID;
         let bet_amount = ASSET_MIN_BALANCE * 10;
-        Chess::<T>::create_match(RawOrigin::Signed(challenger.clone()).into(), opponent.clone(), MatchStyle::Bullet, bet_asset_id.into(), bet_amount.into()).unwrap();
+        // joining an open challenge also updates the OpenChallenges index
+        Chess::<T>::create_match(RawOrigin::Signed(challenger.clone()).into(), None, MatchStyle::Bullet, bet_asset_id.into(), bet_amount.into(), MatchOptions::default()).unwrap();
         let match_id = Chess::<T>::chess_match_id_from_nonce(0).unwrap();
     }: _(RawOrigin::Signed(opponent), match_id)
     verify {
@@ -256,6 +277,7 @@ benchmarks! {
 
     make_move {
         let i in 0 .. ((POSITIONS.len() as u32 - 1) * MOVES_PER_POSITION) as u32;
+        let m in 0 .. T::MaxMoves::get();
 
         let position_index = (i / MOVES_PER_POSITION) as usize;
         let position_to_benchmark = POSITIONS[position_index];
@@ -272,11 +294,12 @@ benchmarks! {
         let bet_asset_id = ASSET_ID;
         let bet_amount = ASSET_MIN_BALANCE * 10;
 
-        Chess::<T>::create_match(RawOrigin::Signed(challenger.clone()).into(), opponent.clone(), MatchStyle::Bullet, bet_asset_id.into(), bet_amount.into()).unwrap();
+        Chess::<T>::create_match(RawOrigin::Signed(challenger.clone()).into(), Some(opponent.clone()), MatchStyle::Bullet, bet_asset_id.into(), bet_amount.into(), MatchOptions::default()).unwrap();
         let match_id = Chess::<T>::chess_match_id_from_nonce(0).unwrap();
         Chess::<T>::join_match(RawOrigin::Signed(opponent.clone()).into(), match_id).unwrap();
 
         Chess::<T>::force_board_state(match_id, position_to_benchmark.as_bytes().to_vec()).unwrap();
+        fill_histories::<T>(match_id, m);
         let chess_match = Chess::<T>::chess_matches(match_id).unwrap();
 
         let player = match chess_match.state {
@@ -287,6 +310,29 @@ benchmarks! {
 
     }: _(RawOrigin::Signed(player), match_id, move_to_benchmark.as_str().into())
 
+    // a move that ends the match, which is then settled
+    make_move_checkmate {
+        let m in 0 .. T::MaxMoves::get();
+        let challenger: T::AccountId = account("Alice", 0, 0);
+        let opponent: T::AccountId = account("Bob", 0, 1);
+        let bet_asset_id = ASSET_ID;
+        let bet_amount = ASSET_MIN_BALANCE * 10;
+
+        Chess::<T>::create_match(RawOrigin::Signed(challenger.clone()).into(), Some(opponent.clone()), MatchStyle::Bullet, bet_asset_id.into(), bet_amount.into(), MatchOptions::default()).unwrap();
+        let match_id = Chess::<T>::chess_match_id_from_nonce(0).unwrap();
+        Chess::<T>::join_match(RawOrigin::Signed(opponent.clone()).into(), match_id).unwrap();
+
+        // fool's mate, blacks mate on their second move
+        Chess::<T>::make_move(RawOrigin::Signed(challenger.clone()).into(), match_id, "f2f3".into()).unwrap();
+        Chess::<T>::make_move(RawOrigin::Signed(opponent.clone()).into(), match_id, "e7e5".into()).unwrap();
+        Chess::<T>::make_move(RawOrigin::Signed(challenger.clone()).into(), match_id, "g2g4".into()).unwrap();
+        fill_histories::<T>(match_id, m);
+    }: make_move(RawOrigin::Signed(opponent), match_id, "d8h4".into())
+    verify {
+        assert!(Chess::<T>::chess_matches(match_id).is_none());
+        assert!(Chess::<T>::finished_matches(match_id).is_some());
+    }
+
     clear_abandoned_match {
         let alice: T::AccountId = account("Alice", 0, 0);
         let bob: T::AccountId = account("Bob", 0, 1);
@@ -298,7 +344,7 @@ benchmarks! {
         // let initial_balance_b = Assets::<T>::balance(bet_asset_id.into(), bob.clone());
         // let initial_balance_c = Assets::<T>::balance(bet_asset_id.into(), janitor.clone());
 
-        Chess::<T>::create_match(RawOrigin::Signed(alice.clone()).into(), bob.clone(), MatchStyle::Bullet, bet_asset_id.into(), bet_amount.into()).unwrap();
+        Chess::<T>::create_match(RawOrigin::Signed(alice.clone()).into(), Some(bob.clone()), MatchStyle::Bullet, bet_asset_id.into(), bet_amount.into(), MatchOptions::default()).unwrap();
         let match_id = Chess::<T>::chess_match_id_from_nonce(0).unwrap();
         Chess::<T>::join_match(RawOrigin::Signed(bob.clone()).into(), match_id).unwrap();
         Chess::<T>::make_move(RawOrigin::Signed(alice.clone()).into(), match_id, "e2e4".into()).unwrap();
@@ -307,10 +353,10 @@ benchmarks! {
         let (janitor_incentive, actual_prize): (BalanceOf<T>, BalanceOf<T>) = chess_match.janitor_incentive();
         let (janitor_incentive, actual_prize): (u64, u64) = (janitor_incentive.saturated_into(), actual_prize.saturated_into());
 
-        // advance the block number to the point where Bob's time-to-move is expired
+        // advance the block number to the point where Bob's clock is expired
         // and Alice's time to claim victory is also expired
         System::<T>::set_block_number(
-            System::<T>::block_number() + <T as Config>::BulletPeriod::get() * 10u32.into() + 1u32.into(),
+            System::<T>::block_number() + <T as Config>::BulletPeriod::get() * 11u32.into() + 1u32.into(),
         );
     }: _(RawOrigin::Signed(janitor.clone()), match_id)
     verify {
@@ -325,5 +371,189 @@ benchmarks! {
         // assert_eq!(final_balance_c, initial_balance_c + janitor_incentive.into());
     }
 
+    resign {
+        let alice: T::AccountId = account("Alice", 0, 0);
+        let bob: T::AccountId = account("Bob", 0, 1);
+        let bet_asset_id = ASSET_ID;
+        let bet_amount = ASSET_MIN_BALANCE * 10;
+
+        Chess::<T>::create_match(RawOrigin::Signed(alice.clone()).into(), Some(bob.clone()), MatchStyle::Bullet, bet_asset_id.into(), bet_amount.into(), MatchOptions::default()).unwrap();
+        let match_id = Chess::<T>::chess_match_id_from_nonce(0).unwrap();
+        Chess::<T>::join_match(RawOrigin::Signed(bob.clone()).into(), match_id).unwrap();
+        Chess::<T>::make_move(RawOrigin::Signed(alice.clone()).into(), match_id, "e2e4".into()).unwrap();
+    }: _(RawOrigin::Signed(bob), match_id)
+    verify {
+        assert!(Chess::<T>::chess_matches(match_id).is_none());
+        assert!(Chess::<T>::chess_match_id_from_nonce(0).is_none());
+    }
+
+    offer_draw {
+        let alice: T::AccountId = account("Alice", 0, 0);
+        let bob: T::AccountId = account("Bob", 0, 1);
+        let bet_asset_id = ASSET_ID;
+        let bet_amount = ASSET_MIN_BALANCE * 10;
+
+        Chess::<T>::create_match(RawOrigin::Signed(alice.clone()).into(), Some(bob.clone()), MatchStyle::Bullet, bet_asset_id.into(), bet_amount.into(), MatchOptions::default()).unwrap();
+        let match_id = Chess::<T>::chess_match_id_from_nonce(0).unwrap();
+        Chess::<T>::join_match(RawOrigin::Signed(bob.clone()).into(), match_id).unwrap();
+    }: _(RawOrigin::Signed(alice.clone()), match_id)
+    verify {
+        let chess_match = Chess::<T>::chess_matches(match_id).unwrap();
+        assert_eq!(chess_match.draw_offer, Some(alice));
+    }
+
+    accept_draw {
+        let alice: T::AccountId = account("Alice", 0, 0);
+        let bob: T::AccountId = account("Bob", 0, 1);
+        let bet_asset_id = ASSET_ID;
+        let bet_amount = ASSET_MIN_BALANCE * 10;
+
+        Chess::<T>::create_match(RawOrigin::Signed(alice.clone()).into(), Some(bob.clone()), MatchStyle::Bullet, bet_asset_id.into(), bet_amount.into(), MatchOptions::default()).unwrap();
+        let match_id = Chess::<T>::chess_match_id_from_nonce(0).unwrap();
+        Chess::<T>::join_match(RawOrigin::Signed(bob.clone()).into(), match_id).unwrap();
+        Chess::<T>::offer_draw(RawOrigin::Signed(alice.clone()).into(), match_id).unwrap();
+    }: _(RawOrigin::Signed(bob), match_id)
+    verify {
+        assert!(Chess::<T>::chess_matches(match_id).is_none());
+        assert!(Chess::<T>::chess_match_id_from_nonce(0).is_none());
+    }
+
+    decline_draw {
+        let alice: T::AccountId = account("Alice", 0, 0);
+        let bob: T::AccountId = account("Bob", 0, 1);
+        let bet_asset_id = ASSET_ID;
+        let bet_amount = ASSET_MIN_BALANCE * 10;
+
+        Chess::<T>::create_match(RawOrigin::Signed(alice.clone()).into(), Some(bob.clone()), MatchStyle::Bullet, bet_asset_id.into(), bet_amount.into(), MatchOptions::default()).unwrap();
+        let match_id = Chess::<T>::chess_match_id_from_nonce(0).unwrap();
+        Chess::<T>::join_match(RawOrigin::Signed(bob.clone()).into(), match_id).unwrap();
+        Chess::<T>::offer_draw(RawOrigin::Signed(alice.clone()).into(), match_id).unwrap();
+    }: _(RawOrigin::Signed(bob), match_id)
+    verify {
+        let chess_match = Chess::<T>::chess_matches(match_id).unwrap();
+        assert_eq!(chess_match.draw_offer, None);
+    }
+
+    claim_draw {
+        let alice: T::AccountId = account("Alice", 0, 0);
+        let bob: T::AccountId = account("Bob", 0, 1);
+        let bet_asset_id = ASSET_ID;
+        let bet_amount = ASSET_MIN_BALANCE * 10;
+
+        Chess::<T>::create_match(RawOrigin::Signed(alice.clone()).into(), Some(bob.clone()), MatchStyle::Bullet, bet_asset_id.into(), bet_amount.into(), MatchOptions::default()).unwrap();
+        let match_id = Chess::<T>::chess_match_id_from_nonce(0).unwrap();
+        Chess::<T>::join_match(RawOrigin::Signed(bob.clone()).into(), match_id).unwrap();
+
+        // shuffle the knights back and forth until the initial position occurs three times
+        for _ in 0..2 {
+            Chess::<T>::make_move(RawOrigin::Signed(alice.clone()).into(), match_id, "g1f3".into()).unwrap();
+            Chess::<T>::make_move(RawOrigin::Signed(bob.clone()).into(), match_id, "g8f6".into()).unwrap();
+            Chess::<T>::make_move(RawOrigin::Signed(alice.clone()).into(), match_id, "f3g1".into()).unwrap();
+            Chess::<T>::make_move(RawOrigin::Signed(bob.clone()).into(), match_id, "f6g8".into()).unwrap();
+        }
+    }: _(RawOrigin::Signed(alice), match_id)
+    verify {
+        assert!(Chess::<T>::chess_matches(match_id).is_none());
+        assert!(Chess::<T>::chess_match_id_from_nonce(0).is_none());
+    }
+
+    prune_finished_match {
+        let alice: T::AccountId = account("Alice", 0, 0);
+        let bob: T::AccountId = account("Bob", 0, 1);
+        let janitor: T::AccountId = account("Charlie", 0, 2);
+        let bet_asset_id = ASSET_ID;
+        let bet_amount = ASSET_MIN_BALANCE * 10;
+
+        Chess::<T>::create_match(RawOrigin::Signed(alice.clone()).into(), Some(bob.clone()), MatchStyle::Bullet, bet_asset_id.into(), bet_amount.into(), MatchOptions::default()).unwrap();
+        let match_id = Chess::<T>::chess_match_id_from_nonce(0).unwrap();
+        Chess::<T>::join_match(RawOrigin::Signed(bob.clone()).into(), match_id).unwrap();
+        Chess::<T>::resign(RawOrigin::Signed(bob.clone()).into(), match_id).unwrap();
+
+        System::<T>::set_block_number(
+            System::<T>::block_number() + <T as Config>::FinishedMatchRetention::get() + 1u32.into(),
+        );
+    }: _(RawOrigin::Signed(janitor), match_id)
+    verify {
+        assert!(Chess::<T>::finished_matches(match_id).is_none());
+    }
+
+    enter_queue {
+        let alice: T::AccountId = account("Alice", 0, 0);
+        let bet_asset_id = ASSET_ID;
+        let bet_amount = ASSET_MIN_BALANCE * 10;
+    }: _(RawOrigin::Signed(alice.clone()), MatchStyle::Bullet, bet_asset_id.into(), bet_amount.into())
+    verify {
+        assert_eq!(Chess::<T>::matchmaking_queue()[0].player, alice);
+    }
+
+    leave_queue {
+        let alice: T::AccountId = account("Alice", 0, 0);
+        let bet_asset_id = ASSET_ID;
+        let bet_amount = ASSET_MIN_BALANCE * 10;
+        Chess::<T>::enter_queue(RawOrigin::Signed(alice.clone()).into(), MatchStyle::Bullet, bet_asset_id.into(), bet_amount.into()).unwrap();
+    }: _(RawOrigin::Signed(alice))
+    verify {
+        assert!(Chess::<T>::matchmaking_queue().is_empty());
+    }
+
+    start_queued_match {
+        let q in 2 .. T::MaxQueueLength::get();
+        let alice: T::AccountId = account("Alice", 0, 0);
+        let bob: T::AccountId = account("Bob", 0, 1);
+        let bet_asset_id = ASSET_ID;
+        let bet_amount = ASSET_MIN_BALANCE * 10;
+        // alice is paired with bob, scanning every filler in between
+        Chess::<T>::enter_queue(RawOrigin::Signed(alice.clone()).into(), MatchStyle::Bullet, bet_asset_id.into(), bet_amount.into()).unwrap();
+        for k in 0 .. q - 2 {
+            let filler: T::AccountId = account("filler", k, 0);
+            Chess::<T>::enter_queue(RawOrigin::Signed(filler).into(), MatchStyle::Blitz, bet_asset_id.into(), 0u32.into()).unwrap();
+        }
+        Chess::<T>::enter_queue(RawOrigin::Signed(bob.clone()).into(), MatchStyle::Bullet, bet_asset_id.into(), bet_amount.into()).unwrap();
+    }: {
+        let mut queue = Chess::<T>::matchmaking_queue();
+        assert!(Chess::<T>::pair_queued_entry(&mut queue, 0).unwrap());
+        MatchmakingQueue::<T>::put(queue);
+    }
+    verify {
+        assert_eq!(Chess::<T>::matchmaking_queue().len() as u32, q - 2);
+        let match_id = Chess::<T>::chess_match_id_from_nonce(0).unwrap();
+        let chess_match = Chess::<T>::chess_matches(match_id).unwrap();
+        assert_eq!(chess_match.state, MatchState::OnGoing(NextMove::Whites));
+    }
+
+    skip_queued_entry {
+        let q in 1 .. T::MaxQueueLength::get();
+        let alice: T::AccountId = account("Alice", 0, 0);
+        let bet_asset_id = ASSET_ID;
+        let bet_amount = ASSET_MIN_BALANCE * 10;
+        // nobody in the queue is compatible with alice
+        Chess::<T>::enter_queue(RawOrigin::Signed(alice.clone()).into(), MatchStyle::Bullet, bet_asset_id.into(), bet_amount.into()).unwrap();
+        for k in 0 .. q - 1 {
+            let filler: T::AccountId = account("filler", k, 0);
+            Chess::<T>::enter_queue(RawOrigin::Signed(filler).into(), MatchStyle::Blitz, bet_asset_id.into(), 0u32.into()).unwrap();
+        }
+        let mut queue = Chess::<T>::matchmaking_queue();
+    }: {
+        assert!(!Chess::<T>::pair_queued_entry(&mut queue, 0).unwrap());
+    }
+    verify {
+        assert_eq!(queue.len() as u32, q);
+    }
+
+    set_parameters {
+        let origin = T::AdminOrigin::try_successful_origin().map_err(|_| BenchmarkError::Weightless)?;
+        let parameters = Parameters {
+            bullet_period: 20u32.into(),
+            blitz_period: 100u32.into(),
+            rapid_period: 300u32.into(),
+            daily_period: 28800u32.into(),
+            incentive_share: 20,
+            k: 16,
+        };
+    }: _<T::RuntimeOrigin>(origin, parameters.clone())
+    verify {
+        assert_eq!(Chess::<T>::chess_parameters(), parameters);
+    }
+
     impl_benchmark_test_suite!(Chess, crate::mock::new_test_ext(), crate::mock::Test);
 }
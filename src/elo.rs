@@ -0,0 +1,105 @@
+//! Deterministic Elo rating updates.
+//!
+//! Floats are a consensus risk in Wasm, so ratings are computed with fixed-point arithmetic.
+//! `10^(diff / 400)` is obtained by multiplying precomputed powers `10^(2^i / 400)`, one for every
+//! bit set in the rating difference.
+
+use crate::rating::RatingSystem;
+use sp_runtime::{FixedPointNumber, FixedU128};
+
+/// Score of a won match
+pub const WIN: FixedU128 = FixedU128::from_inner(FixedU128::DIV);
+/// Score of a drawn match
+pub const DRAW: FixedU128 = FixedU128::from_inner(FixedU128::DIV / 2);
+/// Score of a lost match
+pub const LOSS: FixedU128 = FixedU128::from_inner(0);
+
+/// Rating differences are capped, beyond that the weaker player is expected to score ~0.3%
+pub const MAX_RATING_DIFF: u16 = 1023;
+
+// 10^(2^i / 400), for every bit of `MAX_RATING_DIFF`
+const POWERS_OF_TEN: [FixedU128; 10] = [
+    FixedU128::from_inner(1_005_773_063_001_738_243),
+    FixedU128::from_inner(1_011_579_454_259_898_524),
+    FixedU128::from_inner(1_023_292_992_280_754_131),
+    FixedU128::from_inner(1_047_128_548_050_899_533),
+    FixedU128::from_inner(1_096_478_196_143_185_013),
+    FixedU128::from_inner(1_202_264_434_617_412_906),
+    FixedU128::from_inner(1_445_439_770_745_927_512),
+    FixedU128::from_inner(2_089_296_130_854_039_483),
+    FixedU128::from_inner(4_365_158_322_401_659_675),
+    FixedU128::from_inner(19_054_607_179_632_471_827),
+];
+
+/// Expected score of a player against an opponent, between 0 and 1.
+///
+/// `expected_score(a, b) + expected_score(b, a)` is always exactly 1.
+pub fn expected_score(rating: u16, opponent_rating: u16) -> FixedU128 {
+    let diff = rating.abs_diff(opponent_rating).min(MAX_RATING_DIFF);
+    let power = POWERS_OF_TEN
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| diff & (1 << i) != 0)
+        .fold(WIN, |acc, (_, power)| acc * *power);
+
+    // the stronger player is expected to score 10^(diff / 400) times more than the weaker one
+    let weaker = WIN / (WIN + power);
+    if rating >= opponent_rating {
+        WIN - weaker
+    } else {
+        weaker
+    }
+}
+
+/// Ratings of two players after a match, where `score` is the score of the first player
+/// (`WIN`, `DRAW` or `LOSS`) and `k` is the Elo constant.
+///
+/// Whatever the first player gains, the second player loses, unless a rating hits the bounds
+/// of `u16`, in which case it saturates.
+pub fn new_ratings(k: u16, rating_1: u16, rating_2: u16, score: FixedU128) -> (u16, u16) {
+    let expected = expected_score(rating_1, rating_2);
+
+    // k * (score - expected), rounded half away from zero
+    let div = FixedU128::DIV as i128;
+    let delta = k as i128 * (score.into_inner() as i128 - expected.into_inner() as i128);
+    let delta = if delta >= 0 {
+        (delta + div / 2) / div
+    } else {
+        -((-delta + div / 2) / div)
+    };
+
+    let apply = |rating: u16, delta: i128| -> u16 {
+        (rating as i128 + delta).clamp(0, u16::MAX as i128) as u16
+    };
+    (apply(rating_1, delta), apply(rating_2, -delta))
+}
+
+/// Classic Elo, a single `u16` rating per player
+pub struct Elo;
+
+impl<BlockNumber> RatingSystem<BlockNumber> for Elo {
+    type State = u16;
+
+    fn new_state(rating: u16) -> u16 {
+        rating
+    }
+
+    fn rating(state: &u16) -> u16 {
+        *state
+    }
+
+    // each player moves according to their own constant
+    fn update(
+        k_1: u16,
+        k_2: u16,
+        state_1: &u16,
+        state_2: &u16,
+        score: FixedU128,
+        _now: BlockNumber,
+    ) -> (u16, u16) {
+        (
+            new_ratings(k_1, *state_1, *state_2, score).0,
+            new_ratings(k_2, *state_1, *state_2, score).1,
+        )
+    }
+}
@@ -0,0 +1,293 @@
+//! Deterministic Glicko-2 rating updates.
+//!
+//! Next to their rating, players have a rating deviation and a volatility. Deviations shrink as
+//! players play, so new accounts converge quickly, and grow back with every rating period spent
+//! without playing, so ratings of inactive accounts become more uncertain. Every match is rated
+//! on its own, as a rating period with a single game.
+//! See http://www.glicko.net/glicko/glicko2.pdf
+//!
+//! Like `elo`, everything is computed with fixed-point arithmetic, including `exp`, `ln` and
+//! `sqrt`.
+
+use crate::{elo, rating::RatingSystem};
+use frame_support::{pallet_prelude::*, Parameter};
+use sp_runtime::{
+    traits::{AtLeast32BitUnsigned, IntegerSquareRoot, Saturating, UniqueSaturatedInto},
+    FixedI128, FixedPointNumber, FixedU128,
+};
+use sp_std::marker::PhantomData;
+
+/// Deviation of a player that never played, deviations of inactive players grow up to this
+pub const MAX_DEVIATION: u16 = 350;
+/// Deviations never shrink below this, so ratings keep moving
+pub const MIN_DEVIATION: u16 = 30;
+/// Volatility of a player that never played
+pub const INITIAL_VOLATILITY: FixedU128 = FixedU128::from_inner(60_000_000_000_000_000);
+/// Volatilities are kept between `MIN_VOLATILITY` and `MAX_VOLATILITY`
+pub const MIN_VOLATILITY: FixedU128 = FixedU128::from_inner(10_000_000_000_000_000);
+pub const MAX_VOLATILITY: FixedU128 = FixedU128::from_inner(100_000_000_000_000_000);
+
+const ZERO: FixedI128 = FixedI128::from_inner(0);
+const ONE: FixedI128 = FixedI128::from_inner(FixedI128::DIV);
+const TWO: FixedI128 = FixedI128::from_inner(2 * FixedI128::DIV);
+const LN_2: FixedI128 = FixedI128::from_inner(693_147_180_559_945_309);
+const PI_SQUARED: FixedI128 = FixedI128::from_inner(9_869_604_401_089_358_619);
+// 400 / ln(10), converts ratings and deviations to the Glicko-2 scale
+const SCALE: FixedI128 = FixedI128::from_inner(173_717_792_761_300_731_060);
+// system constant, constrains the change in volatility over time
+const TAU: FixedI128 = FixedI128::from_inner(FixedI128::DIV / 2);
+// convergence tolerance of the volatility iteration
+const EPSILON: FixedI128 = FixedI128::from_inner(1_000_000_000_000);
+// bounds every loop, whether it converged or not
+const MAX_ITERATIONS: u32 = 100;
+
+/// Glicko-2 state of a player
+#[derive(Clone, Debug, Encode, Decode, TypeInfo, MaxEncodedLen, PartialEq, Eq)]
+pub struct Glicko2State<BlockNumber> {
+    pub rating: u16,
+    // rating deviation, in rating points
+    pub deviation: u16,
+    pub volatility: FixedU128,
+    // block of the last rated match
+    pub last_played: BlockNumber,
+}
+
+/// Glicko-2, with rating periods of `RatingPeriod` blocks
+pub struct Glicko2<RatingPeriod>(PhantomData<RatingPeriod>);
+
+impl<BlockNumber, RatingPeriod> RatingSystem<BlockNumber> for Glicko2<RatingPeriod>
+where
+    BlockNumber: Parameter + AtLeast32BitUnsigned + Copy,
+    RatingPeriod: Get<BlockNumber>,
+{
+    type State = Glicko2State<BlockNumber>;
+
+    fn new_state(rating: u16) -> Self::State {
+        Glicko2State {
+            rating,
+            deviation: MAX_DEVIATION,
+            volatility: INITIAL_VOLATILITY,
+            last_played: BlockNumber::zero(),
+        }
+    }
+
+    fn rating(state: &Self::State) -> u16 {
+        state.rating
+    }
+
+    // `k` is specific to Elo, Glicko-2 scales rating changes by the deviations instead
+    fn update(
+        _k_1: u16,
+        _k_2: u16,
+        state_1: &Self::State,
+        state_2: &Self::State,
+        score: FixedU128,
+        now: BlockNumber,
+    ) -> (Self::State, Self::State) {
+        let periods = |state: &Self::State| -> u64 {
+            let period = RatingPeriod::get();
+            if period.is_zero() {
+                return 0;
+            }
+            (now.saturating_sub(state.last_played) / period).unique_saturated_into()
+        };
+        let player_1 = Player::new(state_1, periods(state_1));
+        let player_2 = Player::new(state_2, periods(state_2));
+
+        let score = FixedI128::from_inner(score.into_inner() as i128);
+        (
+            player_1.play(&player_2, score).into_state(now),
+            player_2.play(&player_1, ONE - score).into_state(now),
+        )
+    }
+}
+
+// a player on the Glicko-2 scale
+struct Player {
+    mu: FixedI128,
+    phi: FixedI128,
+    sigma: FixedI128,
+}
+
+impl Player {
+    fn new<BlockNumber>(state: &Glicko2State<BlockNumber>, idle_periods: u64) -> Self {
+        let rating = FixedI128::saturating_from_integer(state.rating);
+        let deviation = state.deviation.clamp(MIN_DEVIATION, MAX_DEVIATION);
+        let phi = FixedI128::saturating_from_integer(deviation) / SCALE;
+        let sigma = state.volatility.clamp(MIN_VOLATILITY, MAX_VOLATILITY);
+        let sigma = FixedI128::from_inner(sigma.into_inner() as i128);
+
+        // the deviation grows with every rating period spent without playing
+        let max_phi = FixedI128::saturating_from_integer(MAX_DEVIATION) / SCALE;
+        let phi_squared = (phi * phi)
+            .saturating_add(
+                (sigma * sigma).saturating_mul(FixedI128::saturating_from_integer(idle_periods)),
+            )
+            .min(max_phi * max_phi);
+
+        Player {
+            mu: (rating - FixedI128::saturating_from_integer(1500)) / SCALE,
+            phi: sqrt(phi_squared),
+            sigma,
+        }
+    }
+
+    // steps 3 to 7 of the Glicko-2 algorithm, with `opponent` as the only game of the period
+    fn play(&self, opponent: &Player, score: FixedI128) -> Player {
+        // rating differences are capped as in `elo`
+        let max_diff = FixedI128::saturating_from_integer(elo::MAX_RATING_DIFF) / SCALE;
+        let diff = (self.mu - opponent.mu).clamp(ZERO - max_diff, max_diff);
+
+        let g = ONE
+            / sqrt(
+                ONE + FixedI128::saturating_from_integer(3) * opponent.phi * opponent.phi
+                    / PI_SQUARED,
+            );
+        let expected = ONE / (ONE + exp(ZERO - g * diff));
+        // estimated variance of the rating, based on the game outcome only
+        let v = ONE / (g * g * expected * (ONE - expected));
+        // estimated improvement in rating
+        let delta = v * g * (score - expected);
+
+        let sigma = self.volatility(delta, v);
+        let phi_star = sqrt(self.phi * self.phi + sigma * sigma);
+        let phi = ONE / sqrt(ONE / (phi_star * phi_star) + ONE / v);
+        Player {
+            mu: self.mu + phi * phi * g * (score - expected),
+            phi,
+            sigma,
+        }
+    }
+
+    // step 5, the new volatility, found with the Illinois algorithm
+    fn volatility(&self, delta: FixedI128, v: FixedI128) -> FixedI128 {
+        let phi_squared = self.phi * self.phi;
+        let delta_squared = delta * delta;
+        let alpha = ln(self.sigma * self.sigma);
+        let f = |x: FixedI128| -> FixedI128 {
+            let exp_x = exp(x);
+            let denominator = phi_squared + v + exp_x;
+            exp_x * (delta_squared - phi_squared - v - exp_x) / (TWO * denominator * denominator)
+                - (x - alpha) / (TAU * TAU)
+        };
+
+        let mut a = alpha;
+        let mut b = if delta_squared > phi_squared + v {
+            ln(delta_squared - phi_squared - v)
+        } else {
+            let mut k = 1;
+            while k < MAX_ITERATIONS
+                && f(alpha - FixedI128::saturating_from_integer(k) * TAU) < ZERO
+            {
+                k += 1;
+            }
+            alpha - FixedI128::saturating_from_integer(k) * TAU
+        };
+
+        let (mut f_a, mut f_b) = (f(a), f(b));
+        let mut iterations = 0;
+        while (b - a > EPSILON || a - b > EPSILON) && f_b != f_a && iterations < MAX_ITERATIONS {
+            let c = a + (a - b) * f_a / (f_b - f_a);
+            let f_c = f(c);
+            if f_c == ZERO || f_b == ZERO || (f_c < ZERO) != (f_b < ZERO) {
+                a = b;
+                f_a = f_b;
+            } else {
+                f_a = f_a / TWO;
+            }
+            b = c;
+            f_b = f_c;
+            iterations += 1;
+        }
+
+        let sigma = exp(a / TWO);
+        let (min, max) = (
+            MIN_VOLATILITY.into_inner() as i128,
+            MAX_VOLATILITY.into_inner() as i128,
+        );
+        FixedI128::from_inner(sigma.into_inner().clamp(min, max))
+    }
+
+    // step 8, back to the Glicko scale
+    fn into_state<BlockNumber>(self, now: BlockNumber) -> Glicko2State<BlockNumber> {
+        let rating = round(self.mu * SCALE) + 1500;
+        let deviation = round(self.phi * SCALE);
+        Glicko2State {
+            rating: rating.clamp(0, u16::MAX as i128) as u16,
+            deviation: deviation.clamp(MIN_DEVIATION as i128, MAX_DEVIATION as i128) as u16,
+            volatility: FixedU128::from_inner(self.sigma.into_inner() as u128),
+            last_played: now,
+        }
+    }
+}
+
+// rounded half away from zero
+fn round(x: FixedI128) -> i128 {
+    let (inner, div) = (x.into_inner(), FixedI128::DIV);
+    if inner >= 0 {
+        (inner + div / 2) / div
+    } else {
+        -((-inner + div / 2) / div)
+    }
+}
+
+fn sqrt(x: FixedI128) -> FixedI128 {
+    let inner = x.into_inner().max(0) as u128;
+    let root = inner.saturating_mul(FixedI128::DIV as u128).integer_sqrt();
+    FixedI128::from_inner(root.min(i128::MAX as u128) as i128)
+}
+
+fn exp(x: FixedI128) -> FixedI128 {
+    // x = n * ln(2) + r, with |r| <= ln(2) / 2
+    let (inner, ln_2) = (x.into_inner(), LN_2.into_inner());
+    let n = if inner >= 0 {
+        (inner + ln_2 / 2) / ln_2
+    } else {
+        -((-inner + ln_2 / 2) / ln_2)
+    };
+    let r = inner - n * ln_2;
+
+    // Taylor series of e^r
+    let div = FixedI128::DIV;
+    let (mut sum, mut term, mut i) = (div, div, 1);
+    while term != 0 && i < MAX_ITERATIONS as i128 {
+        term = term * r / div / i;
+        sum += term;
+        i += 1;
+    }
+
+    // e^x = e^r * 2^n
+    if n >= 0 {
+        let power = 1i128.checked_shl(n.min(127) as u32).unwrap_or(i128::MAX);
+        FixedI128::from_inner(sum.saturating_mul(power))
+    } else {
+        FixedI128::from_inner(sum >> (-n).min(127))
+    }
+}
+
+// only defined for positive values, anything else returns the logarithm of the smallest one
+fn ln(x: FixedI128) -> FixedI128 {
+    // x = m * 2^n, with 1 <= m < 2
+    let div = FixedI128::DIV;
+    let (mut m, mut n) = (x.into_inner().max(1), 0i128);
+    while m >= 2 * div {
+        m >>= 1;
+        n += 1;
+    }
+    while m < div {
+        m <<= 1;
+        n -= 1;
+    }
+
+    // ln(m) = 2 * atanh(z), with z = (m - 1) / (m + 1) < 1/3
+    let z = (m - div) * div / (m + div);
+    let z_squared = z * z / div;
+    let (mut sum, mut term, mut k) = (0, z, 1);
+    while term != 0 {
+        sum += term / k;
+        term = term * z_squared / div;
+        k += 2;
+    }
+
+    FixedI128::from_inner(n * LN_2.into_inner() + 2 * sum)
+}
@@ -16,20 +16,31 @@ mod benchmarking;
 pub mod weights;
 pub use weights::*;
 
+pub mod elo;
+pub mod glicko2;
+pub mod migrations;
+pub mod rating;
+pub mod runtime_api;
+pub mod stakes;
+
+#[cfg(feature = "std")]
+pub mod pgn;
+
 #[frame_support::pallet]
 pub mod pallet {
-    use crate::WeightInfo;
-    use cozy_chess::{Board, Color, GameStatus, Move};
+    use crate::{elo, migrations, rating::RatingSystem, stakes::Stakes, WeightInfo};
+    use cozy_chess::{Board, Color, GameStatus, Move, Piece, Square};
     use frame_support::{
         pallet_prelude::{DispatchResult, ValueQuery, *},
+        serde::{Deserialize, Serialize},
         sp_runtime::{
-            traits::{AccountIdConversion, Hash},
-            FixedPointOperand, Percent, Saturating,
+            traits::{AccountIdConversion, Hash, Zero},
+            FixedPointOperand, FixedU128, Percent, Saturating,
         },
         traits::{
             fungibles::{Inspect, Mutate},
-            tokens::{Balance, Preservation},
-            BuildGenesisConfig,
+            tokens::Balance,
+            BuildGenesisConfig, Randomness,
         },
         PalletId,
     };
@@ -45,6 +56,8 @@ pub mod pallet {
     pub type BalanceOf<T> =
         <<T as Config>::Assets as Inspect<<T as frame_system::Config>::AccountId>>::Balance;
     type AccountIdOf<T> = <T as frame_system::Config>::AccountId;
+    pub type RatingStateOf<T> =
+        <<T as Config>::RatingSystem as RatingSystem<BlockNumberFor<T>>>::State;
 
     pub struct DefaultElo;
     impl Get<u16> for DefaultElo {
@@ -53,12 +66,104 @@ pub mod pallet {
         }
     }
 
-    #[derive(Clone, Debug, Encode, Decode, TypeInfo, PartialEq)]
+    #[derive(Clone, Debug, Encode, Decode, TypeInfo, PartialEq, Serialize, Deserialize)]
+    #[serde(crate = "frame_support::serde")]
     pub enum MatchStyle {
         Bullet, // 1 minute
         Blitz,  // 5 minutes
         Rapid,  // 15 minutes
         Daily,  // 1 day
+        // all values in blocks, bounded by `MinTimeControl` and `MaxTimeControl`
+        Custom {
+            per_move: u32,
+            base: u32,
+            increment: u32,
+        },
+    }
+
+    impl MatchStyle {
+        /// Presets, each one with its own rating
+        pub const RATED: [MatchStyle; 4] = [
+            MatchStyle::Bullet,
+            MatchStyle::Blitz,
+            MatchStyle::Rapid,
+            MatchStyle::Daily,
+        ];
+
+        // custom time controls have no rating of their own, so their matches are unrated
+        pub fn is_rated(&self) -> bool {
+            !matches!(self, MatchStyle::Custom { .. })
+        }
+    }
+
+    /// Time control of a match, in blocks
+    #[derive(Clone, Debug, Encode, Decode, TypeInfo, PartialEq)]
+    pub struct TimeControl<BlockNumber> {
+        // maximum time spent on a single move
+        pub per_move: BlockNumber,
+        // time bank each player starts with
+        pub base: BlockNumber,
+        // time added to the clock of whoever moves
+        pub increment: BlockNumber,
+    }
+
+    impl<BlockNumber: Copy> TimeControl<BlockNumber> {
+        // a time bank for the whole match, plus an increment after every move. A single move can't
+        // take longer than the period
+        pub fn bank(period: BlockNumber, increment: BlockNumber) -> Self {
+            TimeControl {
+                per_move: period,
+                base: period,
+                increment,
+            }
+        }
+
+        // a fixed deadline for every move, the clock gets back at least the whole period on every move
+        pub fn per_move(period: BlockNumber) -> Self {
+            TimeControl {
+                per_move: period,
+                base: period,
+                increment: period,
+            }
+        }
+    }
+
+    /// Parameters adjustable by `AdminOrigin`, defaults are taken from `Config`
+    #[derive(Clone, Debug, Encode, Decode, TypeInfo, PartialEq)]
+    pub struct Parameters<BlockNumber> {
+        pub bullet_period: BlockNumber,
+        pub blitz_period: BlockNumber,
+        pub rapid_period: BlockNumber,
+        pub daily_period: BlockNumber,
+        // percentage of the prize paid to janitors
+        pub incentive_share: u8,
+        // Elo constant
+        pub k: u16,
+    }
+
+    /// Results of every finished match of a player, rated or not
+    #[derive(Clone, Debug, Default, Encode, Decode, TypeInfo, MaxEncodedLen, PartialEq)]
+    pub struct Statistics {
+        pub wins: u32,
+        pub losses: u32,
+        pub draws: u32,
+    }
+
+    impl Statistics {
+        pub fn games_played(&self) -> u32 {
+            self.wins
+                .saturating_add(self.losses)
+                .saturating_add(self.draws)
+        }
+    }
+
+    /// Rating change of a player after a rated match
+    #[derive(Clone, Debug, Encode, Decode, TypeInfo, MaxEncodedLen, PartialEq)]
+    pub struct RatingChange<BlockNumber, Hash> {
+        pub block: BlockNumber,
+        pub match_id: Hash,
+        pub old_rating: u16,
+        pub new_rating: u16,
     }
 
     #[derive(Clone, Debug, Encode, Decode, TypeInfo, PartialEq)]
@@ -67,105 +172,301 @@ pub mod pallet {
         Blacks,
     }
 
+    /// Rules a match is played with
+    #[derive(Clone, Debug, Encode, Decode, TypeInfo, PartialEq)]
+    pub enum Variant {
+        Standard,
+        // Fischer Random, the start position is drawn when the opponent joins
+        Chess960,
+    }
+
+    /// Color the challenger wants to play with
+    #[derive(Clone, Debug, Encode, Decode, TypeInfo, PartialEq)]
+    pub enum ColorPreference {
+        White,
+        Black,
+        // decided by `Config::Randomness` when the match starts
+        Random,
+    }
+
+    /// Optional settings of `create_match`, by default a rated `Standard` match from the usual
+    /// start position, where the challenger plays whites and anyone can join
+    #[derive(Clone, Debug, Encode, Decode, TypeInfo, PartialEq)]
+    pub struct MatchOptions {
+        pub color: ColorPreference,
+        pub variant: Variant,
+        // FEN of a custom start position, only for `Standard` matches
+        pub start_fen: Option<Vec<u8>>,
+        // casual matches don't update ratings
+        pub rated: bool,
+        // bounds of the rating of the opponent, only for preset styles
+        pub min_rating: Option<u16>,
+        pub max_rating: Option<u16>,
+    }
+
+    impl Default for MatchOptions {
+        fn default() -> Self {
+            MatchOptions {
+                color: ColorPreference::White,
+                variant: Variant::Standard,
+                start_fen: None,
+                rated: true,
+                min_rating: None,
+                max_rating: None,
+            }
+        }
+    }
+
+    #[derive(Clone, Debug, Encode, Decode, TypeInfo, PartialEq)]
+    pub enum PromotionPiece {
+        Knight,
+        Bishop,
+        Rook,
+        Queen,
+    }
+
+    impl PromotionPiece {
+        fn from_piece(piece: Piece) -> Option<Self> {
+            match piece {
+                Piece::Knight => Some(PromotionPiece::Knight),
+                Piece::Bishop => Some(PromotionPiece::Bishop),
+                Piece::Rook => Some(PromotionPiece::Rook),
+                Piece::Queen => Some(PromotionPiece::Queen),
+                Piece::Pawn | Piece::King => None,
+            }
+        }
+
+        fn to_piece(&self) -> Piece {
+            match self {
+                PromotionPiece::Knight => Piece::Knight,
+                PromotionPiece::Bishop => Piece::Bishop,
+                PromotionPiece::Rook => Piece::Rook,
+                PromotionPiece::Queen => Piece::Queen,
+            }
+        }
+    }
+
+    /// A move packed into 16 bits:
+    /// origin square (bits 0-5), destination square (bits 6-11) and promotion piece (bits 12-14)
+    #[derive(Clone, Copy, Debug, Encode, Decode, TypeInfo, MaxEncodedLen, PartialEq)]
+    pub struct CompactMove(pub u16);
+
+    impl CompactMove {
+        pub fn from_move(move_obj: Move) -> Self {
+            let promotion: u16 = match move_obj.promotion.and_then(PromotionPiece::from_piece) {
+                None => 0,
+                Some(PromotionPiece::Knight) => 1,
+                Some(PromotionPiece::Bishop) => 2,
+                Some(PromotionPiece::Rook) => 3,
+                Some(PromotionPiece::Queen) => 4,
+            };
+            CompactMove((move_obj.from as u16) | ((move_obj.to as u16) << 6) | (promotion << 12))
+        }
+
+        pub fn to_move(&self) -> Option<Move> {
+            let promotion = match self.0 >> 12 {
+                0 => None,
+                1 => Some(PromotionPiece::Knight),
+                2 => Some(PromotionPiece::Bishop),
+                3 => Some(PromotionPiece::Rook),
+                4 => Some(PromotionPiece::Queen),
+                _ => return None,
+            };
+            Some(Move {
+                from: Square::ALL[(self.0 & 0x3f) as usize],
+                to: Square::ALL[((self.0 >> 6) & 0x3f) as usize],
+                promotion: promotion.map(|p| p.to_piece()),
+            })
+        }
+    }
+
     #[derive(Clone, Debug, Encode, Decode, TypeInfo, PartialEq)]
-    pub enum MatchState {
+    pub enum MatchState<AccountId> {
         AwaitingOpponent,
         OnGoing(NextMove),
-        Won,
-        Drawn,
+        // winner and how the match ended
+        Won(AccountId, Termination),
+        Drawn(Termination),
+    }
+
+    #[derive(Clone, Debug, Encode, Decode, TypeInfo, PartialEq)]
+    pub enum Termination {
+        Checkmate,
+        Resignation,
+        Timeout,
+        Abandonment,
+        Stalemate,
+        InsufficientMaterial,
+        Agreement,
+        Repetition,
+        FiftyMoveRule,
     }
 
     #[derive(Clone, Debug, Encode, Decode, TypeInfo, PartialEq)]
     #[scale_info(skip_type_params(T))]
     pub struct Match<T: Config> {
         pub challenger: T::AccountId,
-        pub opponent: T::AccountId,
+        // None for open challenges, bound to whoever joins the match
+        pub opponent: Option<T::AccountId>,
         // no need for BoundedVec, we only modify this internally (and safely)
         // introducing an extra runtime constant would be overkill
         pub board: Vec<u8>,
-        pub state: MatchState,
+        pub state: MatchState<T::AccountId>,
         pub nonce: u128,
         pub style: MatchStyle,
         pub last_move: BlockNumberFor<T>,
         pub start: BlockNumberFor<T>,
         pub bet_asset_id: AssetIdOf<T>,
         pub bet_amount: T::AssetBalance,
+        // pending draw offer, made by one of the players
+        pub draw_offer: Option<T::AccountId>,
+        // rating range the opponent must be within in order to join (inclusive)
+        pub min_rating: Option<u16>,
+        pub max_rating: Option<u16>,
+        pub color: ColorPreference,
+        // None until the match starts, when colors are assigned according to `color`
+        pub white: Option<T::AccountId>,
+        pub black: Option<T::AccountId>,
+        pub variant: Variant,
+        // FEN of the position the match started from
+        pub start_board: Vec<u8>,
+        // casual matches don't update `PlayerRatings`
+        pub rated: bool,
+        // remaining time of each player, in blocks, as of the last move
+        pub white_clock: BlockNumberFor<T>,
+        pub black_clock: BlockNumberFor<T>,
+        // parameters in force when the match was created
+        pub time_control: TimeControl<BlockNumberFor<T>>,
+        pub incentive_share: u8,
+        pub k: u16,
+    }
+
+    /// Compact record of a match that is over, kept in `FinishedMatches`
+    #[derive(Clone, Debug, Encode, Decode, TypeInfo, PartialEq)]
+    #[scale_info(skip_type_params(T))]
+    pub struct FinishedMatch<T: Config> {
+        pub challenger: T::AccountId,
+        pub opponent: T::AccountId,
+        // None in case of draws
+        pub winner: Option<T::AccountId>,
+        pub termination: Termination,
+        pub board: Vec<u8>,
+        pub end: BlockNumberFor<T>,
+        pub bet_asset_id: AssetIdOf<T>,
+        // amount paid to the winner, or refunded to each player in case of draws
+        pub payout: T::AssetBalance,
+    }
+
+    /// A player waiting in the matchmaking queue, whose stake is already in escrow
+    #[derive(Clone, Debug, Encode, Decode, TypeInfo, PartialEq)]
+    #[scale_info(skip_type_params(T))]
+    pub struct QueueEntry<T: Config> {
+        pub player: T::AccountId,
+        pub style: MatchStyle,
+        pub bet_asset_id: AssetIdOf<T>,
+        pub bet_amount: T::AssetBalance,
+        // rating (for `style`) at the moment the player entered the queue
+        pub rating: u16,
+    }
+
+    impl<T: Config> QueueEntry<T> {
+        // players can only be paired when they agree on style and stake
+        fn is_compatible_with(&self, other: &QueueEntry<T>) -> bool {
+            (self.style == other.style)
+                & (self.bet_asset_id == other.bet_asset_id)
+                & (self.bet_amount == other.bet_amount)
+        }
     }
 
     impl<T: Config> Match<T> {
-        fn challenger_bet(&self) -> DispatchResult {
-            if !T::Assets::asset_exists(self.bet_asset_id.clone()) {
-                return Err(Error::<T>::BetDoesNotExist.into());
-            }
+        // the opponent is always known once the match has started
+        fn opponent(&self) -> sp_std::result::Result<T::AccountId, Error<T>> {
+            self.opponent
+                .clone()
+                .ok_or(Error::<T>::StillAwaitingOpponent)
+        }
 
-            // bet must cover janitor incentives
-            if Percent::from_percent(T::IncentiveShare::get())
-                * self.bet_amount.saturating_add(self.bet_amount)
-                < T::Assets::minimum_balance(self.bet_asset_id.clone())
-            {
-                return Err(Error::<T>::BetTooLow.into());
-            }
+        // colors are always assigned once the match has started
+        fn white(&self) -> sp_std::result::Result<T::AccountId, Error<T>> {
+            self.white.clone().ok_or(Error::<T>::StillAwaitingOpponent)
+        }
 
-            T::Assets::transfer(
+        fn black(&self) -> sp_std::result::Result<T::AccountId, Error<T>> {
+            self.black.clone().ok_or(Error::<T>::StillAwaitingOpponent)
+        }
+
+        fn is_player(&self, who: &T::AccountId) -> bool {
+            (*who == self.challenger) | (self.opponent.as_ref() == Some(who))
+        }
+
+        // friendly matches are played without any stake, so no funds are ever moved
+        fn is_friendly(&self) -> bool {
+            self.bet_amount.is_zero()
+        }
+
+        fn challenger_bet(&self) -> DispatchResult {
+            Pallet::<T>::ensure_valid_bet(
                 self.bet_asset_id.clone(),
-                &self.challenger,
-                &T::pallet_account(),
                 self.bet_amount,
-                Preservation::Expendable,
+                self.incentive_share,
             )?;
-            Ok(())
+            if self.is_friendly() {
+                return Ok(());
+            }
+
+            Pallet::<T>::hold_stake(self.bet_asset_id.clone(), &self.challenger, self.bet_amount)
         }
 
         fn opponent_bet(&self) -> DispatchResult {
-            T::Assets::transfer(
+            if self.is_friendly() {
+                return Ok(());
+            }
+            Pallet::<T>::hold_stake(
                 self.bet_asset_id.clone(),
-                &self.opponent,
-                &T::pallet_account(),
+                &self.opponent()?,
                 self.bet_amount,
-                Preservation::Expendable,
-            )?;
-            Ok(())
+            )
         }
 
         fn abort_bet(&self) -> DispatchResult {
-            T::Assets::transfer(
-                self.bet_asset_id.clone(),
-                &T::pallet_account(),
-                &self.challenger,
-                self.bet_amount,
-                Preservation::Expendable,
-            )?;
-            Ok(())
+            if self.is_friendly() {
+                return Ok(());
+            }
+            Pallet::<T>::release_stake(self.bet_asset_id.clone(), &self.challenger, self.bet_amount)
         }
 
         fn refund_bets(&self) -> DispatchResult {
-            T::Assets::transfer(
+            if self.is_friendly() {
+                return Ok(());
+            }
+            Pallet::<T>::release_stake(
                 self.bet_asset_id.clone(),
-                &T::pallet_account(),
                 &self.challenger,
                 self.bet_amount,
-                Preservation::Expendable,
             )?;
-            T::Assets::transfer(
+            Pallet::<T>::release_stake(
                 self.bet_asset_id.clone(),
-                &T::pallet_account(),
-                &self.opponent,
+                &self.opponent()?,
                 self.bet_amount,
-                Preservation::Expendable,
-            )?;
-            Ok(())
+            )
+        }
+
+        // the player who didn't win
+        fn loser(&self, winner: &T::AccountId) -> sp_std::result::Result<T::AccountId, Error<T>> {
+            if *winner == self.challenger {
+                self.opponent()
+            } else {
+                Ok(self.challenger.clone())
+            }
         }
 
         fn win_bet(&self, winner: &T::AccountId) -> DispatchResult {
-            let win_amount = self.bet_amount.saturating_add(self.bet_amount);
-            T::Assets::transfer(
-                self.bet_asset_id.clone(),
-                &T::pallet_account(),
-                winner,
-                win_amount,
-                Preservation::Expendable,
-            )?;
-            Ok(())
+            if self.is_friendly() {
+                return Ok(());
+            }
+            let asset_id = self.bet_asset_id.clone();
+            Pallet::<T>::release_stake(asset_id.clone(), winner, self.bet_amount)?;
+            Pallet::<T>::transfer_stake(asset_id, &self.loser(winner)?, winner, self.bet_amount)
         }
 
         fn clear_abandoned_bet(
@@ -173,33 +474,46 @@ pub mod pallet {
             winner: &T::AccountId,
             janitor: &T::AccountId,
         ) -> DispatchResult {
-            let (janitor_incentive, actual_prize) = self.janitor_incentive();
-            T::Assets::transfer(
-                self.bet_asset_id.clone(),
-                &T::pallet_account(),
-                janitor,
-                janitor_incentive,
-                Preservation::Expendable,
-            )?;
-            T::Assets::transfer(
-                self.bet_asset_id.clone(),
-                &T::pallet_account(),
+            // there is no incentive to pay out of friendly matches
+            if self.is_friendly() {
+                return Ok(());
+            }
+
+            // the incentive comes out of the loser's stake first, then out of the winner's
+            let (janitor_incentive, _) = self.janitor_incentive();
+            let from_loser = janitor_incentive.min(self.bet_amount);
+            let from_winner = janitor_incentive.saturating_sub(from_loser);
+
+            let asset_id = self.bet_asset_id.clone();
+            let loser = self.loser(winner)?;
+            Pallet::<T>::transfer_stake(asset_id.clone(), &loser, janitor, from_loser)?;
+            Pallet::<T>::transfer_stake(
+                asset_id.clone(),
+                &loser,
                 winner,
-                actual_prize,
-                Preservation::Expendable,
+                self.bet_amount.saturating_sub(from_loser),
             )?;
-            Ok(())
+            Pallet::<T>::transfer_stake(asset_id.clone(), winner, janitor, from_winner)?;
+            Pallet::<T>::release_stake(
+                asset_id,
+                winner,
+                self.bet_amount.saturating_sub(from_winner),
+            )
         }
 
         pub fn janitor_incentive(&self) -> (BalanceOf<T>, BalanceOf<T>) {
             let winner_prize = self.bet_amount.saturating_add(self.bet_amount);
-            let janitor_incentive = Percent::from_percent(T::IncentiveShare::get()) * winner_prize;
+            let janitor_incentive = Percent::from_percent(self.incentive_share) * winner_prize;
             let actual_prize = winner_prize.saturating_sub(janitor_incentive);
             (janitor_incentive, actual_prize)
         }
     }
 
+    /// The current storage version.
+    pub const STORAGE_VERSION: StorageVersion = StorageVersion::new(5);
+
     #[pallet::pallet]
+    #[pallet::storage_version(STORAGE_VERSION)]
     #[pallet::without_storage_info]
     pub struct Pallet<T>(_);
 
@@ -216,25 +530,164 @@ pub mod pallet {
     pub(super) type PlayerMatches<T: Config> =
         StorageDoubleMap<_, Twox64Concat, T::AccountId, Twox64Concat, T::Hash, (), OptionQuery>;
 
+    /// Index of open challenges (matches without a designated opponent) by style and bet asset
+    #[pallet::storage]
+    #[pallet::getter(fn open_challenges)]
+    pub(super) type OpenChallenges<T: Config> = StorageNMap<
+        _,
+        (
+            NMapKey<Twox64Concat, MatchStyle>,
+            NMapKey<Twox64Concat, AssetIdOf<T>>,
+            NMapKey<Twox64Concat, T::Hash>,
+        ),
+        (),
+        OptionQuery,
+    >;
+
     #[pallet::storage]
     #[pallet::getter(fn chess_match_id_from_nonce)]
     pub(super) type MatchIdFromNonce<T: Config> = StorageMap<_, Twox64Concat, u128, T::Hash>;
 
     #[pallet::storage]
-    #[pallet::getter(fn player_elo)]
-    pub(super) type PlayerElo<T: Config> =
-        StorageMap<_, Twox64Concat, T::AccountId, u16, ValueQuery, DefaultElo>;
+    #[pallet::getter(fn position_history)]
+    pub(super) type PositionHistory<T: Config> =
+        StorageMap<_, Twox64Concat, T::Hash, BoundedVec<u64, T::MaxPositionHistory>, ValueQuery>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn match_moves)]
+    pub(super) type MatchMoves<T: Config> =
+        StorageMap<_, Twox64Concat, T::Hash, BoundedVec<CompactMove, T::MaxMoves>, ValueQuery>;
+
+    // matches that outlasted `MaxMoves`, whose move history stops at its first `MaxMoves` moves
+    #[pallet::storage]
+    #[pallet::getter(fn move_history_truncated)]
+    pub(super) type MoveHistoryTruncated<T: Config> =
+        StorageMap<_, Twox64Concat, T::Hash, bool, ValueQuery>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn finished_matches)]
+    pub(super) type FinishedMatches<T: Config> =
+        StorageMap<_, Twox64Concat, T::Hash, FinishedMatch<T>>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn player_finished_matches)]
+    pub(super) type PlayerFinishedMatches<T: Config> =
+        StorageDoubleMap<_, Twox64Concat, T::AccountId, Twox64Concat, T::Hash, (), OptionQuery>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn matchmaking_queue)]
+    pub(super) type MatchmakingQueue<T: Config> =
+        StorageValue<_, BoundedVec<QueueEntry<T>, T::MaxQueueLength>, ValueQuery>;
+
+    #[pallet::type_value]
+    pub fn DefaultParameters<T: Config>() -> Parameters<BlockNumberFor<T>> {
+        Parameters {
+            bullet_period: T::BulletPeriod::get(),
+            blitz_period: T::BlitzPeriod::get(),
+            rapid_period: T::RapidPeriod::get(),
+            daily_period: T::DailyPeriod::get(),
+            incentive_share: T::IncentiveShare::get(),
+            k: T::K::get(),
+        }
+    }
+
+    #[pallet::storage]
+    #[pallet::getter(fn chess_parameters)]
+    pub(super) type ChessParameters<T: Config> =
+        StorageValue<_, Parameters<BlockNumberFor<T>>, ValueQuery, DefaultParameters<T>>;
+
+    #[pallet::type_value]
+    pub fn DefaultRating<T: Config>() -> RatingStateOf<T> {
+        T::RatingSystem::new_state(DefaultElo::get())
+    }
+
+    // ratings from before v3 may still be in `migrations::v3::PlayerElo`, read them with
+    // `Pallet::rating_state`
+    #[pallet::storage]
+    #[pallet::getter(fn player_rating)]
+    pub(super) type PlayerRatings<T: Config> = StorageDoubleMap<
+        _,
+        Twox64Concat,
+        T::AccountId,
+        Twox64Concat,
+        MatchStyle,
+        RatingStateOf<T>,
+        ValueQuery,
+        DefaultRating<T>,
+    >;
+
+    #[pallet::storage]
+    #[pallet::getter(fn player_statistics)]
+    pub(super) type PlayerStatistics<T: Config> =
+        StorageMap<_, Twox64Concat, T::AccountId, Statistics, ValueQuery>;
+
+    // number of rated matches played per style, ratings are provisional until `ProvisionalGames`
+    #[pallet::storage]
+    #[pallet::getter(fn rated_games)]
+    pub(super) type RatedGames<T: Config> =
+        StorageDoubleMap<_, Twox64Concat, T::AccountId, Twox64Concat, MatchStyle, u32, ValueQuery>;
+
+    // ratings that are established regardless of `RatedGames`, i.e. set at genesis or migrated
+    #[pallet::storage]
+    #[pallet::getter(fn established_rating)]
+    pub(super) type EstablishedRatings<T: Config> =
+        StorageDoubleMap<_, Twox64Concat, T::AccountId, Twox64Concat, MatchStyle, bool, ValueQuery>;
+
+    // latest `MaxRatingHistory` rating changes of every player and style, oldest first
+    #[pallet::storage]
+    #[pallet::getter(fn rating_history)]
+    pub(super) type RatingHistory<T: Config> = StorageDoubleMap<
+        _,
+        Twox64Concat,
+        T::AccountId,
+        Twox64Concat,
+        MatchStyle,
+        BoundedVec<RatingChange<BlockNumberFor<T>, T::Hash>, T::MaxRatingHistory>,
+        ValueQuery,
+    >;
+
+    // top `LeaderboardSize` players of every style and their ratings, highest first
+    #[pallet::storage]
+    #[pallet::getter(fn leaderboard)]
+    pub(super) type Leaderboards<T: Config> = StorageMap<
+        _,
+        Twox64Concat,
+        MatchStyle,
+        BoundedVec<(T::AccountId, u16), T::LeaderboardSize>,
+        ValueQuery,
+    >;
+
+    // every player who can be ranked in a style, keyed by `rank_key` so that iterating a style
+    // goes from the highest rating to the lowest. Ties are ordered by account
+    #[pallet::storage]
+    pub(super) type RankedPlayers<T: Config> = StorageDoubleMap<
+        _,
+        Twox64Concat,
+        MatchStyle,
+        Identity,
+        ([u8; 2], T::AccountId),
+        (),
+        OptionQuery,
+    >;
 
     #[pallet::config]
     pub trait Config: frame_system::Config {
         #[pallet::constant]
         type PalletId: Get<PalletId>;
 
-        /// Elo constant
+        /// Default Elo constant, see `ChessParameters`
         /// See https://metinmediamath.wordpress.com/2013/11/27/how-to-calculate-the-elo-rating-including-example/
         #[pallet::constant]
         type K: Get<u16>;
 
+        /// Number of rated matches a rating stays provisional for
+        #[pallet::constant]
+        type ProvisionalGames: Get<u32>;
+
+        /// Elo constant used for provisional ratings, unless the current one is larger
+        #[pallet::constant]
+        type ProvisionalK: Get<u16>;
+
         type RuntimeEvent: From<Event<Self>> + IsType<<Self as frame_system::Config>::RuntimeEvent>;
         type WeightInfo: WeightInfo;
         type Assets: Inspect<Self::AccountId, Balance = Self::AssetBalance>
@@ -245,20 +698,100 @@ pub mod pallet {
             + MaybeSerializeDeserialize
             + TypeInfo;
 
+        /// Default time bank of each player in `Bullet` matches, in blocks, see `ChessParameters`
         #[pallet::constant]
         type BulletPeriod: Get<BlockNumberFor<Self>>;
 
+        /// Default time bank of each player in `Blitz` matches, in blocks, see `ChessParameters`
         #[pallet::constant]
         type BlitzPeriod: Get<BlockNumberFor<Self>>;
 
+        /// Default time bank of each player in `Rapid` matches, in blocks, see `ChessParameters`
         #[pallet::constant]
         type RapidPeriod: Get<BlockNumberFor<Self>>;
 
+        /// Blocks added to the clock of a player after every move in `Bullet` matches
+        #[pallet::constant]
+        type BulletIncrement: Get<BlockNumberFor<Self>>;
+
+        /// Blocks added to the clock of a player after every move in `Blitz` matches
+        #[pallet::constant]
+        type BlitzIncrement: Get<BlockNumberFor<Self>>;
+
+        /// Blocks added to the clock of a player after every move in `Rapid` matches
+        #[pallet::constant]
+        type RapidIncrement: Get<BlockNumberFor<Self>>;
+
+        /// Default time bank of each player in `Daily` matches, in blocks, see `ChessParameters`
         #[pallet::constant]
         type DailyPeriod: Get<BlockNumberFor<Self>>;
 
+        /// Lower bound of every value of `MatchStyle::Custom`, in blocks (increments excluded)
+        #[pallet::constant]
+        type MinTimeControl: Get<BlockNumberFor<Self>>;
+
+        /// Upper bound of every value of `MatchStyle::Custom`, in blocks
+        #[pallet::constant]
+        type MaxTimeControl: Get<BlockNumberFor<Self>>;
+
+        /// Default percentage of the prize paid to janitors, see `ChessParameters`
         #[pallet::constant]
         type IncentiveShare: Get<u8>;
+
+        /// Maximum number of position hashes kept per match for threefold repetition claims
+        #[pallet::constant]
+        type MaxPositionHistory: Get<u32>;
+
+        /// Maximum number of moves (halfmoves) stored per match
+        #[pallet::constant]
+        type MaxMoves: Get<u32>;
+
+        /// Number of blocks a finished match is kept in `FinishedMatches` before it can be pruned
+        #[pallet::constant]
+        type FinishedMatchRetention: Get<BlockNumberFor<Self>>;
+
+        /// Number of blocks a challenge can wait for an opponent before anyone can clear it
+        #[pallet::constant]
+        type ChallengeExpiry: Get<BlockNumberFor<Self>>;
+
+        /// Maximum number of players waiting in the matchmaking queue
+        #[pallet::constant]
+        type MaxQueueLength: Get<u32>;
+
+        /// Number of rating changes kept per player and style in `RatingHistory`
+        #[pallet::constant]
+        type MaxRatingHistory: Get<u32>;
+
+        /// Number of players ranked in the leaderboard of every style
+        #[pallet::constant]
+        type LeaderboardSize: Get<u32>;
+
+        /// Number of rated matches a player must have played in a style to be ranked
+        #[pallet::constant]
+        type MinLeaderboardGames: Get<u32>;
+
+        /// Source of randomness used to assign colors when the challenger has no preference
+        type Randomness: Randomness<Self::Hash, BlockNumberFor<Self>>;
+
+        /// Origin allowed to change `ChessParameters`
+        type AdminOrigin: EnsureOrigin<Self::RuntimeOrigin>;
+
+        /// Where stakes are kept until matches are settled, `stakes::PalletAccount` or
+        /// `stakes::Holds`.
+        /// Switching from one to the other requires `migrations::v5::MigrateToV5`.
+        type Stakes: Stakes<Self::AccountId, AssetIdOf<Self>, Self::AssetBalance>;
+
+        /// How ratings are computed, `elo::Elo` or `glicko2::Glicko2`.
+        /// Switching systems changes the layout of `PlayerRatings` and requires a migration.
+        type RatingSystem: RatingSystem<BlockNumberFor<Self>>;
+    }
+
+    /// Reasons for the pallet to hold funds, when stakes are kept with `stakes::Holds`
+    #[pallet::composite_enum]
+    pub enum HoldReason {
+        /// Stake of a player in a match (or in the matchmaking queue) that isn't settled yet
+        #[codec(index = 0)]
+        MatchStake,
     }
 
     pub trait ConfigHelper: Config {
@@ -274,17 +807,38 @@ pub mod pallet {
     #[pallet::event]
     #[pallet::generate_deposit(pub(super) fn deposit_event)]
     pub enum Event<T: Config> {
-        MatchCreated(T::AccountId, T::AccountId, T::Hash),
+        MatchCreated(
+            T::AccountId,
+            Option<T::AccountId>,
+            T::Hash,
+            MatchStyle,
+            AssetIdOf<T>,
+            T::AssetBalance,
+            Variant,
+        ),
         MatchAborted(T::Hash),
-        MatchStarted(T::Hash),
-        MoveExecuted(T::Hash, T::AccountId, Vec<u8>),
-        MatchWon(T::Hash, T::AccountId, Vec<u8>),
-        MatchDrawn(T::Hash, Vec<u8>),
+        // match id, white, black, start position
+        MatchStarted(T::Hash, T::AccountId, T::AccountId, Vec<u8>),
+        MoveExecuted(T::Hash, T::AccountId, Vec<u8>, Option<PromotionPiece>),
+        MatchWon(T::Hash, T::AccountId, Vec<u8>, Termination),
+        MatchDrawn(T::Hash, Vec<u8>, Termination),
+        // the following three are no longer emitted, failed settlements revert the call instead.
+        // They are kept so that later events keep their index
         MatchRefundError(T::Hash),
         MatchAwardError(T::Hash, T::AccountId),
         MatchClearanceError(T::Hash, T::AccountId, T::AccountId),
+        MatchResigned(T::Hash, T::AccountId, Vec<u8>),
+        DrawOffered(T::Hash, T::AccountId),
+        DrawDeclined(T::Hash, T::AccountId),
+        FinishedMatchPruned(T::Hash),
+        QueueEntered(T::AccountId, MatchStyle, AssetIdOf<T>, T::AssetBalance),
+        QueueLeft(T::AccountId),
+        ParametersSet(Parameters<BlockNumberFor<T>>),
+        // style, ranked players from highest to lowest rating
+        LeaderboardChanged(MatchStyle, Vec<T::AccountId>),
     }
 
+    // clients decode events and errors by index, new variants are only ever appended
     #[pallet::error]
     pub enum Error<T> {
         NonceOverflow,
@@ -304,11 +858,44 @@ pub mod pallet {
         MatchNotOnGoing,
         MatchNotAbandoned,
         MoveNotExpired,
+        NotMatchPlayer,
+        DrawAlreadyOffered,
+        NoDrawOffer,
+        InvalidDrawClaim,
+        NonExistentFinishedMatch,
+        FinishedMatchNotExpired,
+        InvalidRatingBounds,
+        RatingOutOfBounds,
+        AlreadyInQueue,
+        NotInQueue,
+        QueueFull,
+        InvalidStartPosition,
+        OutOfTime,
+        InvalidTimeControl,
+        InvalidParameters,
+        InvalidRatedMatch,
+        ChallengeNotExpired,
     }
 
-    const MOVE_FEN_LENGTH: usize = 4;
+    // UCI moves are 4 characters long, or 5 when a promotion piece is appended (e.g. `e7e8q`)
+    const MIN_MOVE_FEN_LENGTH: usize = 4;
+    const MAX_MOVE_FEN_LENGTH: usize = 5;
+
+    // longest FEN is around 90 characters, anything longer than this is garbage
+    const MAX_BOARD_FEN_LENGTH: usize = 128;
 
-    type GenesisInfo<T> = (AccountIdOf<T>, u16);
+    // a draw can be claimed once the same position occurs three times,
+    // or after fifty moves (i.e. 100 halfmoves) without captures or pawn moves
+    const THREEFOLD_REPETITION: usize = 3;
+    const FIFTY_MOVE_RULE_HALFMOVES: u8 = 100;
+
+    // Chess960 start positions are numbered from 0 to 959 (Scharnagl numbering)
+    const CHESS960_POSITIONS: u16 = 960;
+    // largest multiple of 960 that fits in a u16 (68 * 960), random values from there on are redrawn
+    const CHESS960_REJECTION_THRESHOLD: u16 = CHESS960_POSITIONS * (u16::MAX / CHESS960_POSITIONS);
+
+    // account, style and initial rating
+    type GenesisInfo<T> = (AccountIdOf<T>, MatchStyle, u16);
 
     #[pallet::genesis_config]
     pub struct GenesisConfig<T: Config> {
@@ -325,55 +912,158 @@ pub mod pallet {
     #[pallet::genesis_build]
     impl<T: Config> BuildGenesisConfig for GenesisConfig<T> {
         fn build(&self) {
-            for (account, elo) in &self.elo {
-                <PlayerElo<T>>::insert(account, elo);
+            for (account, style, elo) in &self.elo {
+                <PlayerRatings<T>>::insert(account, style, T::RatingSystem::new_state(*elo));
+                // ratings set at genesis are established, though no games were played yet
+                <EstablishedRatings<T>>::insert(account, style, true);
             }
         }
     }
 
+    #[pallet::hooks]
+    impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+        // matchmaking and lazy migrations only use the weight left over at the end of each block
+        fn on_idle(_n: BlockNumberFor<T>, remaining_weight: Weight) -> Weight {
+            let consumed = Self::pair_queued_players(remaining_weight);
+            // ratings from before v3 are migrated with whatever is left
+            consumed.saturating_add(migrations::v3::migrate_players::<T>(
+                remaining_weight.saturating_sub(consumed),
+            ))
+        }
+    }
+
     #[pallet::call]
     impl<T: Config> Pallet<T> {
         #[pallet::call_index(0)]
         #[pallet::weight(T::WeightInfo::create_match())]
         pub fn create_match(
             origin: OriginFor<T>,
-            opponent: T::AccountId,
+            opponent: Option<T::AccountId>,
             style: MatchStyle,
             bet_asset_id: AssetIdOf<T>,
             bet_amount: T::AssetBalance,
+            options: MatchOptions,
         ) -> DispatchResult {
             let challenger = ensure_signed(origin)?;
+            let MatchOptions {
+                color,
+                variant,
+                start_fen,
+                rated,
+                min_rating,
+                max_rating,
+            } = options;
+
+            // ratings only compare matches played under the same conditions,
+            // and friendly matches would let anyone farm ratings for free
+            if rated {
+                ensure!(
+                    variant == Variant::Standard
+                        && start_fen.is_none()
+                        && style.is_rated()
+                        && !bet_amount.is_zero(),
+                    Error::<T>::InvalidRatedMatch
+                );
+            }
 
-            if challenger == opponent {
+            if opponent.as_ref() == Some(&challenger) {
                 return Err(Error::<T>::InvalidOpponent.into());
             }
 
+            if let (Some(min), Some(max)) = (min_rating, max_rating) {
+                ensure!(min <= max, Error::<T>::InvalidRatingBounds);
+            }
+            if min_rating.is_some() || max_rating.is_some() {
+                ensure!(style.is_rated(), Error::<T>::InvalidRatingBounds);
+            }
+
+            Self::ensure_valid_time_control(&style)?;
+
+            // teaching and handicap (odds) matches start from an arbitrary legal position
+            let start_board = match start_fen {
+                Some(fen) => {
+                    // Chess960 start positions are drawn by the pallet
+                    ensure!(
+                        variant == Variant::Standard,
+                        Error::<T>::InvalidStartPosition
+                    );
+                    ensure!(
+                        fen.len() <= MAX_BOARD_FEN_LENGTH,
+                        Error::<T>::InvalidBoardEncoding
+                    );
+                    let board_obj = Self::decode_board(fen, &variant)?;
+                    ensure!(
+                        Self::game_status(&board_obj) == GameStatus::Ongoing,
+                        Error::<T>::InvalidStartPosition
+                    );
+                    Self::encode_board(board_obj, &variant)
+                }
+                None => Self::init_board(),
+            };
+
             let nonce = <NextNonce<T>>::get();
+            let parameters = Self::chess_parameters();
+            let time_control = Self::time_control(&style);
+            let now = <frame_system::Pallet<T>>::block_number();
 
             let new_match: Match<T> = Match {
                 challenger: challenger.clone(),
                 opponent: opponent.clone(),
-                board: Self::init_board(),
+                board: start_board.clone(),
                 state: MatchState::AwaitingOpponent,
                 nonce: nonce.clone(),
                 style,
-                last_move: 0u32.into(),
+                // until the match starts, this is when the challenge was made
+                last_move: now,
                 start: 0u32.into(),
                 bet_asset_id,
                 bet_amount,
+                draw_offer: None,
+                min_rating,
+                max_rating,
+                color,
+                white: None,
+                black: None,
+                variant,
+                start_board,
+                rated,
+                white_clock: time_control.base,
+                black_clock: time_control.base,
+                time_control,
+                incentive_share: parameters.incentive_share,
+                k: parameters.k,
             };
 
             new_match.challenger_bet()?;
 
             let match_id = Self::match_id(challenger.clone(), opponent.clone(), nonce.clone());
+            let event = Event::MatchCreated(
+                challenger.clone(),
+                opponent.clone(),
+                match_id,
+                new_match.style.clone(),
+                new_match.bet_asset_id.clone(),
+                new_match.bet_amount,
+                new_match.variant.clone(),
+            );
+            match &opponent {
+                Some(opponent) => <PlayerMatches<T>>::insert(opponent.clone(), match_id, ()),
+                None => <OpenChallenges<T>>::insert(
+                    (
+                        new_match.style.clone(),
+                        new_match.bet_asset_id.clone(),
+                        match_id,
+                    ),
+                    (),
+                ),
+            }
             <Matches<T>>::insert(match_id, new_match);
             <PlayerMatches<T>>::insert(challenger.clone(), match_id, ());
-            <PlayerMatches<T>>::insert(opponent.clone(), match_id, ());
             <MatchIdFromNonce<T>>::insert(nonce, match_id);
 
             Self::increment_nonce()?;
 
-            Self::deposit_event(Event::MatchCreated(challenger, opponent, match_id));
+            Self::deposit_event(event);
 
             Ok(())
         }
@@ -398,10 +1088,7 @@ pub mod pallet {
 
             chess_match.abort_bet()?;
 
-            <Matches<T>>::remove(match_id);
-            <PlayerMatches<T>>::remove(who.clone(), match_id);
-            <PlayerMatches<T>>::remove(chess_match.opponent, match_id);
-            <MatchIdFromNonce<T>>::remove(chess_match.nonce);
+            Self::remove_match(match_id, &chess_match);
 
             Self::deposit_event(Event::MatchAborted(match_id));
 
@@ -418,32 +1105,86 @@ pub mod pallet {
                 None => return Err(Error::<T>::NonExistentMatch.into()),
             };
 
-            if who != chess_match.opponent {
-                return Err(Error::<T>::NotMatchOpponent.into());
+            if chess_match.state != MatchState::AwaitingOpponent {
+                return Err(Error::<T>::NotAwaitingOpponent.into());
+            }
+
+            let open_challenge = chess_match.opponent.is_none();
+            match &chess_match.opponent {
+                Some(opponent) => {
+                    if who != *opponent {
+                        return Err(Error::<T>::NotMatchOpponent.into());
+                    }
+                }
+                // anyone but the challenger can join an open challenge
+                None => {
+                    if who == chess_match.challenger {
+                        return Err(Error::<T>::InvalidOpponent.into());
+                    }
+                }
+            }
+
+            let rating = Self::rating(&who, &chess_match.style);
+            if chess_match.min_rating.map_or(false, |min| rating < min)
+                | chess_match.max_rating.map_or(false, |max| rating > max)
+            {
+                return Err(Error::<T>::RatingOutOfBounds.into());
             }
 
+            chess_match.opponent = Some(who.clone());
             chess_match.opponent_bet()?;
 
-            chess_match.state = MatchState::OnGoing(NextMove::Whites);
+            if open_challenge {
+                <OpenChallenges<T>>::remove((
+                    chess_match.style.clone(),
+                    chess_match.bet_asset_id.clone(),
+                    match_id,
+                ));
+                <PlayerMatches<T>>::insert(who.clone(), match_id, ());
+            }
+
+            // Chess960 start positions are only drawn now, so the challenger can't know them in advance
+            if chess_match.variant == Variant::Chess960 {
+                let board_obj = Board::chess960_startpos(Self::chess960_position(match_id));
+                chess_match.board = Self::encode_board(board_obj, &chess_match.variant);
+                chess_match.start_board = chess_match.board.clone();
+            }
+
+            let board_obj = Self::decode_board(chess_match.board.clone(), &chess_match.variant)?;
+            Self::record_position(match_id, board_obj.hash(), board_obj.halfmove_clock());
+
+            Self::assign_colors(match_id, &mut chess_match)?;
+            let (white, black) = (chess_match.white()?, chess_match.black()?);
+            let start_board = chess_match.start_board.clone();
+
+            // custom start positions may have blacks to move
+            chess_match.state = match board_obj.side_to_move() {
+                Color::White => MatchState::OnGoing(NextMove::Whites),
+                Color::Black => MatchState::OnGoing(NextMove::Blacks),
+            };
             chess_match.start = <frame_system::Pallet<T>>::block_number();
+            // clock of whoever moves first starts right away
+            chess_match.last_move = chess_match.start;
             <Matches<T>>::insert(match_id, chess_match);
 
-            Self::deposit_event(Event::MatchStarted(match_id));
+            Self::deposit_event(Event::MatchStarted(match_id, white, black, start_board));
 
             Ok(())
         }
 
+        // charged as if the move ended a match with a full move history, the actual cost is
+        // refunded once the length of the history and the outcome of the move are known
         #[pallet::call_index(3)]
-        #[pallet::weight(T::WeightInfo::make_move())]
+        #[pallet::weight(T::WeightInfo::make_move_checkmate(T::MaxMoves::get()))]
         pub fn make_move(
             origin: OriginFor<T>,
             match_id: T::Hash,
             move_fen: Vec<u8>,
-        ) -> DispatchResult {
+        ) -> DispatchResultWithPostInfo {
             let who = ensure_signed(origin)?;
 
             ensure!(
-                move_fen.len() == MOVE_FEN_LENGTH,
+                (MIN_MOVE_FEN_LENGTH..=MAX_MOVE_FEN_LENGTH).contains(&move_fen.len()),
                 Error::<T>::InvalidMoveEncoding
             );
 
@@ -456,99 +1197,105 @@ pub mod pallet {
                 MatchState::AwaitingOpponent => {
                     return Err(Error::<T>::StillAwaitingOpponent.into())
                 }
-                MatchState::Won | MatchState::Drawn => {
+                MatchState::Won(..) | MatchState::Drawn(_) => {
                     return Err(Error::<T>::MatchAlreadyFinished.into())
                 }
                 MatchState::OnGoing(NextMove::Whites) => {
-                    if who != chess_match.challenger {
+                    if chess_match.white.as_ref() != Some(&who) {
                         return Err(Error::<T>::NotYourTurn.into());
                     }
                 }
                 MatchState::OnGoing(NextMove::Blacks) => {
-                    if who != chess_match.opponent {
+                    if chess_match.black.as_ref() != Some(&who) {
                         return Err(Error::<T>::NotYourTurn.into());
                     }
                 }
             }
 
-            let mut board_obj: Board = Self::decode_board(chess_match.board)?;
+            let now = <frame_system::Pallet<T>>::block_number();
+            let elapsed = now.saturating_sub(chess_match.last_move);
+            let time_control = chess_match.time_control.clone();
+            let clock = match chess_match.state {
+                MatchState::OnGoing(NextMove::Whites) => &mut chess_match.white_clock,
+                _ => &mut chess_match.black_clock,
+            };
+            // once the flag falls, the match can only be settled by clear_abandoned_match
+            ensure!(
+                elapsed <= (*clock).min(time_control.per_move),
+                Error::<T>::OutOfTime
+            );
+            *clock = clock
+                .saturating_sub(elapsed)
+                .saturating_add(time_control.increment);
+
+            let mut board_obj: Board = Self::decode_board(chess_match.board, &chess_match.variant)?;
             let move_obj: Move = Self::decode_move(move_fen.clone())?;
 
             if !board_obj.is_legal(move_obj) {
                 return Err(Error::<T>::IllegalMove.into());
             }
 
+            // promotion piece was already validated by decode_move
+            let promotion = move_obj.promotion.and_then(PromotionPiece::from_piece);
+
+            // the move history is only a record, a legal move is never rejected because of it
+            let (recorded, m) = <MatchMoves<T>>::mutate(match_id, |moves| {
+                let recorded = moves.try_push(CompactMove::from_move(move_obj)).is_ok();
+                (recorded, moves.len() as u32)
+            });
+            if !recorded {
+                <MoveHistoryTruncated<T>>::insert(match_id, true);
+            }
+
             // we already checked for legality, so we call play_unchecked (faster)
             board_obj.play_unchecked(move_obj);
 
             // check game status: Won? Drawn? OnGoing?
-            chess_match.state = match board_obj.status() {
+            chess_match.state = match Self::game_status(&board_obj) {
                 GameStatus::Ongoing => match board_obj.side_to_move() {
                     Color::White => MatchState::OnGoing(NextMove::Whites),
                     Color::Black => MatchState::OnGoing(NextMove::Blacks),
                 },
-                GameStatus::Won => MatchState::Won,
-                GameStatus::Drawn => MatchState::Drawn,
+                GameStatus::Won => MatchState::Won(who.clone(), Termination::Checkmate),
+                GameStatus::Drawn => MatchState::Drawn(Self::draw_termination(&board_obj)),
             };
 
-            chess_match.board = Self::encode_board(board_obj);
-            chess_match.last_move = <frame_system::Pallet<T>>::block_number();
+            let position_hash = board_obj.hash();
+            let halfmove_clock = board_obj.halfmove_clock();
+
+            chess_match.board = Self::encode_board(board_obj, &chess_match.variant);
+            chess_match.last_move = now;
+
+            // a draw offer expires as soon as the offerer's opponent moves
+            if chess_match.draw_offer.is_some() && chess_match.draw_offer != Some(who.clone()) {
+                chess_match.draw_offer = None;
+            }
 
-            Self::deposit_event(Event::MoveExecuted(match_id, who.clone(), move_fen));
-            if chess_match.state == MatchState::Won {
+            Self::deposit_event(Event::MoveExecuted(
+                match_id,
+                who.clone(),
+                move_fen,
+                promotion,
+            ));
+            if let MatchState::Won(winner, termination) = chess_match.state.clone() {
                 Self::deposit_event(Event::MatchWon(
                     match_id,
-                    who.clone(),
+                    winner.clone(),
                     chess_match.board.clone(),
+                    termination.clone(),
                 ));
+                Self::settle_win(match_id, chess_match, winner, termination, None)?;
+            } else if let MatchState::Drawn(termination) = chess_match.state.clone() {
+                Self::settle_draw(match_id, chess_match, termination)?;
+            } else {
+                // match still ongoing, update on-chain board
+                <Matches<T>>::insert(match_id, chess_match);
+                Self::record_position(match_id, position_hash, halfmove_clock);
+                return Ok(Some(T::WeightInfo::make_move(m)).into());
+            }
 
-                // winner gets both deposits
-                chess_match.win_bet(&who)?;
-
-                // update elo rating
-                let (score_1, score_2) = if chess_match.challenger == who {
-                    (1_f32, 0_f32)
-                } else {
-                    (0_f32, 1_f32)
-                };
-                Self::update_elo(
-                    chess_match.challenger.clone(),
-                    score_1,
-                    chess_match.opponent.clone(),
-                    score_2,
-                );
-
-                // match is over, clean up storage
-                <Matches<T>>::remove(match_id);
-                <PlayerMatches<T>>::remove(chess_match.challenger.clone(), match_id);
-                <PlayerMatches<T>>::remove(chess_match.opponent, match_id);
-                <MatchIdFromNonce<T>>::remove(chess_match.nonce);
-            } else if chess_match.state == MatchState::Drawn {
-                Self::deposit_event(Event::MatchDrawn(match_id, chess_match.board.clone()));
-
-                // return deposit to both players
-                chess_match.refund_bets()?;
-
-                // update elo rating
-                Self::update_elo(
-                    chess_match.challenger.clone(),
-                    0.5,
-                    chess_match.opponent.clone(),
-                    0.5,
-                );
-
-                // match is over, clean up storage
-                <Matches<T>>::remove(match_id);
-                <PlayerMatches<T>>::remove(chess_match.challenger.clone(), match_id);
-                <PlayerMatches<T>>::remove(chess_match.opponent, match_id);
-                <MatchIdFromNonce<T>>::remove(chess_match.nonce);
-            } else {
-                // match still ongoing, update on-chain board
-                <Matches<T>>::insert(match_id, chess_match);
-            }
-
-            Ok(())
-        }
+            Ok(Some(T::WeightInfo::make_move_checkmate(m)).into())
+        }
 
         #[pallet::call_index(4)]
         #[pallet::weight(T::WeightInfo::clear_abandoned_match())]
@@ -559,79 +1306,411 @@ pub mod pallet {
                 None => return Err(Error::<T>::NonExistentMatch.into()),
             };
 
+            let now = <frame_system::Pallet<T>>::block_number();
+            let diff = now - chess_match.last_move;
+
+            // challenges nobody accepted in time are aborted, and the challenger gets the stake back
+            if chess_match.state == MatchState::AwaitingOpponent {
+                ensure!(
+                    diff > T::ChallengeExpiry::get(),
+                    Error::<T>::ChallengeNotExpired
+                );
+
+                chess_match.abort_bet()?;
+
+                Self::remove_match(match_id, &chess_match);
+
+                Self::deposit_event(Event::MatchAborted(match_id));
+
+                return Ok(());
+            }
+
             ensure!(
                 (chess_match.state == MatchState::OnGoing(NextMove::Whites))
                     | (chess_match.state == MatchState::OnGoing(NextMove::Blacks)),
                 Error::<T>::MatchNotOnGoing
             );
 
-            let now = <frame_system::Pallet<T>>::block_number();
-            let diff = now - chess_match.last_move;
-
-            let expired: bool = match chess_match.style {
-                MatchStyle::Bullet => diff > T::BulletPeriod::get(),
-                MatchStyle::Blitz => diff > T::BlitzPeriod::get(),
-                MatchStyle::Rapid => diff > T::RapidPeriod::get(),
-                MatchStyle::Daily => diff > T::DailyPeriod::get(),
+            // the flag falls once whoever is supposed to move runs out of time,
+            // or takes longer than the time allowed for a single move
+            let time_control = &chess_match.time_control;
+            let clock = match chess_match.state {
+                MatchState::OnGoing(NextMove::Whites) => chess_match.white_clock,
+                _ => chess_match.black_clock,
             };
+            let flag_fall = clock.min(time_control.per_move);
+            let expired: bool = diff > flag_fall;
 
             ensure!(expired, Error::<T>::MoveNotExpired);
 
+            // whoever was supposed to move loses
             let winner = match chess_match.state {
-                MatchState::OnGoing(NextMove::Whites) => chess_match.opponent.clone(),
-                _ => chess_match.challenger.clone(),
+                MatchState::OnGoing(NextMove::Whites) => chess_match.black()?,
+                _ => chess_match.white()?,
+            };
+
+            // the winner has 10 times the time per move to claim victory after the flag falls
+            let abandoned: bool =
+                diff > flag_fall.saturating_add(time_control.per_move.saturating_mul(10u32.into()));
+
+            // a match is only considered abandoned when a janitor clears it
+            let termination = if chess_match.is_player(&who) | !abandoned {
+                Termination::Timeout
+            } else {
+                Termination::Abandonment
             };
 
             Self::deposit_event(Event::MatchWon(
                 match_id,
                 winner.clone(),
                 chess_match.board.clone(),
+                termination.clone(),
             ));
 
-            let abandoned: bool = match chess_match.style {
-                MatchStyle::Bullet => diff > T::BulletPeriod::get() * 10u32.into(),
-                MatchStyle::Blitz => diff > T::BlitzPeriod::get() * 10u32.into(),
-                MatchStyle::Rapid => diff > T::RapidPeriod::get() * 10u32.into(),
-                MatchStyle::Daily => diff > T::DailyPeriod::get() * 10u32.into(),
+            // who cleared the match after match is abandoned gets the incentive
+            let janitor = (termination == Termination::Abandonment).then_some(who);
+            Self::settle_win(match_id, chess_match, winner, termination, janitor)
+        }
+
+        #[pallet::call_index(5)]
+        #[pallet::weight(T::WeightInfo::resign())]
+        pub fn resign(origin: OriginFor<T>, match_id: T::Hash) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            let chess_match = match Self::chess_matches(match_id) {
+                Some(m) => m,
+                None => return Err(Error::<T>::NonExistentMatch.into()),
             };
 
-            if (who == chess_match.challenger) | (who == chess_match.opponent) | !abandoned {
-                // winner gets both deposits before match becomes abandoned
-                match chess_match.win_bet(&winner) {
-                    Ok(()) => {}
-                    Err(_) => Self::deposit_event(Event::MatchAwardError(match_id, winner.clone())),
+            match chess_match.state {
+                MatchState::AwaitingOpponent => {
+                    return Err(Error::<T>::StillAwaitingOpponent.into())
                 }
+                MatchState::Won(..) | MatchState::Drawn(_) => {
+                    return Err(Error::<T>::MatchAlreadyFinished.into())
+                }
+                MatchState::OnGoing(_) => {}
+            }
+
+            let winner = if who == chess_match.challenger {
+                chess_match.opponent()?
+            } else if chess_match.opponent.as_ref() == Some(&who) {
+                chess_match.challenger.clone()
             } else {
-                // who cleared the match after match is abandoned gets the incentive,
-                // and the winner gets both deposits minus the incentive share
-                match chess_match.clear_abandoned_bet(&winner, &who) {
-                    Ok(()) => {}
-                    Err(_) => Self::deposit_event(Event::MatchClearanceError(
-                        match_id,
-                        winner.clone(),
-                        who,
-                    )),
+                return Err(Error::<T>::NotMatchPlayer.into());
+            };
+
+            Self::deposit_event(Event::MatchResigned(
+                match_id,
+                who,
+                chess_match.board.clone(),
+            ));
+
+            // opponent of who resigned gets both deposits
+            Self::settle_win(
+                match_id,
+                chess_match,
+                winner,
+                Termination::Resignation,
+                None,
+            )
+        }
+
+        #[pallet::call_index(6)]
+        #[pallet::weight(T::WeightInfo::offer_draw())]
+        pub fn offer_draw(origin: OriginFor<T>, match_id: T::Hash) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            let mut chess_match = match Self::chess_matches(match_id) {
+                Some(m) => m,
+                None => return Err(Error::<T>::NonExistentMatch.into()),
+            };
+
+            match chess_match.state {
+                MatchState::AwaitingOpponent => {
+                    return Err(Error::<T>::StillAwaitingOpponent.into())
+                }
+                MatchState::Won(..) | MatchState::Drawn(_) => {
+                    return Err(Error::<T>::MatchAlreadyFinished.into())
+                }
+                MatchState::OnGoing(_) => {}
+            }
+
+            if !chess_match.is_player(&who) {
+                return Err(Error::<T>::NotMatchPlayer.into());
+            }
+
+            ensure!(
+                chess_match.draw_offer.is_none(),
+                Error::<T>::DrawAlreadyOffered
+            );
+
+            chess_match.draw_offer = Some(who.clone());
+            <Matches<T>>::insert(match_id, chess_match);
+
+            Self::deposit_event(Event::DrawOffered(match_id, who));
+
+            Ok(())
+        }
+
+        #[pallet::call_index(7)]
+        #[pallet::weight(T::WeightInfo::accept_draw())]
+        pub fn accept_draw(origin: OriginFor<T>, match_id: T::Hash) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            let chess_match = match Self::chess_matches(match_id) {
+                Some(m) => m,
+                None => return Err(Error::<T>::NonExistentMatch.into()),
+            };
+
+            Self::ensure_draw_offered_to(&chess_match, &who)?;
+
+            Self::settle_draw(match_id, chess_match, Termination::Agreement)
+        }
+
+        #[pallet::call_index(8)]
+        #[pallet::weight(T::WeightInfo::decline_draw())]
+        pub fn decline_draw(origin: OriginFor<T>, match_id: T::Hash) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            let mut chess_match = match Self::chess_matches(match_id) {
+                Some(m) => m,
+                None => return Err(Error::<T>::NonExistentMatch.into()),
+            };
+
+            Self::ensure_draw_offered_to(&chess_match, &who)?;
+
+            chess_match.draw_offer = None;
+            <Matches<T>>::insert(match_id, chess_match);
+
+            Self::deposit_event(Event::DrawDeclined(match_id, who));
+
+            Ok(())
+        }
+
+        #[pallet::call_index(9)]
+        #[pallet::weight(T::WeightInfo::claim_draw())]
+        pub fn claim_draw(origin: OriginFor<T>, match_id: T::Hash) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            let chess_match = match Self::chess_matches(match_id) {
+                Some(m) => m,
+                None => return Err(Error::<T>::NonExistentMatch.into()),
+            };
+
+            match chess_match.state {
+                MatchState::AwaitingOpponent => {
+                    return Err(Error::<T>::StillAwaitingOpponent.into())
+                }
+                MatchState::Won(..) | MatchState::Drawn(_) => {
+                    return Err(Error::<T>::MatchAlreadyFinished.into())
                 }
+                MatchState::OnGoing(_) => {}
             }
 
-            // update elo rating
-            let looser = if chess_match.challenger == winner {
-                chess_match.opponent.clone()
+            if !chess_match.is_player(&who) {
+                return Err(Error::<T>::NotMatchPlayer.into());
+            }
+
+            let board_obj = Self::decode_board(chess_match.board.clone(), &chess_match.variant)?;
+            let position_hash = board_obj.hash();
+            let repetitions = Self::position_history(match_id)
+                .iter()
+                .filter(|h| **h == position_hash)
+                .count();
+
+            let termination = if repetitions >= THREEFOLD_REPETITION {
+                Termination::Repetition
+            } else if board_obj.halfmove_clock() >= FIFTY_MOVE_RULE_HALFMOVES {
+                Termination::FiftyMoveRule
             } else {
-                chess_match.challenger.clone()
+                return Err(Error::<T>::InvalidDrawClaim.into());
             };
-            Self::update_elo(winner.clone(), 1_f32, looser, 0_f32);
 
-            <Matches<T>>::remove(match_id);
-            <PlayerMatches<T>>::remove(chess_match.challenger.clone(), match_id);
-            <PlayerMatches<T>>::remove(chess_match.opponent, match_id);
-            <MatchIdFromNonce<T>>::remove(chess_match.nonce);
+            Self::settle_draw(match_id, chess_match, termination)
+        }
+
+        #[pallet::call_index(10)]
+        #[pallet::weight(T::WeightInfo::prune_finished_match())]
+        pub fn prune_finished_match(origin: OriginFor<T>, match_id: T::Hash) -> DispatchResult {
+            ensure_signed(origin)?;
+
+            let finished_match = match Self::finished_matches(match_id) {
+                Some(m) => m,
+                None => return Err(Error::<T>::NonExistentFinishedMatch.into()),
+            };
+
+            let now = <frame_system::Pallet<T>>::block_number();
+            ensure!(
+                now.saturating_sub(finished_match.end) > T::FinishedMatchRetention::get(),
+                Error::<T>::FinishedMatchNotExpired
+            );
+
+            <FinishedMatches<T>>::remove(match_id);
+            <PlayerFinishedMatches<T>>::remove(finished_match.challenger, match_id);
+            <PlayerFinishedMatches<T>>::remove(finished_match.opponent, match_id);
+
+            Self::deposit_event(Event::FinishedMatchPruned(match_id));
+
+            Ok(())
+        }
+
+        #[pallet::call_index(11)]
+        #[pallet::weight(T::WeightInfo::enter_queue())]
+        pub fn enter_queue(
+            origin: OriginFor<T>,
+            style: MatchStyle,
+            bet_asset_id: AssetIdOf<T>,
+            bet_amount: T::AssetBalance,
+        ) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            let mut queue = Self::matchmaking_queue();
+            ensure!(
+                !queue.iter().any(|entry| entry.player == who),
+                Error::<T>::AlreadyInQueue
+            );
+
+            Self::ensure_valid_time_control(&style)?;
+            Self::ensure_valid_bet(
+                bet_asset_id.clone(),
+                bet_amount,
+                Self::chess_parameters().incentive_share,
+            )?;
+
+            queue
+                .try_push(QueueEntry {
+                    player: who.clone(),
+                    style: style.clone(),
+                    bet_asset_id: bet_asset_id.clone(),
+                    bet_amount,
+                    rating: Self::rating(&who, &style),
+                })
+                .map_err(|_| Error::<T>::QueueFull)?;
+
+            // stake stays in escrow until the player is paired or leaves the queue
+            Self::hold_stake(bet_asset_id.clone(), &who, bet_amount)?;
+
+            <MatchmakingQueue<T>>::put(queue);
+
+            Self::deposit_event(Event::QueueEntered(who, style, bet_asset_id, bet_amount));
+
+            Ok(())
+        }
+
+        #[pallet::call_index(12)]
+        #[pallet::weight(T::WeightInfo::leave_queue())]
+        pub fn leave_queue(origin: OriginFor<T>) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            let mut queue = Self::matchmaking_queue();
+            let index = match queue.iter().position(|entry| entry.player == who) {
+                Some(i) => i,
+                None => return Err(Error::<T>::NotInQueue.into()),
+            };
+            let entry = queue.remove(index);
+
+            Self::release_stake(entry.bet_asset_id, &who, entry.bet_amount)?;
+
+            <MatchmakingQueue<T>>::put(queue);
+
+            Self::deposit_event(Event::QueueLeft(who));
+
+            Ok(())
+        }
+
+        #[pallet::call_index(13)]
+        #[pallet::weight(T::WeightInfo::set_parameters())]
+        pub fn set_parameters(
+            origin: OriginFor<T>,
+            parameters: Parameters<BlockNumberFor<T>>,
+        ) -> DispatchResult {
+            T::AdminOrigin::ensure_origin(origin)?;
+
+            // matches must be able to time out, and janitors can't take more than the prize
+            ensure!(
+                !parameters.bullet_period.is_zero()
+                    & !parameters.blitz_period.is_zero()
+                    & !parameters.rapid_period.is_zero()
+                    & !parameters.daily_period.is_zero()
+                    & (parameters.incentive_share <= 100),
+                Error::<T>::InvalidParameters
+            );
+
+            <ChessParameters<T>>::put(parameters.clone());
+
+            Self::deposit_event(Event::ParametersSet(parameters));
 
             Ok(())
         }
     }
 
     impl<T: Config> Pallet<T> {
+        /// Rating of a player for a match style
+        pub fn rating(who: &T::AccountId, style: &MatchStyle) -> u16 {
+            T::RatingSystem::rating(&Self::rating_state(who, style))
+        }
+
+        /// Rating state of a player for a match style, including ratings from before v3 that
+        /// weren't migrated yet
+        pub fn rating_state(who: &T::AccountId, style: &MatchStyle) -> RatingStateOf<T> {
+            <PlayerRatings<T>>::try_get(who, style).unwrap_or_else(|_| {
+                migrations::v3::legacy_rating::<T>(who, style)
+                    .unwrap_or_else(DefaultRating::<T>::get)
+            })
+        }
+
+        /// Whether a player still has less than `ProvisionalGames` rated matches for a style,
+        /// unless their rating was established at genesis or by a migration
+        pub fn is_provisional(who: &T::AccountId, style: &MatchStyle) -> bool {
+            !Self::established_rating(who, style)
+                && migrations::v3::legacy_rating::<T>(who, style).is_none()
+                && Self::rated_games(who, style) < T::ProvisionalGames::get()
+        }
+
+        /// Current time control of a match style. Bullet, Blitz and Rapid give their period as a bank
+        /// for the whole match plus their increment after every move, Daily gives its period to
+        /// every move instead
+        pub fn time_control(style: &MatchStyle) -> TimeControl<BlockNumberFor<T>> {
+            let parameters = Self::chess_parameters();
+            match style {
+                MatchStyle::Bullet => {
+                    TimeControl::bank(parameters.bullet_period, T::BulletIncrement::get())
+                }
+                MatchStyle::Blitz => {
+                    TimeControl::bank(parameters.blitz_period, T::BlitzIncrement::get())
+                }
+                MatchStyle::Rapid => {
+                    TimeControl::bank(parameters.rapid_period, T::RapidIncrement::get())
+                }
+                MatchStyle::Daily => TimeControl::per_move(parameters.daily_period),
+                MatchStyle::Custom {
+                    per_move,
+                    base,
+                    increment,
+                } => TimeControl {
+                    per_move: (*per_move).into(),
+                    base: (*base).into(),
+                    increment: (*increment).into(),
+                },
+            }
+        }
+
+        // custom time controls must be bounded, otherwise a match could never time out
+        fn ensure_valid_time_control(style: &MatchStyle) -> DispatchResult {
+            if let MatchStyle::Custom { .. } = style {
+                let time_control = Self::time_control(style);
+                let (min, max) = (T::MinTimeControl::get(), T::MaxTimeControl::get());
+                ensure!(
+                    (min..=max).contains(&time_control.per_move)
+                        & (min..=max).contains(&time_control.base)
+                        & (time_control.increment <= max),
+                    Error::<T>::InvalidTimeControl
+                );
+            }
+            Ok(())
+        }
+
         fn increment_nonce() -> DispatchResult {
             <NextNonce<T>>::try_mutate(|nonce| {
                 let next = nonce.checked_add(1).ok_or(Error::<T>::NonceOverflow)?;
@@ -641,7 +1720,470 @@ pub mod pallet {
             })
         }
 
-        fn match_id(challenger: T::AccountId, opponent: T::AccountId, nonce: u128) -> T::Hash {
+        // puts `amount` of the free balance of `who` aside, as a match stake
+        pub(crate) fn hold_stake(
+            asset_id: AssetIdOf<T>,
+            who: &T::AccountId,
+            amount: T::AssetBalance,
+        ) -> DispatchResult {
+            if amount.is_zero() {
+                return Ok(());
+            }
+            T::Stakes::hold(asset_id, who, amount)
+        }
+
+        // gives `amount` of the stake of `who` back to their free balance
+        fn release_stake(
+            asset_id: AssetIdOf<T>,
+            who: &T::AccountId,
+            amount: T::AssetBalance,
+        ) -> DispatchResult {
+            if amount.is_zero() {
+                return Ok(());
+            }
+            T::Stakes::release(asset_id, who, amount)
+        }
+
+        // pays `amount` out of the stake of `source` into the free balance of `dest`
+        fn transfer_stake(
+            asset_id: AssetIdOf<T>,
+            source: &T::AccountId,
+            dest: &T::AccountId,
+            amount: T::AssetBalance,
+        ) -> DispatchResult {
+            if amount.is_zero() {
+                return Ok(());
+            }
+            T::Stakes::transfer(asset_id, source, dest, amount)
+        }
+
+        fn ensure_valid_bet(
+            bet_asset_id: AssetIdOf<T>,
+            bet_amount: T::AssetBalance,
+            incentive_share: u8,
+        ) -> DispatchResult {
+            if !T::Assets::asset_exists(bet_asset_id.clone()) {
+                return Err(Error::<T>::BetDoesNotExist.into());
+            }
+
+            // bet must cover janitor incentives, unless there is no bet at all
+            if !bet_amount.is_zero()
+                && Percent::from_percent(incentive_share) * bet_amount.saturating_add(bet_amount)
+                    < T::Assets::minimum_balance(bet_asset_id)
+            {
+                return Err(Error::<T>::BetTooLow.into());
+            }
+
+            Ok(())
+        }
+
+        // Pairs queued players until no compatible entries are left or `max_weight` is used up.
+        // Entries are served in the order they entered the queue, each one paired with the
+        // compatible entry closest to its rating. The older entry of each pair plays whites.
+        pub(crate) fn pair_queued_players(max_weight: Weight) -> Weight {
+            let mut consumed = T::DbWeight::get().reads(1);
+            if consumed.any_gt(max_weight) {
+                return Weight::zero();
+            }
+            let len = match <MatchmakingQueue<T>>::decode_len() {
+                Some(len) if len >= 2 => len as u32,
+                _ => return consumed,
+            };
+
+            // looking at an entry scans the whole queue, pairing it also writes the queue back
+            let scan_weight = T::WeightInfo::skip_queued_entry(len);
+            let pair_weight = T::WeightInfo::start_queued_match(len);
+            let exceeds = |consumed: Weight| {
+                consumed
+                    .saturating_add(scan_weight)
+                    .saturating_add(pair_weight)
+                    .any_gt(max_weight)
+            };
+            if exceeds(consumed) {
+                return consumed;
+            }
+
+            let mut queue = Self::matchmaking_queue();
+            let mut paired = false;
+            let mut i = 0;
+            while i < queue.len() && !exceeds(consumed) {
+                consumed = consumed.saturating_add(scan_weight);
+                match Self::pair_queued_entry(&mut queue, i) {
+                    Ok(true) => {
+                        consumed = consumed.saturating_add(pair_weight);
+                        paired = true;
+                    }
+                    Ok(false) => i += 1,
+                    Err(_) => break,
+                }
+            }
+
+            if paired {
+                <MatchmakingQueue<T>>::put(queue);
+            }
+
+            consumed
+        }
+
+        // pairs the entry at `i` with the compatible entry closest to its rating, if any, and
+        // removes both from `queue`. Returns whether a match was started
+        pub(crate) fn pair_queued_entry(
+            queue: &mut BoundedVec<QueueEntry<T>, T::MaxQueueLength>,
+            i: usize,
+        ) -> Result<bool, DispatchError> {
+            let closest = queue
+                .iter()
+                .enumerate()
+                .skip(i + 1)
+                .filter(|(_, entry)| entry.is_compatible_with(&queue[i]))
+                .min_by_key(|(_, entry)| entry.rating.abs_diff(queue[i].rating))
+                .map(|(j, _)| j);
+
+            match closest {
+                Some(j) => {
+                    Self::start_queued_match(&queue[i], &queue[j])?;
+                    // j > i, so removing j first keeps i valid
+                    queue.remove(j);
+                    queue.remove(i);
+                    Ok(true)
+                }
+                None => Ok(false),
+            }
+        }
+
+        // creates a match between two queued players and starts it right away,
+        // their stakes are already in escrow
+        fn start_queued_match(white: &QueueEntry<T>, black: &QueueEntry<T>) -> DispatchResult {
+            let nonce = <NextNonce<T>>::get();
+            Self::increment_nonce()?;
+
+            let now = <frame_system::Pallet<T>>::block_number();
+            let board_obj = Board::default();
+            let parameters = Self::chess_parameters();
+            let time_control = Self::time_control(&white.style);
+            let chess_match: Match<T> = Match {
+                challenger: white.player.clone(),
+                opponent: Some(black.player.clone()),
+                board: Self::init_board(),
+                state: MatchState::OnGoing(NextMove::Whites),
+                nonce,
+                style: white.style.clone(),
+                // whites may not notice the match started right away,
+                // so their clock starts when the match is created
+                last_move: now,
+                start: now,
+                bet_asset_id: white.bet_asset_id.clone(),
+                bet_amount: white.bet_amount,
+                draw_offer: None,
+                min_rating: None,
+                max_rating: None,
+                color: ColorPreference::White,
+                white: Some(white.player.clone()),
+                black: Some(black.player.clone()),
+                variant: Variant::Standard,
+                start_board: Self::init_board(),
+                // friendly matches are never rated
+                rated: white.style.is_rated() && !white.bet_amount.is_zero(),
+                white_clock: time_control.base,
+                black_clock: time_control.base,
+                time_control,
+                incentive_share: parameters.incentive_share,
+                k: parameters.k,
+            };
+
+            let match_id =
+                Self::match_id(white.player.clone(), chess_match.opponent.clone(), nonce);
+            Self::deposit_event(Event::MatchCreated(
+                white.player.clone(),
+                chess_match.opponent.clone(),
+                match_id,
+                chess_match.style.clone(),
+                chess_match.bet_asset_id.clone(),
+                chess_match.bet_amount,
+                chess_match.variant.clone(),
+            ));
+
+            <Matches<T>>::insert(match_id, chess_match);
+            <PlayerMatches<T>>::insert(white.player.clone(), match_id, ());
+            <PlayerMatches<T>>::insert(black.player.clone(), match_id, ());
+            <MatchIdFromNonce<T>>::insert(nonce, match_id);
+            Self::record_position(match_id, board_obj.hash(), board_obj.halfmove_clock());
+
+            Self::deposit_event(Event::MatchStarted(
+                match_id,
+                white.player.clone(),
+                black.player.clone(),
+                Self::init_board(),
+            ));
+
+            Ok(())
+        }
+
+        // draws a Chess960 start position, uniformly over the 960 of them. The position is only
+        // as unpredictable as `Config::Randomness`: with a source known ahead of the block (e.g.
+        // `pallet_insecure_randomness_collective_flip`), the opponent can foresee it and pick when to join
+        fn chess960_position(match_id: T::Hash) -> u32 {
+            let (random, _) = T::Randomness::random(&(b"chess/960", match_id).encode());
+            let chunks = random
+                .as_ref()
+                .chunks_exact(2)
+                .map(|chunk| u16::from_le_bytes([chunk[0], chunk[1]]));
+
+            // values from the last incomplete multiple of 960 are rejected, as they'd favor the first positions.
+            // falling back to the last chunk only happens if every chunk is rejected (~0.4% each)
+            let mut last = 0;
+            for seed in chunks {
+                if seed < CHESS960_REJECTION_THRESHOLD {
+                    return (seed % CHESS960_POSITIONS) as u32;
+                }
+                last = seed;
+            }
+            (last % CHESS960_POSITIONS) as u32
+        }
+
+        // assigns colors according to the challenger's preference, once the opponent is known
+        fn assign_colors(match_id: T::Hash, chess_match: &mut Match<T>) -> DispatchResult {
+            let opponent = chess_match.opponent()?;
+            let challenger_plays_white = match chess_match.color {
+                ColorPreference::White => true,
+                ColorPreference::Black => false,
+                ColorPreference::Random => {
+                    let (random, _) = T::Randomness::random(&(b"chess/color", match_id).encode());
+                    random.as_ref().first().map_or(true, |byte| byte % 2 == 0)
+                }
+            };
+
+            if challenger_plays_white {
+                chess_match.white = Some(chess_match.challenger.clone());
+                chess_match.black = Some(opponent);
+            } else {
+                chess_match.white = Some(opponent);
+                chess_match.black = Some(chess_match.challenger.clone());
+            }
+
+            Ok(())
+        }
+
+        // checks that `who` is a player of the match and that their opponent offered a draw
+        fn ensure_draw_offered_to(chess_match: &Match<T>, who: &T::AccountId) -> DispatchResult {
+            if !chess_match.is_player(who) {
+                return Err(Error::<T>::NotMatchPlayer.into());
+            }
+
+            match &chess_match.draw_offer {
+                Some(offerer) if offerer != who => Ok(()),
+                _ => Err(Error::<T>::NoDrawOffer.into()),
+            }
+        }
+
+        fn record_position(match_id: T::Hash, position_hash: u64, halfmove_clock: u8) {
+            <PositionHistory<T>>::mutate(match_id, |history| {
+                // positions before a capture or pawn move can never be repeated
+                if halfmove_clock == 0 {
+                    history.clear();
+                }
+                // drop the oldest position once the history is full
+                if history.len() as u32 >= T::MaxPositionHistory::get() && !history.is_empty() {
+                    history.remove(0);
+                }
+                let _ = history.try_push(position_hash);
+            });
+        }
+
+        // Ends a match won by `winner`: pays out the stakes (minus the incentive of `janitor`, if
+        // any), updates ratings, then archives the match and cleans up storage.
+        // Settlement takes several steps, if one fails the whole call is reverted and the match
+        // stays in storage, so that no stake is left behind.
+        fn settle_win(
+            match_id: T::Hash,
+            mut chess_match: Match<T>,
+            winner: T::AccountId,
+            termination: Termination,
+            janitor: Option<T::AccountId>,
+        ) -> DispatchResult {
+            let loser = chess_match.loser(&winner)?;
+            chess_match.state = MatchState::Won(winner.clone(), termination);
+
+            let payout = match janitor {
+                Some(janitor) => {
+                    // the winner gets both deposits minus the incentive share
+                    chess_match.clear_abandoned_bet(&winner, &janitor)?;
+                    chess_match.janitor_incentive().1
+                }
+                None => {
+                    // the winner gets both deposits
+                    chess_match.win_bet(&winner)?;
+                    chess_match
+                        .bet_amount
+                        .saturating_add(chess_match.bet_amount)
+                }
+            };
+
+            // update ratings, unless the match is unrated
+            if chess_match.rated {
+                Self::update_ratings(
+                    match_id,
+                    chess_match.k,
+                    &chess_match.style,
+                    winner,
+                    loser,
+                    elo::WIN,
+                );
+            }
+
+            Self::archive_match(match_id, &chess_match, payout);
+            Self::remove_match(match_id, &chess_match);
+
+            Ok(())
+        }
+
+        // Ends a match in a draw: refunds both stakes, updates ratings, then archives the match
+        // and cleans up storage. Reverted as a whole if any step fails, like `settle_win`.
+        fn settle_draw(
+            match_id: T::Hash,
+            mut chess_match: Match<T>,
+            termination: Termination,
+        ) -> DispatchResult {
+            chess_match.state = MatchState::Drawn(termination.clone());
+
+            Self::deposit_event(Event::MatchDrawn(
+                match_id,
+                chess_match.board.clone(),
+                termination,
+            ));
+
+            // return deposit to both players
+            chess_match.refund_bets()?;
+
+            // update ratings, unless the match is unrated
+            if chess_match.rated {
+                Self::update_ratings(
+                    match_id,
+                    chess_match.k,
+                    &chess_match.style,
+                    chess_match.white()?,
+                    chess_match.black()?,
+                    elo::DRAW,
+                );
+            }
+
+            Self::archive_match(match_id, &chess_match, chess_match.bet_amount);
+            Self::remove_match(match_id, &chess_match);
+
+            Ok(())
+        }
+
+        fn archive_match(match_id: T::Hash, chess_match: &Match<T>, payout: T::AssetBalance) {
+            let (winner, termination) = match &chess_match.state {
+                MatchState::Won(winner, termination) => (Some(winner.clone()), termination.clone()),
+                MatchState::Drawn(termination) => (None, termination.clone()),
+                // only matches that are over get archived
+                MatchState::AwaitingOpponent | MatchState::OnGoing(_) => return,
+            };
+            let opponent = match &chess_match.opponent {
+                Some(opponent) => opponent.clone(),
+                None => return,
+            };
+
+            let finished_match = FinishedMatch::<T> {
+                challenger: chess_match.challenger.clone(),
+                opponent: opponent.clone(),
+                winner,
+                termination,
+                board: chess_match.board.clone(),
+                end: <frame_system::Pallet<T>>::block_number(),
+                bet_asset_id: chess_match.bet_asset_id.clone(),
+                payout,
+            };
+            let challenger = &chess_match.challenger;
+            match &finished_match.winner {
+                Some(winner) => {
+                    let looser = if winner == challenger {
+                        &opponent
+                    } else {
+                        challenger
+                    };
+                    <PlayerStatistics<T>>::mutate(winner, |stats| {
+                        stats.wins = stats.wins.saturating_add(1)
+                    });
+                    <PlayerStatistics<T>>::mutate(looser, |stats| {
+                        stats.losses = stats.losses.saturating_add(1)
+                    });
+                }
+                None => {
+                    for player in [challenger, &opponent] {
+                        <PlayerStatistics<T>>::mutate(player, |stats| {
+                            stats.draws = stats.draws.saturating_add(1)
+                        });
+                    }
+                }
+            }
+
+            <FinishedMatches<T>>::insert(match_id, finished_match);
+            <PlayerFinishedMatches<T>>::insert(chess_match.challenger.clone(), match_id, ());
+            <PlayerFinishedMatches<T>>::insert(opponent, match_id, ());
+        }
+
+        pub(crate) fn remove_match(match_id: T::Hash, chess_match: &Match<T>) {
+            <Matches<T>>::remove(match_id);
+            <PlayerMatches<T>>::remove(chess_match.challenger.clone(), match_id);
+            match &chess_match.opponent {
+                Some(opponent) => <PlayerMatches<T>>::remove(opponent.clone(), match_id),
+                None => <OpenChallenges<T>>::remove((
+                    chess_match.style.clone(),
+                    chess_match.bet_asset_id.clone(),
+                    match_id,
+                )),
+            }
+            <MatchIdFromNonce<T>>::remove(chess_match.nonce);
+            <PositionHistory<T>>::remove(match_id);
+            <MatchMoves<T>>::remove(match_id);
+            <MoveHistoryTruncated<T>>::remove(match_id);
+        }
+
+        // unlike `Board::status`, the fifty-move rule doesn't end the match on its own (it is
+        // a draw either player may claim with `claim_draw`), while insufficient material does
+        fn game_status(board: &Board) -> GameStatus {
+            let has_legal_moves = board.generate_moves(|_| true);
+            if !has_legal_moves {
+                // checkmate or stalemate
+                if board.checkers().is_empty() {
+                    GameStatus::Drawn
+                } else {
+                    GameStatus::Won
+                }
+            } else if Self::insufficient_material(board) {
+                GameStatus::Drawn
+            } else {
+                GameStatus::Ongoing
+            }
+        }
+
+        // tells apart the different kinds of draws reported by `game_status`
+        fn draw_termination(board: &Board) -> Termination {
+            let has_legal_moves = board.generate_moves(|_| true);
+            if !has_legal_moves {
+                Termination::Stalemate
+            } else if Self::insufficient_material(board) {
+                Termination::InsufficientMaterial
+            } else {
+                Termination::FiftyMoveRule
+            }
+        }
+
+        // neither side can ever checkmate: king against king, or king and a single minor piece
+        // against king
+        fn insufficient_material(board: &Board) -> bool {
+            let others = board.occupied() ^ board.pieces(Piece::King);
+            let minors = board.pieces(Piece::Knight) | board.pieces(Piece::Bishop);
+            others.len() <= 1 && (others & !minors).is_empty()
+        }
+
+        fn match_id(
+            challenger: T::AccountId,
+            opponent: Option<T::AccountId>,
+            nonce: u128,
+        ) -> T::Hash {
             T::Hashing::hash_of(&(challenger, opponent, nonce))
         }
 
@@ -649,16 +2191,24 @@ pub mod pallet {
             format!("{}", Board::default()).as_bytes().to_vec()
         }
 
-        fn encode_board(board: Board) -> Vec<u8> {
-            format!("{}", board).as_bytes().to_vec()
+        fn encode_board(board: Board, variant: &Variant) -> Vec<u8> {
+            match variant {
+                Variant::Standard => format!("{}", board).as_bytes().to_vec(),
+                // Shredder-FEN, since Chess960 castling rights can't always be expressed as KQkq
+                Variant::Chess960 => format!("{:#}", board).as_bytes().to_vec(),
+            }
         }
 
-        fn decode_board(encoded_board: Vec<u8>) -> sp_std::result::Result<Board, Error<T>> {
+        fn decode_board(
+            encoded_board: Vec<u8>,
+            variant: &Variant,
+        ) -> sp_std::result::Result<Board, Error<T>> {
             let s = match from_utf8(encoded_board.as_slice()) {
                 Ok(s) => s,
                 Err(_) => "",
             };
-            match Board::from_str(s) {
+            // Chess960 boards are encoded as Shredder-FEN, standard ones only accept KQkq castling rights
+            match Board::from_fen(s, *variant == Variant::Chess960) {
                 Ok(g) => Ok(g),
                 Err(_) => Err(Error::<T>::InvalidBoardEncoding.into()),
             }
@@ -669,10 +2219,17 @@ pub mod pallet {
                 Ok(s) => s,
                 Err(_) => "",
             };
-            match Move::from_str(s) {
-                Ok(m) => Ok(m),
-                Err(_) => Err(Error::<T>::InvalidMoveEncoding.into()),
+            let m = match Move::from_str(s) {
+                Ok(m) => m,
+                Err(_) => return Err(Error::<T>::InvalidMoveEncoding.into()),
+            };
+            // pawns can only be promoted to knights, bishops, rooks or queens
+            if let Some(piece) = m.promotion {
+                if PromotionPiece::from_piece(piece).is_none() {
+                    return Err(Error::<T>::InvalidMoveEncoding.into());
+                }
             }
+            Ok(m)
         }
 
         // needed for benchmarking
@@ -688,51 +2245,125 @@ pub mod pallet {
 
             chess_match.board = encoded_board.clone();
 
-            let board_obj = Self::decode_board(encoded_board)?;
-            chess_match.state = match board_obj.status() {
+            let board_obj = Self::decode_board(encoded_board, &chess_match.variant)?;
+            chess_match.state = match Self::game_status(&board_obj) {
                 GameStatus::Ongoing => match board_obj.side_to_move() {
                     Color::White => MatchState::OnGoing(NextMove::Whites),
                     Color::Black => MatchState::OnGoing(NextMove::Blacks),
                 },
-                GameStatus::Won => MatchState::Won,
-                GameStatus::Drawn => MatchState::Drawn,
+                // the side to move has been checkmated
+                GameStatus::Won => match board_obj.side_to_move() {
+                    Color::White => MatchState::Won(chess_match.black()?, Termination::Checkmate),
+                    Color::Black => MatchState::Won(chess_match.white()?, Termination::Checkmate),
+                },
+                GameStatus::Drawn => MatchState::Drawn(Self::draw_termination(&board_obj)),
             };
 
-            if chess_match.state == MatchState::Won {
-                // match is over, clean up storage
-                <Matches<T>>::remove(match_id);
-                <PlayerMatches<T>>::remove(chess_match.challenger.clone(), match_id);
-                <PlayerMatches<T>>::remove(chess_match.opponent, match_id);
-                <MatchIdFromNonce<T>>::remove(chess_match.nonce);
-            } else if chess_match.state == MatchState::Drawn {
+            if let MatchState::Won(..) | MatchState::Drawn(_) = chess_match.state {
                 // match is over, clean up storage
-                <Matches<T>>::remove(match_id);
-                <PlayerMatches<T>>::remove(chess_match.challenger.clone(), match_id);
-                <PlayerMatches<T>>::remove(chess_match.opponent, match_id);
-                <MatchIdFromNonce<T>>::remove(chess_match.nonce);
+                Self::remove_match(match_id, &chess_match);
             } else {
                 // match still ongoing, update on-chain board
+                // and restart the position history from the forced board
                 <Matches<T>>::insert(match_id, chess_match);
+                <PositionHistory<T>>::remove(match_id);
+                Self::record_position(match_id, board_obj.hash(), board_obj.halfmove_clock());
             }
 
             Ok(())
         }
 
-        fn update_elo(player1: T::AccountId, score_1: f32, player2: T::AccountId, score_2: f32) {
-            let rating1 = Self::player_elo(&player1) as f32;
-            let rating2 = Self::player_elo(&player2) as f32;
-            let transformed_rating1 = 10_f32.powf(rating1 / 400_f32);
-            let transformed_rating2 = 10_f32.powf(rating2 / 400_f32);
-            let expected_score1 =
-                &transformed_rating1 / (&transformed_rating1 + &transformed_rating2);
-            let expected_score2 =
-                &transformed_rating2 / (&transformed_rating1 + &transformed_rating2);
-            let new_rating1 =
-                (&rating1 + T::K::get() as f32 * (score_1 - expected_score1)).round() as u16;
-            let new_rating2 =
-                (&rating2 + T::K::get() as f32 * (score_2 - expected_score2)).round() as u16;
-            <PlayerElo<T>>::insert(&player1, new_rating1);
-            <PlayerElo<T>>::insert(&player2, new_rating2);
+        // only the rating of the style that was played is updated
+        fn update_ratings(
+            match_id: T::Hash,
+            k: u16,
+            style: &MatchStyle,
+            player1: T::AccountId,
+            player2: T::AccountId,
+            score_1: FixedU128,
+        ) {
+            migrations::v3::migrate_player::<T>(&player1);
+            migrations::v3::migrate_player::<T>(&player2);
+            let now = <frame_system::Pallet<T>>::block_number();
+            let old_rating1 = Self::rating(&player1, style);
+            let old_rating2 = Self::rating(&player2, style);
+
+            // provisional ratings move faster
+            let k_of = |player: &T::AccountId| {
+                if Self::is_provisional(player, style) {
+                    k.max(T::ProvisionalK::get())
+                } else {
+                    k
+                }
+            };
+            let (new_rating1, new_rating2) = T::RatingSystem::update(
+                k_of(&player1),
+                k_of(&player2),
+                &Self::rating_state(&player1, style),
+                &Self::rating_state(&player2, style),
+                score_1,
+                now,
+            );
+            <PlayerRatings<T>>::insert(&player1, style, new_rating1);
+            <PlayerRatings<T>>::insert(&player2, style, new_rating2);
+            for (player, old_rating) in [(&player1, old_rating1), (&player2, old_rating2)] {
+                <RatedGames<T>>::mutate(player, style, |games| *games = games.saturating_add(1));
+                let change = RatingChange {
+                    block: now,
+                    match_id,
+                    old_rating,
+                    new_rating: Self::rating(player, style),
+                };
+                <RatingHistory<T>>::mutate(player, style, |history| {
+                    // the oldest change makes room for the new one
+                    if history.len() >= T::MaxRatingHistory::get() as usize && !history.is_empty() {
+                        history.remove(0);
+                    }
+                    let _ = history.try_push(change);
+                });
+            }
+            Self::update_leaderboard(style, &[(&player1, old_rating1), (&player2, old_rating2)]);
+        }
+
+        // big-endian and inverted, so that higher ratings sort first in `RankedPlayers`
+        fn rank_key(rating: u16) -> [u8; 2] {
+            (u16::MAX - rating).to_be_bytes()
+        }
+
+        // moves the players of a match, with their ratings before it, to their new rank in
+        // `RankedPlayers`, then takes the top of the style as its leaderboard
+        fn update_leaderboard(style: &MatchStyle, players: &[(&T::AccountId, u16)]) {
+            let previous: Vec<T::AccountId> = Self::leaderboard(style)
+                .into_iter()
+                .map(|(player, _)| player)
+                .collect();
+
+            for (player, old_rating) in players {
+                <RankedPlayers<T>>::remove(style, (Self::rank_key(*old_rating), (*player).clone()));
+                if Self::rated_games(*player, style) >= T::MinLeaderboardGames::get() {
+                    let rating = Self::rating(player, style);
+                    <RankedPlayers<T>>::insert(
+                        style,
+                        (Self::rank_key(rating), (*player).clone()),
+                        (),
+                    );
+                }
+            }
+            let leaderboard: Vec<(T::AccountId, u16)> = <RankedPlayers<T>>::iter_key_prefix(style)
+                .take(T::LeaderboardSize::get() as usize)
+                .map(|(key, player)| (player, u16::MAX - u16::from_be_bytes(key)))
+                .collect();
+
+            let ranked: Vec<T::AccountId> = leaderboard
+                .iter()
+                .map(|(player, _)| player.clone())
+                .collect();
+            let changed = ranked.len() != previous.len()
+                || ranked.iter().any(|player| !previous.contains(player));
+            <Leaderboards<T>>::insert(style, BoundedVec::truncate_from(leaderboard));
+            if changed {
+                Self::deposit_event(Event::LeaderboardChanged(style.clone(), ranked));
+            }
         }
     }
 }
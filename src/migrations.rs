@@ -0,0 +1,678 @@
+//! Storage migrations for the chess pallet.
+
+use crate::pallet::{
+    ColorPreference, Config, Match, MatchState, MatchStyle, Matches, NextMove, Pallet, TimeControl,
+};
+use crate::{rating::RatingSystem, AssetIdOf, Variant};
+use cozy_chess::Board;
+use frame_support::{
+    pallet_prelude::*,
+    traits::{GetStorageVersion, OnRuntimeUpgrade, StorageVersion},
+    weights::Weight,
+};
+use frame_system::pallet_prelude::BlockNumberFor;
+use scale_info::prelude::format;
+use sp_std::vec::Vec;
+
+pub mod v1 {
+    use super::*;
+
+    /// `MatchState` before the winner and termination reason were recorded.
+    #[derive(Clone, Debug, Encode, Decode, TypeInfo, PartialEq)]
+    pub enum OldMatchState {
+        AwaitingOpponent,
+        OnGoing(NextMove),
+        Won,
+        Drawn,
+    }
+
+    /// `Match` before draw offers and `MatchState` v1.
+    #[derive(Clone, Debug, Encode, Decode, TypeInfo, PartialEq)]
+    #[scale_info(skip_type_params(T))]
+    pub struct OldMatch<T: Config> {
+        pub challenger: T::AccountId,
+        pub opponent: T::AccountId,
+        pub board: Vec<u8>,
+        pub state: OldMatchState,
+        pub nonce: u128,
+        pub style: MatchStyle,
+        pub last_move: BlockNumberFor<T>,
+        pub start: BlockNumberFor<T>,
+        pub bet_asset_id: AssetIdOf<T>,
+        pub bet_amount: T::AssetBalance,
+    }
+
+    /// `Matches` as stored in v1, before open challenges.
+    #[frame_support::storage_alias]
+    pub(crate) type Matches<T: Config> = StorageMap<
+        Pallet<T>,
+        Twox64Concat,
+        <T as frame_system::Config>::Hash,
+        super::v2::OldMatch<T>,
+    >;
+
+    /// Translates every stored `Match` into the v1 layout.
+    ///
+    /// Finished matches are removed from storage as soon as they end, so `Won` and `Drawn` are
+    /// not expected here. Should one show up anyway, it is dropped since its winner can't be
+    /// recovered.
+    pub struct MigrateToV1<T>(PhantomData<T>);
+
+    impl<T: Config> OnRuntimeUpgrade for MigrateToV1<T> {
+        fn on_runtime_upgrade() -> Weight {
+            let on_chain_version = Pallet::<T>::on_chain_storage_version();
+            if on_chain_version >= 1 {
+                log::info!(
+                    target: "runtime::chess",
+                    "MigrateToV1 skipped, on-chain storage version is {:?}",
+                    on_chain_version
+                );
+                return T::DbWeight::get().reads(1);
+            }
+
+            let mut translated = 0u64;
+            let mut dropped = 0u64;
+            Matches::<T>::translate::<OldMatch<T>, _>(|match_id, old| {
+                let state = match old.state {
+                    OldMatchState::AwaitingOpponent => MatchState::AwaitingOpponent,
+                    OldMatchState::OnGoing(next_move) => MatchState::OnGoing(next_move),
+                    OldMatchState::Won | OldMatchState::Drawn => {
+                        log::warn!(
+                            target: "runtime::chess",
+                            "dropping finished match {:?} during MigrateToV1",
+                            match_id
+                        );
+                        dropped += 1;
+                        return None;
+                    }
+                };
+                translated += 1;
+                Some(super::v2::OldMatch {
+                    challenger: old.challenger,
+                    opponent: old.opponent,
+                    board: old.board,
+                    state,
+                    nonce: old.nonce,
+                    style: old.style,
+                    last_move: old.last_move,
+                    start: old.start,
+                    bet_asset_id: old.bet_asset_id,
+                    bet_amount: old.bet_amount,
+                    draw_offer: None,
+                })
+            });
+
+            StorageVersion::new(1).put::<Pallet<T>>();
+            log::info!(
+                target: "runtime::chess",
+                "MigrateToV1 translated {} matches, dropped {}",
+                translated,
+                dropped
+            );
+
+            let items = translated + dropped;
+            T::DbWeight::get().reads_writes(items + 1, items + 1)
+        }
+
+        #[cfg(feature = "try-runtime")]
+        fn pre_upgrade() -> Result<Vec<u8>, sp_runtime::TryRuntimeError> {
+            Ok((Matches::<T>::iter_keys().count() as u32).encode())
+        }
+
+        #[cfg(feature = "try-runtime")]
+        fn post_upgrade(state: Vec<u8>) -> Result<(), sp_runtime::TryRuntimeError> {
+            let old_count = u32::decode(&mut &state[..])
+                .map_err(|_| "MigrateToV1: failed to decode pre_upgrade state")?;
+            ensure!(
+                Matches::<T>::iter_values().count() as u32 <= old_count,
+                "MigrateToV1: more matches after the upgrade than before"
+            );
+            ensure!(
+                Pallet::<T>::on_chain_storage_version() >= 1,
+                "MigrateToV1: storage version not updated"
+            );
+            Ok(())
+        }
+    }
+}
+
+pub mod v2 {
+    use super::*;
+
+    /// `Match` before open challenges, when the opponent was always known.
+    #[derive(Clone, Debug, Encode, Decode, TypeInfo, PartialEq)]
+    #[scale_info(skip_type_params(T))]
+    pub struct OldMatch<T: Config> {
+        pub challenger: T::AccountId,
+        pub opponent: T::AccountId,
+        pub board: Vec<u8>,
+        pub state: MatchState<T::AccountId>,
+        pub nonce: u128,
+        pub style: MatchStyle,
+        pub last_move: BlockNumberFor<T>,
+        pub start: BlockNumberFor<T>,
+        pub bet_asset_id: AssetIdOf<T>,
+        pub bet_amount: T::AssetBalance,
+        pub draw_offer: Option<T::AccountId>,
+    }
+
+    /// Makes the opponent of every stored `Match` optional.
+    pub struct MigrateToV2<T>(PhantomData<T>);
+
+    impl<T: Config> OnRuntimeUpgrade for MigrateToV2<T> {
+        fn on_runtime_upgrade() -> Weight {
+            let on_chain_version = Pallet::<T>::on_chain_storage_version();
+            if on_chain_version != 1 {
+                log::info!(
+                    target: "runtime::chess",
+                    "MigrateToV2 skipped, on-chain storage version is {:?}",
+                    on_chain_version
+                );
+                return T::DbWeight::get().reads(1);
+            }
+
+            let mut translated = 0u64;
+            let parameters = Pallet::<T>::chess_parameters();
+            Matches::<T>::translate::<OldMatch<T>, _>(|_, old| {
+                translated += 1;
+                // challengers always played whites
+                let (white, black) = match old.state {
+                    MatchState::AwaitingOpponent => (None, None),
+                    _ => (Some(old.challenger.clone()), Some(old.opponent.clone())),
+                };
+                // matches used to have a fixed deadline per move, whatever their style
+                let time_control =
+                    TimeControl::per_move(Pallet::<T>::time_control(&old.style).per_move);
+                Some(Match {
+                    challenger: old.challenger,
+                    opponent: Some(old.opponent),
+                    board: old.board,
+                    state: old.state,
+                    nonce: old.nonce,
+                    style: old.style,
+                    last_move: old.last_move,
+                    start: old.start,
+                    bet_asset_id: old.bet_asset_id,
+                    bet_amount: old.bet_amount,
+                    draw_offer: old.draw_offer,
+                    min_rating: None,
+                    max_rating: None,
+                    color: ColorPreference::White,
+                    white,
+                    black,
+                    variant: Variant::Standard,
+                    start_board: format!("{}", Board::default()).as_bytes().to_vec(),
+                    rated: true,
+                    white_clock: time_control.base,
+                    black_clock: time_control.base,
+                    time_control,
+                    incentive_share: parameters.incentive_share,
+                    k: parameters.k,
+                })
+            });
+
+            StorageVersion::new(2).put::<Pallet<T>>();
+            log::info!(
+                target: "runtime::chess",
+                "MigrateToV2 translated {} matches",
+                translated
+            );
+
+            T::DbWeight::get().reads_writes(translated + 1, translated + 1)
+        }
+
+        #[cfg(feature = "try-runtime")]
+        fn pre_upgrade() -> Result<Vec<u8>, sp_runtime::TryRuntimeError> {
+            Ok((Matches::<T>::iter_keys().count() as u32).encode())
+        }
+
+        #[cfg(feature = "try-runtime")]
+        fn post_upgrade(state: Vec<u8>) -> Result<(), sp_runtime::TryRuntimeError> {
+            let old_count = u32::decode(&mut &state[..])
+                .map_err(|_| "MigrateToV2: failed to decode pre_upgrade state")?;
+            ensure!(
+                Matches::<T>::iter_values().count() as u32 == old_count,
+                "MigrateToV2: number of matches changed during the upgrade"
+            );
+            ensure!(
+                Matches::<T>::iter_values().all(|m| m.opponent.is_some()),
+                "MigrateToV2: match without opponent"
+            );
+            ensure!(
+                Pallet::<T>::on_chain_storage_version() >= 2,
+                "MigrateToV2: storage version not updated"
+            );
+            Ok(())
+        }
+    }
+}
+
+pub mod v3 {
+    use super::*;
+    use crate::pallet::{EstablishedRatings, PlayerRatings, RatingStateOf};
+
+    /// `PlayerElo` as stored in v2, a single rating shared by every style.
+    #[frame_support::storage_alias]
+    pub(crate) type PlayerElo<T: Config> =
+        StorageMap<Pallet<T>, Twox64Concat, <T as frame_system::Config>::AccountId, u16>;
+
+    /// Only bumps the storage version, since copying every rating at once isn't bounded.
+    ///
+    /// The single rating of every player is copied into each of the preset styles lazily instead,
+    /// as a new state of `Config::RatingSystem`, and marked as established:
+    /// - before the ratings of a player are updated, see `migrate_player`
+    /// - a few players per block in `on_idle`, see `migrate_players`
+    ///
+    /// Until then, `Pallet::rating` and `Pallet::is_provisional` read the rating from `PlayerElo`.
+    pub struct MigrateToV3<T>(PhantomData<T>);
+
+    // copies `elo` into every preset style that has no rating of its own yet
+    fn migrate_rating<T: Config>(player: &T::AccountId, elo: u16) {
+        for style in MatchStyle::RATED.iter() {
+            if !PlayerRatings::<T>::contains_key(player, style) {
+                PlayerRatings::<T>::insert(player, style, T::RatingSystem::new_state(elo));
+                EstablishedRatings::<T>::insert(player, style, true);
+            }
+        }
+    }
+
+    /// Migrates the rating of `player` from `PlayerElo`, if it is still there
+    pub(crate) fn migrate_player<T: Config>(player: &T::AccountId) {
+        if let Some(elo) = PlayerElo::<T>::take(player) {
+            migrate_rating::<T>(player, elo);
+        }
+    }
+
+    /// Migrates as many ratings from `PlayerElo` as `max_weight` allows, returns the weight used
+    pub(crate) fn migrate_players<T: Config>(max_weight: Weight) -> Weight {
+        let mut consumed = T::DbWeight::get().reads(1);
+        if consumed.any_gt(max_weight) {
+            return Weight::zero();
+        }
+        let styles = MatchStyle::RATED.len() as u64;
+        let per_player = T::DbWeight::get().reads_writes(1 + styles, 1 + styles * 2);
+
+        let mut legacy = PlayerElo::<T>::drain();
+        while !consumed.saturating_add(per_player).any_gt(max_weight) {
+            match legacy.next() {
+                Some((player, elo)) => migrate_rating::<T>(&player, elo),
+                None => break,
+            }
+            consumed = consumed.saturating_add(per_player);
+        }
+        consumed
+    }
+
+    /// Rating state of `player` in `style` from `PlayerElo`, if it wasn't migrated yet
+    pub(crate) fn legacy_rating<T: Config>(
+        player: &T::AccountId,
+        style: &MatchStyle,
+    ) -> Option<RatingStateOf<T>> {
+        if !MatchStyle::RATED.contains(style) {
+            return None;
+        }
+        PlayerElo::<T>::get(player).map(T::RatingSystem::new_state)
+    }
+
+    impl<T: Config> OnRuntimeUpgrade for MigrateToV3<T> {
+        fn on_runtime_upgrade() -> Weight {
+            let on_chain_version = Pallet::<T>::on_chain_storage_version();
+            if on_chain_version != 2 {
+                log::info!(
+                    target: "runtime::chess",
+                    "MigrateToV3 skipped, on-chain storage version is {:?}",
+                    on_chain_version
+                );
+                return T::DbWeight::get().reads(1);
+            }
+
+            StorageVersion::new(3).put::<Pallet<T>>();
+            log::info!(
+                target: "runtime::chess",
+                "MigrateToV3 done, ratings are migrated lazily"
+            );
+
+            T::DbWeight::get().reads_writes(1, 1)
+        }
+
+        #[cfg(feature = "try-runtime")]
+        fn pre_upgrade() -> Result<Vec<u8>, sp_runtime::TryRuntimeError> {
+            Ok(PlayerElo::<T>::iter().collect::<Vec<_>>().encode())
+        }
+
+        #[cfg(feature = "try-runtime")]
+        fn post_upgrade(state: Vec<u8>) -> Result<(), sp_runtime::TryRuntimeError> {
+            let ratings = Vec::<(T::AccountId, u16)>::decode(&mut &state[..])
+                .map_err(|_| "MigrateToV3: failed to decode pre_upgrade state")?;
+            for (player, elo) in ratings {
+                for style in MatchStyle::RATED.iter() {
+                    ensure!(
+                        Pallet::<T>::rating(&player, style)
+                            == T::RatingSystem::rating(&T::RatingSystem::new_state(elo)),
+                        "MigrateToV3: legacy rating not visible after the upgrade"
+                    );
+                }
+            }
+            ensure!(
+                Pallet::<T>::on_chain_storage_version() >= 3,
+                "MigrateToV3: storage version not updated"
+            );
+            Ok(())
+        }
+    }
+}
+
+pub mod v4 {
+    use super::*;
+    use crate::pallet::{EstablishedRatings, PlayerRatings};
+
+    /// Marks every rating that existed before games were counted as established, so upgrading
+    /// doesn't make them provisional again. `RatedGames` only counts matches from the upgrade on.
+    pub struct MigrateToV4<T>(PhantomData<T>);
+
+    impl<T: Config> OnRuntimeUpgrade for MigrateToV4<T> {
+        fn on_runtime_upgrade() -> Weight {
+            let on_chain_version = Pallet::<T>::on_chain_storage_version();
+            if on_chain_version != 3 {
+                log::info!(
+                    target: "runtime::chess",
+                    "MigrateToV4 skipped, on-chain storage version is {:?}",
+                    on_chain_version
+                );
+                return T::DbWeight::get().reads(1);
+            }
+
+            let mut migrated = 0u64;
+            for (player, style) in PlayerRatings::<T>::iter_keys() {
+                EstablishedRatings::<T>::insert(player, style, true);
+                migrated += 1;
+            }
+
+            StorageVersion::new(4).put::<Pallet<T>>();
+            log::info!(
+                target: "runtime::chess",
+                "MigrateToV4 marked {} ratings as established",
+                migrated
+            );
+
+            T::DbWeight::get().reads_writes(migrated + 1, migrated + 1)
+        }
+
+        #[cfg(feature = "try-runtime")]
+        fn post_upgrade(_state: Vec<u8>) -> Result<(), sp_runtime::TryRuntimeError> {
+            ensure!(
+                PlayerRatings::<T>::iter_keys()
+                    .all(|(player, style)| !Pallet::<T>::is_provisional(&player, &style)),
+                "MigrateToV4: provisional rating after the upgrade"
+            );
+            ensure!(
+                Pallet::<T>::on_chain_storage_version() >= 4,
+                "MigrateToV4: storage version not updated"
+            );
+            Ok(())
+        }
+    }
+}
+
+pub mod v5 {
+    use super::*;
+    use crate::{
+        pallet::{ConfigHelper, MatchmakingQueue},
+        stakes::Stakes,
+    };
+    use frame_support::{
+        storage::with_storage_layer,
+        traits::{fungibles::Mutate, tokens::Preservation},
+    };
+    use sp_runtime::{traits::Zero, DispatchResult};
+
+    /// Moves every stake escrowed in the pallet account to `Config::Stakes`, e.g. back to its
+    /// player as a hold with `stakes::Holds`. Only bumps the storage version when `Config::Stakes`
+    /// keeps stakes in the pallet account, since there is nothing to move then.
+    ///
+    /// Stakes are escrowed for open challenges (challenger only), matches in progress (both
+    /// players) and matchmaking queue entries. Finished matches are already paid out. Every match
+    /// and queue entry is migrated on its own: if its stakes can't be put on hold, it is cancelled
+    /// and its stakes are refunded from the pallet account instead. If a refund fails as well, the
+    /// match or queue entry is left as it is, with its stakes stuck in the pallet account, and an
+    /// error is logged. `post_upgrade` fails if any stake is left in the pallet account.
+    pub struct MigrateToV5<T>(PhantomData<T>);
+
+    // what the migration went through, for logs and weight
+    #[derive(Default)]
+    struct Counts {
+        // stakes put on hold or refunded
+        stakes: u64,
+        // matches and queue entries cancelled, since their stakes couldn't be put on hold
+        cancelled: u64,
+        // matches and queue entries left as they are, since their stakes couldn't be refunded
+        stuck: u64,
+        reads: u64,
+        writes: u64,
+    }
+
+    // what happened to the stakes of a match or queue entry
+    #[derive(PartialEq)]
+    enum Outcome {
+        Held,
+        Refunded,
+        Stuck,
+    }
+
+    impl<T: Config> MigrateToV5<T> {
+        // players whose stake is escrowed for a match
+        fn staked_players(chess_match: &Match<T>) -> Vec<T::AccountId> {
+            if chess_match.bet_amount.is_zero() {
+                return Vec::new();
+            }
+            match chess_match.state {
+                MatchState::AwaitingOpponent => sp_std::vec![chess_match.challenger.clone()],
+                MatchState::OnGoing(_) => sp_std::iter::once(chess_match.challenger.clone())
+                    .chain(chess_match.opponent.clone())
+                    .collect(),
+                MatchState::Won(..) | MatchState::Drawn(_) => Vec::new(),
+            }
+        }
+
+        // pays the stake of every player back out of the pallet account, to `Config::Stakes` if
+        // `hold` is set. Either every stake is paid back, or none is
+        fn pay_back(
+            asset_id: &AssetIdOf<T>,
+            players: &[T::AccountId],
+            amount: T::AssetBalance,
+            hold: bool,
+        ) -> DispatchResult {
+            with_storage_layer(|| {
+                for player in players {
+                    T::Assets::transfer(
+                        asset_id.clone(),
+                        &T::pallet_account(),
+                        player,
+                        amount,
+                        Preservation::Expendable,
+                    )?;
+                    if hold {
+                        Pallet::<T>::hold_stake(asset_id.clone(), player, amount)?;
+                    }
+                }
+                Ok(())
+            })
+        }
+
+        // puts the stakes of `players` on hold, or refunds them if that isn't possible
+        fn migrate_stakes(
+            asset_id: &AssetIdOf<T>,
+            players: &[T::AccountId],
+            amount: T::AssetBalance,
+            counts: &mut Counts,
+        ) -> Outcome {
+            // every stake reads and writes the pallet account, the player account and its holds
+            counts.stakes += players.len() as u64;
+            counts.reads += players.len() as u64 * 3;
+            counts.writes += players.len() as u64 * 3;
+            let Err(e) = Self::pay_back(asset_id, players, amount, true) else {
+                return Outcome::Held;
+            };
+            log::warn!(
+                target: "runtime::chess",
+                "MigrateToV5 couldn't put the stakes of {:?} on hold, refunding them: {:?}",
+                players,
+                e
+            );
+            if let Err(e) = Self::pay_back(asset_id, players, amount, false) {
+                log::error!(
+                    target: "runtime::chess",
+                    "MigrateToV5 couldn't refund the stakes of {:?}, they stay in the pallet \
+                     account: {:?}",
+                    players,
+                    e
+                );
+                counts.stuck += 1;
+                return Outcome::Stuck;
+            }
+            counts.cancelled += 1;
+            Outcome::Refunded
+        }
+
+        fn migrate(counts: &mut Counts) {
+            let matches: Vec<_> = Matches::<T>::iter().collect();
+            counts.reads += matches.len() as u64;
+            for (match_id, chess_match) in matches {
+                let players = Self::staked_players(&chess_match);
+                if players.is_empty() {
+                    continue;
+                }
+                let outcome = Self::migrate_stakes(
+                    &chess_match.bet_asset_id,
+                    &players,
+                    chess_match.bet_amount,
+                    counts,
+                );
+                if outcome == Outcome::Refunded {
+                    log::warn!(
+                        target: "runtime::chess",
+                        "MigrateToV5 cancelled match {:?}",
+                        match_id
+                    );
+                    Pallet::<T>::remove_match(match_id, &chess_match);
+                    counts.writes += 7;
+                }
+            }
+
+            counts.reads += 1;
+            let mut kept = Vec::new();
+            for entry in MatchmakingQueue::<T>::get() {
+                let players = sp_std::vec![entry.player.clone()];
+                if entry.bet_amount.is_zero()
+                    || Self::migrate_stakes(&entry.bet_asset_id, &players, entry.bet_amount, counts)
+                        != Outcome::Refunded
+                {
+                    kept.push(entry);
+                }
+            }
+            MatchmakingQueue::<T>::put(BoundedVec::truncate_from(kept));
+            counts.writes += 1;
+        }
+
+        // stakes escrowed in the pallet account, per asset
+        #[cfg(feature = "try-runtime")]
+        fn escrow() -> Vec<(AssetIdOf<T>, T::AssetBalance)> {
+            use sp_runtime::Saturating;
+
+            let mut escrow: Vec<(AssetIdOf<T>, T::AssetBalance)> = Vec::new();
+            let mut add = |asset_id: AssetIdOf<T>, amount: T::AssetBalance| match escrow
+                .iter_mut()
+                .find(|(id, _)| *id == asset_id)
+            {
+                Some((_, total)) => *total = total.saturating_add(amount),
+                None => escrow.push((asset_id, amount)),
+            };
+            for chess_match in Matches::<T>::iter_values() {
+                for _ in Self::staked_players(&chess_match) {
+                    add(chess_match.bet_asset_id.clone(), chess_match.bet_amount);
+                }
+            }
+            for entry in MatchmakingQueue::<T>::get() {
+                add(entry.bet_asset_id, entry.bet_amount);
+            }
+            escrow
+        }
+    }
+
+    impl<T: Config> OnRuntimeUpgrade for MigrateToV5<T> {
+        fn on_runtime_upgrade() -> Weight {
+            let on_chain_version = Pallet::<T>::on_chain_storage_version();
+            if on_chain_version != 4 {
+                log::info!(
+                    target: "runtime::chess",
+                    "MigrateToV5 skipped, on-chain storage version is {:?}",
+                    on_chain_version
+                );
+                return T::DbWeight::get().reads(1);
+            }
+
+            let mut counts = Counts::default();
+            if !T::Stakes::in_pallet_account() {
+                Self::migrate(&mut counts);
+            }
+            StorageVersion::new(5).put::<Pallet<T>>();
+            if counts.stuck > 0 {
+                log::error!(
+                    target: "runtime::chess",
+                    "MigrateToV5 left {} matches or queue entries with their stakes in the \
+                     pallet account",
+                    counts.stuck
+                );
+            }
+            log::info!(
+                target: "runtime::chess",
+                "MigrateToV5 moved {} stakes, {} matches or queue entries were cancelled",
+                counts.stakes,
+                counts.cancelled
+            );
+
+            T::DbWeight::get().reads_writes(counts.reads + 1, counts.writes + 1)
+        }
+
+        // the pallet account must be left without any escrow, so its balance of every asset must
+        // drop by the whole escrow
+        #[cfg(feature = "try-runtime")]
+        fn pre_upgrade() -> Result<Vec<u8>, sp_runtime::TryRuntimeError> {
+            use frame_support::traits::fungibles::Inspect;
+
+            if Pallet::<T>::on_chain_storage_version() != 4 || T::Stakes::in_pallet_account() {
+                return Ok(Vec::<(AssetIdOf<T>, T::AssetBalance, T::AssetBalance)>::new().encode());
+            }
+            let balances: Vec<_> = Self::escrow()
+                .into_iter()
+                .map(|(asset_id, escrow)| {
+                    let balance = T::Assets::balance(asset_id.clone(), &T::pallet_account());
+                    (asset_id, balance, escrow)
+                })
+                .collect();
+            Ok(balances.encode())
+        }
+
+        #[cfg(feature = "try-runtime")]
+        fn post_upgrade(state: Vec<u8>) -> Result<(), sp_runtime::TryRuntimeError> {
+            use frame_support::traits::fungibles::Inspect;
+            use sp_runtime::Saturating;
+
+            ensure!(
+                Pallet::<T>::on_chain_storage_version() >= 5,
+                "MigrateToV5: storage version not updated"
+            );
+            let balances =
+                Vec::<(AssetIdOf<T>, T::AssetBalance, T::AssetBalance)>::decode(&mut &state[..])
+                    .map_err(|_| "MigrateToV5: failed to decode pre_upgrade state")?;
+            for (asset_id, balance, escrow) in balances {
+                ensure!(
+                    T::Assets::balance(asset_id, &T::pallet_account())
+                        == balance.saturating_sub(escrow),
+                    "MigrateToV5: stakes left in the pallet account"
+                );
+            }
+            Ok(())
+        }
+    }
+}
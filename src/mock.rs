@@ -1,14 +1,21 @@
-use crate::{self as pallet_chess};
+use crate::{self as pallet_chess, stakes::Stakes};
 use frame_support::{
     parameter_types,
-    traits::{AsEnsureOriginWithArg, ConstU16, ConstU32, ConstU64},
-    PalletId,
+    storage::types::{Key, ValueQuery},
+    traits::{
+        fungibles::{self, Dust},
+        tokens::{
+            DepositConsequence, Fortitude, Precision, Preservation, Provenance, WithdrawConsequence,
+        },
+        AsEnsureOriginWithArg, ConstU16, ConstU32, ConstU64, Randomness,
+    },
+    PalletId, Twox64Concat,
 };
 use frame_system as system;
 use sp_core::H256;
 use sp_runtime::{
     traits::{BlakeTwo256, IdentityLookup},
-    BuildStorage,
+    BuildStorage, DispatchError, DispatchResult,
 };
 
 type Block = frame_system::mocking::MockBlock<Test>;
@@ -53,14 +60,40 @@ parameter_types! {
     pub const BulletPeriod: u64 = 10;
     pub const BlitzPeriod: u64 = 50;
     pub const RapidPeriod: u64 = 150;
+    pub const BulletIncrement: u64 = 0;
+    pub const BlitzIncrement: u64 = 1;
+    pub const RapidIncrement: u64 = 2;
     pub const DailyPeriod: u64 = 14400;
+    pub const MinTimeControl: u64 = 5;
+    pub const MaxTimeControl: u64 = 432000; // 30 days
     pub const ChessPalletId: PalletId = PalletId(*b"subchess");
     pub const IncentiveShare: u8 = 10; // janitor gets 10% of the prize
+    pub const MaxPositionHistory: u32 = 128;
+    pub const MaxMoves: u32 = 1024;
+    pub const FinishedMatchRetention: u64 = 100;
+    pub const ChallengeExpiry: u64 = 14400;
+    pub const MaxQueueLength: u32 = 16;
+    pub const LeaderboardSize: u32 = 3;
+    pub const MaxRatingHistory: u32 = 4;
+    // every byte of the mocked random output, see `TestRandomness`
+    pub static RandomSeed: u8 = 0;
+    // stakes are kept with `stakes::Holds` instead of `stakes::PalletAccount`, see `TestStakes`
+    pub static UseHolds: bool = false;
+    pub MatchStakeReason: RuntimeHoldReason = pallet_chess::HoldReason::MatchStake.into();
+}
+
+pub struct TestRandomness;
+impl Randomness<H256, u64> for TestRandomness {
+    fn random(_subject: &[u8]) -> (H256, u64) {
+        (H256::repeat_byte(RandomSeed::get()), System::block_number())
+    }
 }
 
 impl pallet_chess::Config for Test {
     type PalletId = ChessPalletId;
     type K = ConstU16<32>;
+    type ProvisionalGames = ConstU32<2>;
+    type ProvisionalK = ConstU16<64>;
     type RuntimeEvent = RuntimeEvent;
     type WeightInfo = pallet_chess::weights::SubstrateWeight<Test>;
     type Assets = Assets;
@@ -68,8 +101,25 @@ impl pallet_chess::Config for Test {
     type BulletPeriod = BulletPeriod;
     type BlitzPeriod = BlitzPeriod;
     type RapidPeriod = RapidPeriod;
+    type BulletIncrement = BulletIncrement;
+    type BlitzIncrement = BlitzIncrement;
+    type RapidIncrement = RapidIncrement;
     type DailyPeriod = DailyPeriod;
+    type MinTimeControl = MinTimeControl;
+    type MaxTimeControl = MaxTimeControl;
     type IncentiveShare = IncentiveShare;
+    type MaxPositionHistory = MaxPositionHistory;
+    type MaxMoves = MaxMoves;
+    type FinishedMatchRetention = FinishedMatchRetention;
+    type ChallengeExpiry = ChallengeExpiry;
+    type MaxQueueLength = MaxQueueLength;
+    type MaxRatingHistory = MaxRatingHistory;
+    type LeaderboardSize = LeaderboardSize;
+    type MinLeaderboardGames = ConstU32<2>;
+    type Randomness = TestRandomness;
+    type AdminOrigin = frame_system::EnsureRoot<u64>;
+    type RatingSystem = pallet_chess::elo::Elo;
+    type Stakes = TestStakes;
 }
 
 impl pallet_balances::Config for Test {
@@ -109,6 +159,178 @@ impl pallet_assets::Config for Test {
     type WeightInfo = ();
 }
 
+// `pallet_assets` doesn't support holds, so they are kept next to it in order to test
+// `stakes::Holds`. Free balances stay in `pallet_assets`, holds are kept per reason.
+#[frame_support::storage_alias]
+pub type AssetHolds = StorageNMap<
+    MockAssets,
+    (
+        Key<Twox64Concat, u32>,
+        Key<Twox64Concat, u64>,
+        Key<Twox64Concat, RuntimeHoldReason>,
+    ),
+    u64,
+    ValueQuery,
+>;
+
+fn total_on_hold(asset: u32, who: &u64) -> u64 {
+    AssetHolds::iter_prefix_values((asset, *who)).sum()
+}
+
+pub struct AssetsWithHolds;
+
+impl fungibles::Inspect<u64> for AssetsWithHolds {
+    type AssetId = u32;
+    type Balance = u64;
+
+    fn total_issuance(asset: u32) -> u64 {
+        <Assets as fungibles::Inspect<u64>>::total_issuance(asset)
+    }
+
+    fn minimum_balance(asset: u32) -> u64 {
+        <Assets as fungibles::Inspect<u64>>::minimum_balance(asset)
+    }
+
+    fn total_balance(asset: u32, who: &u64) -> u64 {
+        <Assets as fungibles::Inspect<u64>>::total_balance(asset, who) + total_on_hold(asset, who)
+    }
+
+    fn balance(asset: u32, who: &u64) -> u64 {
+        <Assets as fungibles::Inspect<u64>>::balance(asset, who)
+    }
+
+    fn reducible_balance(
+        asset: u32,
+        who: &u64,
+        preservation: Preservation,
+        force: Fortitude,
+    ) -> u64 {
+        <Assets as fungibles::Inspect<u64>>::reducible_balance(asset, who, preservation, force)
+    }
+
+    fn can_deposit(
+        asset: u32,
+        who: &u64,
+        amount: u64,
+        provenance: Provenance,
+    ) -> DepositConsequence {
+        <Assets as fungibles::Inspect<u64>>::can_deposit(asset, who, amount, provenance)
+    }
+
+    fn can_withdraw(asset: u32, who: &u64, amount: u64) -> WithdrawConsequence<u64> {
+        <Assets as fungibles::Inspect<u64>>::can_withdraw(asset, who, amount)
+    }
+
+    fn asset_exists(asset: u32) -> bool {
+        <Assets as fungibles::Inspect<u64>>::asset_exists(asset)
+    }
+}
+
+impl fungibles::Unbalanced<u64> for AssetsWithHolds {
+    fn handle_dust(dust: Dust<u64, Self>) {
+        <Assets as fungibles::Unbalanced<u64>>::handle_dust(Dust(dust.0, dust.1))
+    }
+
+    fn write_balance(asset: u32, who: &u64, amount: u64) -> Result<Option<u64>, DispatchError> {
+        <Assets as fungibles::Unbalanced<u64>>::write_balance(asset, who, amount)
+    }
+
+    fn set_total_issuance(asset: u32, amount: u64) {
+        <Assets as fungibles::Unbalanced<u64>>::set_total_issuance(asset, amount)
+    }
+
+    fn decrease_balance(
+        asset: u32,
+        who: &u64,
+        amount: u64,
+        precision: Precision,
+        preservation: Preservation,
+        force: Fortitude,
+    ) -> Result<u64, DispatchError> {
+        <Assets as fungibles::Unbalanced<u64>>::decrease_balance(
+            asset,
+            who,
+            amount,
+            precision,
+            preservation,
+            force,
+        )
+    }
+
+    fn increase_balance(
+        asset: u32,
+        who: &u64,
+        amount: u64,
+        precision: Precision,
+    ) -> Result<u64, DispatchError> {
+        <Assets as fungibles::Unbalanced<u64>>::increase_balance(asset, who, amount, precision)
+    }
+}
+
+impl fungibles::Mutate<u64> for AssetsWithHolds {}
+
+impl fungibles::InspectHold<u64> for AssetsWithHolds {
+    type Reason = RuntimeHoldReason;
+
+    fn total_balance_on_hold(asset: u32, who: &u64) -> u64 {
+        total_on_hold(asset, who)
+    }
+
+    fn balance_on_hold(asset: u32, reason: &RuntimeHoldReason, who: &u64) -> u64 {
+        AssetHolds::get((asset, who, reason))
+    }
+}
+
+impl fungibles::UnbalancedHold<u64> for AssetsWithHolds {
+    fn set_balance_on_hold(
+        asset: u32,
+        reason: &RuntimeHoldReason,
+        who: &u64,
+        amount: u64,
+    ) -> DispatchResult {
+        AssetHolds::insert((asset, who, reason), amount);
+        Ok(())
+    }
+}
+
+impl fungibles::MutateHold<u64> for AssetsWithHolds {}
+
+type PalletAccountStakes = pallet_chess::stakes::PalletAccount<Test>;
+type HoldStakes = pallet_chess::stakes::Holds<AssetsWithHolds, MatchStakeReason>;
+
+// `stakes::PalletAccount` by default, `stakes::Holds` when `UseHolds` is set
+pub struct TestStakes;
+
+impl Stakes<u64, u32, u64> for TestStakes {
+    fn hold(asset_id: u32, who: &u64, amount: u64) -> DispatchResult {
+        if UseHolds::get() {
+            <HoldStakes as Stakes<u64, u32, u64>>::hold(asset_id, who, amount)
+        } else {
+            PalletAccountStakes::hold(asset_id, who, amount)
+        }
+    }
+
+    fn release(asset_id: u32, who: &u64, amount: u64) -> DispatchResult {
+        if UseHolds::get() {
+            <HoldStakes as Stakes<u64, u32, u64>>::release(asset_id, who, amount)
+        } else {
+            PalletAccountStakes::release(asset_id, who, amount)
+        }
+    }
+
+    fn transfer(asset_id: u32, source: &u64, dest: &u64, amount: u64) -> DispatchResult {
+        if UseHolds::get() {
+            <HoldStakes as Stakes<u64, u32, u64>>::transfer(asset_id, source, dest, amount)
+        } else {
+            PalletAccountStakes::transfer(asset_id, source, dest, amount)
+        }
+    }
+
+    fn in_pallet_account() -> bool {
+        !UseHolds::get()
+    }
+}
+
 pub const ASSET_ID: u32 = 200u32;
 pub const ASSET_MIN_BALANCE: u64 = 1_000u64;
 
@@ -120,6 +342,7 @@ frame_support::parameter_types! {
 // Build genesis storage according to the mock runtime.
 #[allow(dead_code)]
 pub fn new_test_ext() -> sp_io::TestExternalities {
+    UseHolds::set(false);
     let mut storage = system::GenesisConfig::<Test>::default()
         .build_storage()
         .unwrap();
@@ -147,16 +370,34 @@ pub fn new_test_ext() -> sp_io::TestExternalities {
                 frame_benchmarking::account("Bob", 0, 1),
                 asset_min_balance * 100,
             ),
+            (
+                asset_id,
+                frame_benchmarking::account("Charlie", 0, 2),
+                asset_min_balance * 100,
+            ),
+            (
+                asset_id,
+                frame_benchmarking::account("Dave", 0, 3),
+                asset_min_balance * 100,
+            ),
         ],
     }
     .assimilate_storage(&mut storage)
     .unwrap();
 
     pallet_chess::GenesisConfig::<Test> {
-        elo: vec![
+        elo: [
             (frame_benchmarking::account("Alice", 0, 0), 2000),
             (frame_benchmarking::account("Bob", 0, 1), 2400),
-        ],
+            (frame_benchmarking::account("Dave", 0, 3), 2300),
+        ]
+        .into_iter()
+        .flat_map(|(account, elo)| {
+            pallet_chess::MatchStyle::RATED
+                .into_iter()
+                .map(move |style| (account, style, elo))
+        })
+        .collect(),
     }
     .assimilate_storage(&mut storage)
     .unwrap();
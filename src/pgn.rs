@@ -0,0 +1,324 @@
+//! Export matches as [*Portable Game Notation* (PGN)](https://en.wikipedia.org/wiki/Portable_Game_Notation).
+//!
+//! Moves are stored on-chain in UCI notation, while PGN requires Standard Algebraic Notation (SAN).
+//! The helpers in this module replay the match from its initial position in order to translate
+//! every move. They are only meant to be used off-chain (e.g. by indexers and UIs).
+
+use crate::{CompactMove, Config, Event, Match, MatchState, Variant};
+use cozy_chess::{BitBoard, Board, Color, GameStatus, Move, Piece, Square};
+use std::str::{from_utf8, FromStr};
+
+// recommended maximum line length for the movetext of exported PGN
+const MAX_LINE_LENGTH: usize = 80;
+
+#[derive(Debug, PartialEq)]
+pub enum PgnError {
+    /// no `MatchCreated` event was found for the requested match
+    MatchNotFound,
+    InvalidBoardEncoding,
+    InvalidMoveEncoding,
+    IllegalMove,
+    /// the moves don't lead to the current board of the match, e.g. because the move history was
+    /// truncated (see `Pallet::move_history_truncated`)
+    IncompleteMoveHistory,
+}
+
+/// Builds the PGN of a match from its on-chain state and move list (see `Pallet::match_moves`).
+///
+/// Matches and their move lists are removed from storage once they are over, so this only
+/// exports matches in progress, whose result is always `*`. Finished matches can be exported with
+/// `events_to_pgn`.
+pub fn match_to_pgn<T: Config>(
+    chess_match: &Match<T>,
+    moves: &[CompactMove],
+) -> Result<String, PgnError> {
+    let moves = moves
+        .iter()
+        .map(|m| m.to_move().ok_or(PgnError::InvalidMoveEncoding))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let start_board = decode_board(&chess_match.start_board, &chess_match.variant)?;
+    let (movetext, board) = movetext(start_board.clone(), &moves)?;
+    if board != decode_board(&chess_match.board, &chess_match.variant)? {
+        return Err(PgnError::IncompleteMoveHistory);
+    }
+
+    let result = match &chess_match.state {
+        MatchState::Won(winner, _) if chess_match.white.as_ref() == Some(winner) => "1-0",
+        MatchState::Won(..) => "0-1",
+        MatchState::Drawn(_) => "1/2-1/2",
+        MatchState::AwaitingOpponent | MatchState::OnGoing(_) => "*",
+    };
+
+    let mut tags = vec![
+        ("White", player_tag(&chess_match.white)),
+        ("Black", player_tag(&chess_match.black)),
+        ("Result", result.to_string()),
+    ];
+    tags.extend(setup_tags(
+        &chess_match.variant,
+        &start_board,
+        &chess_match.start_board,
+    ));
+    tags.extend([
+        ("MatchStyle", format!("{:?}", chess_match.style)),
+        ("BetAsset", format!("{:?}", chess_match.bet_asset_id)),
+        ("BetAmount", format!("{:?}", chess_match.bet_amount)),
+    ]);
+
+    Ok(render(&tags, &movetext, result))
+}
+
+/// Builds the PGN of a match from the pallet events emitted during its lifetime.
+///
+/// Unlike `match_to_pgn`, this also works for finished matches, which are no longer in storage.
+pub fn events_to_pgn<T: Config>(
+    match_id: T::Hash,
+    events: impl IntoIterator<Item = Event<T>>,
+) -> Result<String, PgnError> {
+    let mut created = None;
+    let mut colors = None;
+    let mut start = None;
+    let mut moves = Vec::new();
+    let mut result = "*";
+
+    for event in events {
+        match event {
+            Event::MatchCreated(_, _, id, style, bet_asset_id, bet_amount, variant)
+                if id == match_id =>
+            {
+                created = Some((style, bet_asset_id, bet_amount, variant));
+            }
+            // colors and Chess960 start positions are only known once the match starts
+            Event::MatchStarted(id, white, black, start_board) if id == match_id => {
+                colors = Some((white, black));
+                start = Some(start_board);
+            }
+            Event::MoveExecuted(id, _, move_fen, _) if id == match_id => {
+                let s = from_utf8(&move_fen).map_err(|_| PgnError::InvalidMoveEncoding)?;
+                moves.push(Move::from_str(s).map_err(|_| PgnError::InvalidMoveEncoding)?);
+            }
+            Event::MatchWon(id, winner, _, _) if id == match_id => {
+                result = match &colors {
+                    Some((white, _)) if *white == winner => "1-0",
+                    _ => "0-1",
+                };
+            }
+            Event::MatchResigned(id, who, _) if id == match_id => {
+                result = match &colors {
+                    Some((white, _)) if *white == who => "0-1",
+                    _ => "1-0",
+                };
+            }
+            Event::MatchDrawn(id, _, _) if id == match_id => result = "1/2-1/2",
+            _ => {}
+        }
+    }
+
+    let (style, bet_asset_id, bet_amount, variant) = created.ok_or(PgnError::MatchNotFound)?;
+    let (white, black) = match colors {
+        Some((white, black)) => (Some(white), Some(black)),
+        None => (None, None),
+    };
+    let start_fen = start.unwrap_or_else(|| format!("{}", Board::default()).into_bytes());
+    let start_board = decode_board(&start_fen, &variant)?;
+    let (movetext, _) = movetext(start_board.clone(), &moves)?;
+
+    let mut tags = vec![
+        ("White", player_tag(&white)),
+        ("Black", player_tag(&black)),
+        ("Result", result.to_string()),
+    ];
+    tags.extend(setup_tags(&variant, &start_board, &start_fen));
+    tags.extend([
+        ("MatchStyle", format!("{:?}", style)),
+        ("BetAsset", format!("{:?}", bet_asset_id)),
+        ("BetAmount", format!("{:?}", bet_amount)),
+    ]);
+
+    Ok(render(&tags, &movetext, result))
+}
+
+// same as `Pallet::decode_board`, Chess960 boards are encoded as Shredder-FEN.
+// Chess960 matches awaiting an opponent still hold the standard start position
+fn decode_board(encoded_board: &[u8], variant: &Variant) -> Result<Board, PgnError> {
+    let s = from_utf8(encoded_board).map_err(|_| PgnError::InvalidBoardEncoding)?;
+    let board = match variant {
+        Variant::Standard => Board::from_fen(s, false),
+        Variant::Chess960 => Board::from_fen(s, true).or_else(|_| Board::from_fen(s, false)),
+    };
+    board.map_err(|_| PgnError::InvalidBoardEncoding)
+}
+
+// tags describing where the movetext starts from, when it's not the standard start position
+fn setup_tags(
+    variant: &Variant,
+    start_board: &Board,
+    start_fen: &[u8],
+) -> Vec<(&'static str, String)> {
+    let mut tags = Vec::new();
+    if *variant == Variant::Chess960 {
+        tags.push(("Variant", "Chess960".to_string()));
+    }
+    if *start_board != Board::default() {
+        tags.push(("SetUp", "1".to_string()));
+        tags.push(("FEN", String::from_utf8_lossy(start_fen).into_owned()));
+    }
+    tags
+}
+
+// players are unknown until the match starts, in which case they are left as "?"
+fn player_tag<AccountId: core::fmt::Debug>(player: &Option<AccountId>) -> String {
+    match player {
+        Some(player) => format!("{:?}", player),
+        None => "?".to_string(),
+    }
+}
+
+fn render(tags: &[(&str, String)], movetext: &[String], result: &str) -> String {
+    let mut pgn = String::new();
+
+    // the Seven Tag Roster must come first, tags we know nothing about are left as "?"
+    for (name, value) in [
+        ("Event", "Substrate Chess"),
+        ("Site", "?"),
+        ("Date", "????.??.??"),
+        ("Round", "?"),
+    ] {
+        pgn.push_str(&format!("[{} \"{}\"]\n", name, value));
+    }
+    for (name, value) in tags {
+        pgn.push_str(&format!(
+            "[{} \"{}\"]\n",
+            name,
+            value.replace('\\', "\\\\").replace('"', "\\\"")
+        ));
+    }
+    pgn.push('\n');
+
+    let mut line = String::new();
+    for token in movetext.iter().map(String::as_str).chain([result]) {
+        if !line.is_empty() && line.len() + 1 + token.len() > MAX_LINE_LENGTH {
+            pgn.push_str(&line);
+            pgn.push('\n');
+            line.clear();
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(token);
+    }
+    pgn.push_str(&line);
+    pgn.push('\n');
+
+    pgn
+}
+
+// replays `moves` from `board`, returning the movetext tokens and the final board
+fn movetext(mut board: Board, moves: &[Move]) -> Result<(Vec<String>, Board), PgnError> {
+    let mut tokens = Vec::new();
+
+    for (i, m) in moves.iter().enumerate() {
+        if board.side_to_move() == Color::White {
+            tokens.push(format!("{}.", board.fullmove_number()));
+        } else if i == 0 {
+            tokens.push(format!("{}...", board.fullmove_number()));
+        }
+
+        if !board.is_legal(*m) {
+            return Err(PgnError::IllegalMove);
+        }
+        tokens.push(san(&board, *m));
+        board.play_unchecked(*m);
+    }
+
+    Ok((tokens, board))
+}
+
+// translates a legal move into Standard Algebraic Notation
+fn san(board: &Board, m: Move) -> String {
+    let color = board.side_to_move();
+    let piece = board.piece_on(m.from);
+    let (from_file, from_rank) = file_and_rank(m.from);
+    let (to_file, _) = file_and_rank(m.to);
+    let mut san = String::new();
+
+    if piece == Some(Piece::King) && board.color_on(m.to) == Some(color) {
+        // cozy-chess encodes castling as the king capturing its own rook
+        san.push_str(if to_file > from_file { "O-O" } else { "O-O-O" });
+    } else {
+        let capture =
+            board.color_on(m.to).is_some() || (piece == Some(Piece::Pawn) && from_file != to_file);
+
+        match piece {
+            Some(Piece::Pawn) => {
+                if capture {
+                    san.push(from_file);
+                }
+            }
+            Some(p) => {
+                san.push(piece_char(p));
+
+                // other pieces of the same kind that could legally move to the same square
+                let others: Vec<Square> = (board.pieces(p) & board.colors(color))
+                    .iter()
+                    .filter(|sq| {
+                        *sq != m.from
+                            && board.is_legal(Move {
+                                from: *sq,
+                                to: m.to,
+                                promotion: None,
+                            })
+                    })
+                    .collect();
+                if !others.is_empty() {
+                    if others.iter().all(|sq| file_and_rank(*sq).0 != from_file) {
+                        san.push(from_file);
+                    } else if others.iter().all(|sq| file_and_rank(*sq).1 != from_rank) {
+                        san.push(from_rank);
+                    } else {
+                        san.push(from_file);
+                        san.push(from_rank);
+                    }
+                }
+            }
+            None => {}
+        }
+
+        if capture {
+            san.push('x');
+        }
+        san.push_str(&format!("{}", m.to));
+        if let Some(p) = m.promotion {
+            san.push('=');
+            san.push(piece_char(p));
+        }
+    }
+
+    let mut next = board.clone();
+    next.play_unchecked(m);
+    if next.status() == GameStatus::Won {
+        san.push('#');
+    } else if next.checkers() != BitBoard::EMPTY {
+        san.push('+');
+    }
+
+    san
+}
+
+fn file_and_rank(square: Square) -> (char, char) {
+    let s = format!("{}", square);
+    let mut chars = s.chars();
+    (chars.next().unwrap_or('?'), chars.next().unwrap_or('?'))
+}
+
+fn piece_char(piece: Piece) -> char {
+    match piece {
+        Piece::Pawn => 'P',
+        Piece::Knight => 'N',
+        Piece::Bishop => 'B',
+        Piece::Rook => 'R',
+        Piece::Queen => 'Q',
+        Piece::King => 'K',
+    }
+}
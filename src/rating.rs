@@ -0,0 +1,31 @@
+//! Pluggable rating systems.
+
+use frame_support::Parameter;
+use sp_runtime::FixedU128;
+
+/// Computes player ratings out of match results.
+///
+/// Ratings are plain `u16` values, as used by rating-bounded seeks and matchmaking, but every
+/// system keeps whatever it needs per player in its own `State`, stored in `PlayerRatings`.
+pub trait RatingSystem<BlockNumber> {
+    /// Per-player state of the system
+    type State: Parameter;
+
+    /// State of a player that never played, starting at `rating`
+    fn new_state(rating: u16) -> Self::State;
+
+    /// Rating of a player
+    fn rating(state: &Self::State) -> u16;
+
+    /// States of two players after a match, where `score` is the score of the first player
+    /// (`elo::WIN`, `elo::DRAW` or `elo::LOSS`), `k_1` and `k_2` are the Elo constants of each
+    /// player and `now` is the block the match ended.
+    fn update(
+        k_1: u16,
+        k_2: u16,
+        state_1: &Self::State,
+        state_2: &Self::State,
+        score: FixedU128,
+        now: BlockNumber,
+    ) -> (Self::State, Self::State);
+}
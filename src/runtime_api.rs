@@ -0,0 +1,21 @@
+//! Runtime API of the chess pallet.
+
+use crate::{MatchStyle, RatingChange};
+use parity_scale_codec::Codec;
+use sp_std::vec::Vec;
+
+sp_api::decl_runtime_apis! {
+    /// Read-only queries for clients, e.g. to plot rating charts without scraping an archive node.
+    pub trait ChessApi<AccountId, BlockNumber, Hash>
+    where
+        AccountId: Codec,
+        BlockNumber: Codec,
+        Hash: Codec,
+    {
+        /// Latest rating changes of a player in a match style, oldest first
+        fn rating_history(
+            player: AccountId,
+            style: MatchStyle,
+        ) -> Vec<RatingChange<BlockNumber, Hash>>;
+    }
+}
@@ -0,0 +1,130 @@
+//! Where the stakes of players are kept until their match is settled.
+
+use crate::{AssetIdOf, Config, ConfigHelper};
+use frame_support::{
+    pallet_prelude::*,
+    traits::{
+        fungibles::{Mutate, MutateHold},
+        tokens::{Fortitude, Precision, Preservation, Restriction},
+    },
+};
+use sp_std::marker::PhantomData;
+
+/// Keeps stakes aside while matches (or matchmaking queue entries) are in progress.
+pub trait Stakes<AccountId, AssetId, Balance> {
+    /// Puts `amount` of the free balance of `who` aside, as a stake
+    fn hold(asset_id: AssetId, who: &AccountId, amount: Balance) -> DispatchResult;
+
+    /// Gives `amount` of the stake of `who` back to them
+    fn release(asset_id: AssetId, who: &AccountId, amount: Balance) -> DispatchResult;
+
+    /// Pays `amount` out of the stake of `source` into the free balance of `dest`
+    fn transfer(
+        asset_id: AssetId,
+        source: &AccountId,
+        dest: &AccountId,
+        amount: Balance,
+    ) -> DispatchResult;
+
+    /// Whether stakes are kept in the pallet account, where they were before `Stakes` existed
+    fn in_pallet_account() -> bool {
+        false
+    }
+}
+
+/// Stakes are transferred to the pallet account (derived from `Config::PalletId`) and paid out of
+/// it. Works with any `fungibles` implementation, such as `pallet-assets`.
+pub struct PalletAccount<T>(PhantomData<T>);
+
+impl<T: Config> Stakes<T::AccountId, AssetIdOf<T>, T::AssetBalance> for PalletAccount<T> {
+    fn hold(asset_id: AssetIdOf<T>, who: &T::AccountId, amount: T::AssetBalance) -> DispatchResult {
+        T::Assets::transfer(
+            asset_id,
+            who,
+            &T::pallet_account(),
+            amount,
+            Preservation::Expendable,
+        )?;
+        Ok(())
+    }
+
+    fn release(
+        asset_id: AssetIdOf<T>,
+        who: &T::AccountId,
+        amount: T::AssetBalance,
+    ) -> DispatchResult {
+        T::Assets::transfer(
+            asset_id,
+            &T::pallet_account(),
+            who,
+            amount,
+            Preservation::Expendable,
+        )?;
+        Ok(())
+    }
+
+    // every stake is in the same pot, so it doesn't matter whose stake pays
+    fn transfer(
+        asset_id: AssetIdOf<T>,
+        _source: &T::AccountId,
+        dest: &T::AccountId,
+        amount: T::AssetBalance,
+    ) -> DispatchResult {
+        T::Assets::transfer(
+            asset_id,
+            &T::pallet_account(),
+            dest,
+            amount,
+            Preservation::Expendable,
+        )?;
+        Ok(())
+    }
+
+    fn in_pallet_account() -> bool {
+        true
+    }
+}
+
+/// Stakes stay in the accounts of the players, on hold with `Reason` (usually
+/// `HoldReason::MatchStake`, converted into the runtime's hold reason). Needs an `Assets`
+/// implementation that supports holds.
+pub struct Holds<Assets, Reason>(PhantomData<(Assets, Reason)>);
+
+impl<AccountId, Assets, Reason> Stakes<AccountId, Assets::AssetId, Assets::Balance>
+    for Holds<Assets, Reason>
+where
+    Assets: MutateHold<AccountId>,
+    Reason: Get<Assets::Reason>,
+{
+    fn hold(asset_id: Assets::AssetId, who: &AccountId, amount: Assets::Balance) -> DispatchResult {
+        Assets::hold(asset_id, &Reason::get(), who, amount)
+    }
+
+    fn release(
+        asset_id: Assets::AssetId,
+        who: &AccountId,
+        amount: Assets::Balance,
+    ) -> DispatchResult {
+        Assets::release(asset_id, &Reason::get(), who, amount, Precision::Exact)?;
+        Ok(())
+    }
+
+    fn transfer(
+        asset_id: Assets::AssetId,
+        source: &AccountId,
+        dest: &AccountId,
+        amount: Assets::Balance,
+    ) -> DispatchResult {
+        Assets::transfer_on_hold(
+            asset_id,
+            &Reason::get(),
+            source,
+            dest,
+            amount,
+            Precision::Exact,
+            Restriction::Free,
+            Fortitude::Polite,
+        )?;
+        Ok(())
+    }
+}
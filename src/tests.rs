@@ -1,7 +1,25 @@
-use crate::{mock::*, Config, Error, Event, MatchState, MatchStyle, NextMove, PlayerMatches};
+use crate::{
+    elo, glicko2, migrations, mock::*, pgn, rating::RatingSystem, weights::WeightInfo,
+    ColorPreference, CompactMove, Config, ConfigHelper, Error, Event, MatchOptions, MatchState,
+    MatchStyle, NextMove, OpenChallenges, Parameters, PlayerFinishedMatches, PlayerMatches,
+    PromotionPiece, RatingChange, Termination, TimeControl, Variant,
+};
 use cozy_chess::Board;
 use frame_benchmarking::account;
-use frame_support::{assert_noop, assert_ok};
+use frame_support::{
+    assert_noop, assert_ok,
+    storage::unhashed,
+    traits::{
+        fungibles::{InspectHold, Mutate},
+        tokens::{Fortitude, Precision},
+        ConstU64, GetStorageVersion, Hooks, OnRuntimeUpgrade, StorageVersion,
+    },
+    weights::Weight,
+    BoundedVec,
+};
+use parity_scale_codec::Encode;
+use sp_runtime::DispatchError;
+use std::str::from_utf8;
 
 #[test]
 fn create_match_works() {
@@ -16,10 +34,11 @@ fn create_match_works() {
         assert_noop!(
             Chess::create_match(
                 RuntimeOrigin::signed(alice),
-                bob,
+                Some(bob),
                 MatchStyle::Bullet,
                 bet_asset_id,
-                bet_amount_low
+                bet_amount_low,
+                MatchOptions::default()
             ),
             Error::<Test>::BetTooLow
         );
@@ -31,10 +50,11 @@ fn create_match_works() {
         assert_noop!(
             Chess::create_match(
                 RuntimeOrigin::signed(alice),
-                bob,
+                Some(bob),
                 MatchStyle::Bullet,
                 bet_asset_id_noop,
-                bet_amount
+                bet_amount,
+                MatchOptions::default()
             ),
             Error::<Test>::BetDoesNotExist
         );
@@ -43,10 +63,11 @@ fn create_match_works() {
         assert_noop!(
             Chess::create_match(
                 RuntimeOrigin::signed(alice),
-                alice,
+                Some(alice),
                 MatchStyle::Bullet,
                 bet_asset_id,
-                bet_amount
+                bet_amount,
+                MatchOptions::default()
             ),
             Error::<Test>::InvalidOpponent
         );
@@ -56,17 +77,18 @@ fn create_match_works() {
 
         assert_ok!(Chess::create_match(
             RuntimeOrigin::signed(alice),
-            bob,
+            Some(bob),
             MatchStyle::Bullet,
             bet_asset_id,
-            bet_amount
+            bet_amount,
+            MatchOptions::default()
         ));
 
         let match_id = Chess::chess_match_id_from_nonce(0).unwrap();
         let chess_match = Chess::chess_matches(match_id).unwrap();
 
         assert_eq!(chess_match.challenger, alice);
-        assert_eq!(chess_match.opponent, bob);
+        assert_eq!(chess_match.opponent, Some(bob));
         assert_eq!(
             chess_match.board,
             Board::default().to_string().as_bytes().to_vec()
@@ -92,10 +114,11 @@ fn abort_match_works() {
 
         assert_ok!(Chess::create_match(
             RuntimeOrigin::signed(alice),
-            bob,
+            Some(bob),
             MatchStyle::Bullet,
             bet_asset_id,
-            bet_amount
+            bet_amount,
+            MatchOptions::default()
         ));
 
         let match_id = Chess::chess_match_id_from_nonce(0).unwrap();
@@ -115,6 +138,54 @@ fn abort_match_works() {
     });
 }
 
+#[test]
+fn expired_challenges_can_be_cleared() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+
+        let alice = account("Alice", 0, 0);
+        let bob = account("Bob", 0, 1);
+
+        let bet_asset_id = AssetId::get();
+
+        let initial_balance_a = Assets::balance(bet_asset_id, alice);
+        let bet_amount = AssetMinBalance::get() * 5;
+
+        assert_ok!(Chess::create_match(
+            RuntimeOrigin::signed(alice),
+            None,
+            MatchStyle::Bullet,
+            bet_asset_id,
+            bet_amount,
+            MatchOptions::default()
+        ));
+
+        let match_id = Chess::chess_match_id_from_nonce(0).unwrap();
+        let created = System::block_number();
+        assert_eq!(Chess::chess_matches(match_id).unwrap().last_move, created);
+
+        System::set_block_number(created + ChallengeExpiry::get());
+        assert_noop!(
+            Chess::clear_abandoned_match(RuntimeOrigin::signed(bob), match_id),
+            Error::<Test>::ChallengeNotExpired
+        );
+
+        // anyone can clear the challenge once it expires
+        System::set_block_number(created + ChallengeExpiry::get() + 1);
+        assert_ok!(Chess::clear_abandoned_match(
+            RuntimeOrigin::signed(bob),
+            match_id
+        ));
+        System::assert_last_event(Event::MatchAborted(match_id).into());
+
+        assert_eq!(Chess::chess_matches(match_id), None);
+        assert_eq!(Chess::chess_match_id_from_nonce(0), None);
+        assert_eq!(Chess::player_matches(alice, match_id), None);
+        assert_eq!(OpenChallenges::<Test>::iter().count(), 0);
+        assert_eq!(Assets::balance(bet_asset_id, alice), initial_balance_a);
+    });
+}
+
 #[test]
 fn join_match_works() {
     new_test_ext().execute_with(|| {
@@ -130,10 +201,11 @@ fn join_match_works() {
 
         assert_ok!(Chess::create_match(
             RuntimeOrigin::signed(alice),
-            bob,
+            Some(bob),
             MatchStyle::Bullet,
             bet_asset_id,
-            bet_amount
+            bet_amount,
+            MatchOptions::default()
         ));
 
         let match_id = Chess::chess_match_id_from_nonce(0).unwrap();
@@ -151,176 +223,695 @@ fn join_match_works() {
 }
 
 #[test]
-fn make_move_works() {
+fn open_challenge_works() {
     new_test_ext().execute_with(|| {
-        System::set_block_number(1);
-
         let alice = account("Alice", 0, 0);
         let bob = account("Bob", 0, 1);
 
         let bet_asset_id = AssetId::get();
+        let bet_amount = AssetMinBalance::get() * 5; // assuming T::IncentiveShare is 10%
 
-        let initial_balance_a = Assets::balance(bet_asset_id, alice);
         let initial_balance_b = Assets::balance(bet_asset_id, bob);
 
-        let bet_amount = AssetMinBalance::get() * 5; // assuming T::IncentiveShare is 10%
-
         assert_ok!(Chess::create_match(
             RuntimeOrigin::signed(alice),
-            bob,
-            MatchStyle::Bullet,
+            None,
+            MatchStyle::Blitz,
             bet_asset_id,
-            bet_amount
+            bet_amount,
+            MatchOptions::default()
         ));
 
         let match_id = Chess::chess_match_id_from_nonce(0).unwrap();
+        System::assert_last_event(
+            Event::MatchCreated {
+                0: alice,
+                1: None,
+                2: match_id,
+                3: MatchStyle::Blitz,
+                4: bet_asset_id,
+                5: bet_amount,
+                6: Variant::Standard,
+            }
+            .into(),
+        );
+        assert_eq!(Chess::chess_matches(match_id).unwrap().opponent, None);
 
-        assert_ok!(Chess::join_match(RuntimeOrigin::signed(bob), match_id));
+        // open challenges are listed by style and bet asset
+        let open_challenges =
+            OpenChallenges::<Test>::iter_key_prefix((MatchStyle::Blitz,)).collect::<Vec<_>>();
+        assert_eq!(open_challenges, vec![(bet_asset_id, match_id)]);
+        assert_eq!(
+            OpenChallenges::<Test>::iter_key_prefix((MatchStyle::Bullet,)).count(),
+            0
+        );
 
-        // test successful make_move
-        assert_ok!(Chess::make_move(
-            RuntimeOrigin::signed(alice),
-            match_id,
-            "e2e4".into()
-        ));
+        // challenger can't join their own open challenge
+        assert_noop!(
+            Chess::join_match(RuntimeOrigin::signed(alice), match_id),
+            Error::<Test>::InvalidOpponent
+        );
+
+        assert_ok!(Chess::join_match(RuntimeOrigin::signed(bob), match_id));
         System::assert_last_event(
-            Event::MoveExecuted {
+            Event::MatchStarted {
                 0: match_id,
                 1: alice,
-                2: "e2e4".into(),
+                2: bob,
+                3: Board::default().to_string().into(),
             }
             .into(),
         );
 
-        // test NotYourTurn error
-        assert_noop!(
-            Chess::make_move(RuntimeOrigin::signed(alice), match_id, "e7e5".into()),
-            Error::<Test>::NotYourTurn
+        let chess_match = Chess::chess_matches(match_id).unwrap();
+        assert_eq!(chess_match.opponent, Some(bob));
+        assert_eq!(chess_match.state, MatchState::OnGoing(NextMove::Whites));
+        assert_eq!(Chess::player_matches(bob, match_id), Some(()));
+        assert_eq!(OpenChallenges::<Test>::iter().count(), 0);
+        assert_eq!(
+            Assets::balance(bet_asset_id, bob),
+            initial_balance_b - bet_amount
         );
 
-        // test IllegalMove error
+        // match can't be joined twice
         assert_noop!(
-            Chess::make_move(RuntimeOrigin::signed(bob), match_id, "e2e4".into()),
-            Error::<Test>::IllegalMove
+            Chess::join_match(RuntimeOrigin::signed(bob), match_id),
+            Error::<Test>::NotAwaitingOpponent
         );
 
-        // test InvalidMoveEncoding
-        assert_noop!(
-            Chess::make_move(RuntimeOrigin::signed(bob), match_id, "1234".into()),
-            Error::<Test>::InvalidMoveEncoding
-        );
+        // aborting an open challenge removes it from the index
+        assert_ok!(Chess::create_match(
+            RuntimeOrigin::signed(alice),
+            None,
+            MatchStyle::Bullet,
+            bet_asset_id,
+            bet_amount,
+            MatchOptions::default()
+        ));
+        let match_id = Chess::chess_match_id_from_nonce(1).unwrap();
+        assert_eq!(OpenChallenges::<Test>::iter().count(), 1);
 
-        // test InvalidMoveEncoding
-        assert_noop!(
-            Chess::make_move(RuntimeOrigin::signed(bob), match_id, "e1e2e3".into()),
-            Error::<Test>::InvalidMoveEncoding
-        );
+        assert_ok!(Chess::abort_match(RuntimeOrigin::signed(alice), match_id));
+        assert_eq!(OpenChallenges::<Test>::iter().count(), 0);
+    });
+}
 
-        // test InvalidMoveEncoding
+#[test]
+fn rating_bounded_seek_works() {
+    new_test_ext().execute_with(|| {
+        let alice = account("Alice", 0, 0); // elo 2000
+        let bob = account("Bob", 0, 1); // elo 2400
+
+        let bet_asset_id = AssetId::get();
+        let bet_amount = AssetMinBalance::get() * 5; // assuming T::IncentiveShare is 10%
+
+        // assert InvalidRatingBounds error
         assert_noop!(
-            Chess::make_move(RuntimeOrigin::signed(bob), match_id, "1".into()),
-            Error::<Test>::InvalidMoveEncoding
+            Chess::create_match(
+                RuntimeOrigin::signed(alice),
+                None,
+                MatchStyle::Bullet,
+                bet_asset_id,
+                bet_amount,
+                MatchOptions {
+                    min_rating: Some(2200),
+                    max_rating: Some(1800),
+                    ..Default::default()
+                }
+            ),
+            Error::<Test>::InvalidRatingBounds
         );
 
-        // test MatchWon
-        assert_ok!(Chess::make_move(
-            RuntimeOrigin::signed(bob),
-            match_id,
-            "e7e5".into()
-        ));
-        assert_ok!(Chess::make_move(
+        assert_ok!(Chess::create_match(
             RuntimeOrigin::signed(alice),
-            match_id,
-            "g1f3".into()
+            None,
+            MatchStyle::Bullet,
+            bet_asset_id,
+            bet_amount,
+            MatchOptions {
+                min_rating: Some(1800),
+                max_rating: Some(2200),
+                ..Default::default()
+            }
         ));
-        assert_ok!(Chess::make_move(
+        let match_id = Chess::chess_match_id_from_nonce(0).unwrap();
+
+        // bob is rated above the seek's range
+        assert_noop!(
+            Chess::join_match(RuntimeOrigin::signed(bob), match_id),
+            Error::<Test>::RatingOutOfBounds
+        );
+        assert_ok!(Chess::abort_match(RuntimeOrigin::signed(alice), match_id));
+
+        // one-sided bounds
+        assert_ok!(Chess::create_match(
             RuntimeOrigin::signed(bob),
-            match_id,
-            "b8c6".into()
-        ));
-        assert_ok!(Chess::make_move(
-            RuntimeOrigin::signed(alice),
-            match_id,
-            "d2d4".into()
+            None,
+            MatchStyle::Bullet,
+            bet_asset_id,
+            bet_amount,
+            MatchOptions {
+                min_rating: Some(2100),
+                ..Default::default()
+            }
         ));
-        assert_ok!(Chess::make_move(
+        let match_id = Chess::chess_match_id_from_nonce(1).unwrap();
+        assert_noop!(
+            Chess::join_match(RuntimeOrigin::signed(alice), match_id),
+            Error::<Test>::RatingOutOfBounds
+        );
+        assert_ok!(Chess::abort_match(RuntimeOrigin::signed(bob), match_id));
+
+        assert_ok!(Chess::create_match(
             RuntimeOrigin::signed(bob),
-            match_id,
-            "e5d4".into()
+            None,
+            MatchStyle::Bullet,
+            bet_asset_id,
+            bet_amount,
+            MatchOptions {
+                max_rating: Some(2000),
+                ..Default::default()
+            }
         ));
-        assert_ok!(Chess::make_move(
+        let match_id = Chess::chess_match_id_from_nonce(2).unwrap();
+        assert_ok!(Chess::join_match(RuntimeOrigin::signed(alice), match_id));
+
+        let chess_match = Chess::chess_matches(match_id).unwrap();
+        assert_eq!(chess_match.opponent, Some(alice));
+        assert_eq!(chess_match.state, MatchState::OnGoing(NextMove::Whites));
+    });
+}
+
+#[test]
+fn color_preference_works() {
+    new_test_ext().execute_with(|| {
+        let alice = account("Alice", 0, 0);
+        let bob = account("Bob", 0, 1);
+
+        let bet_asset_id = AssetId::get();
+        let bet_amount = AssetMinBalance::get() * 5; // assuming T::IncentiveShare is 10%
+
+        // alice wants to play blacks
+        assert_ok!(Chess::create_match(
             RuntimeOrigin::signed(alice),
-            match_id,
-            "f3d4".into()
+            Some(bob),
+            MatchStyle::Bullet,
+            bet_asset_id,
+            bet_amount,
+            MatchOptions {
+                color: ColorPreference::Black,
+                ..Default::default()
+            }
         ));
+        let match_id = Chess::chess_match_id_from_nonce(0).unwrap();
+
+        let chess_match = Chess::chess_matches(match_id).unwrap();
+        assert_eq!(chess_match.white, None);
+        assert_eq!(chess_match.black, None);
+
+        assert_ok!(Chess::join_match(RuntimeOrigin::signed(bob), match_id));
+        System::assert_last_event(
+            Event::MatchStarted {
+                0: match_id,
+                1: bob,
+                2: alice,
+                3: Board::default().to_string().into(),
+            }
+            .into(),
+        );
+
+        let chess_match = Chess::chess_matches(match_id).unwrap();
+        assert_eq!(chess_match.white, Some(bob));
+        assert_eq!(chess_match.black, Some(alice));
+
+        // whites move first
+        assert_noop!(
+            Chess::make_move(RuntimeOrigin::signed(alice), match_id, "e2e4".into()),
+            Error::<Test>::NotYourTurn
+        );
         assert_ok!(Chess::make_move(
             RuntimeOrigin::signed(bob),
             match_id,
-            "f8c5".into()
+            "e2e4".into()
         ));
         assert_ok!(Chess::make_move(
             RuntimeOrigin::signed(alice),
             match_id,
-            "c2c3".into()
-        ));
-        assert_ok!(Chess::make_move(
-            RuntimeOrigin::signed(bob),
-            match_id,
-            "d8f6".into()
+            "e7e5".into()
         ));
-        assert_ok!(Chess::make_move(
+
+        // bob doesn't move in time, so alice (blacks) claims victory
+        System::set_block_number(System::block_number() + BulletPeriod::get() + 1);
+        assert_ok!(Chess::clear_abandoned_match(
             RuntimeOrigin::signed(alice),
-            match_id,
-            "d4c6".into()
-        ));
-        assert_ok!(Chess::make_move(
-            RuntimeOrigin::signed(bob),
-            match_id,
-            "f6f2".into()
+            match_id
         ));
         System::assert_has_event(
             Event::MatchWon {
                 0: match_id,
-                1: bob,
-                2: "r1b1k1nr/pppp1ppp/2N5/2b5/4P3/2P5/PP3qPP/RNBQKB1R w KQkq - 0 7".into(),
+                1: alice,
+                2: "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 2".into(),
+                3: Termination::Timeout,
             }
             .into(),
         );
-        assert_eq!(Chess::chess_matches(match_id), None);
+        assert_eq!(Chess::player_rating(alice, MatchStyle::Bullet), 2029);
+        assert_eq!(Chess::player_rating(bob, MatchStyle::Bullet), 2371);
 
-        let final_balance_a = Assets::balance(bet_asset_id, alice);
-        let final_balance_b = Assets::balance(bet_asset_id, bob);
-        assert_eq!(final_balance_a, initial_balance_a - bet_amount);
-        assert_eq!(final_balance_b, initial_balance_b + bet_amount);
+        // random colors
+        for (seed, white, black) in [(0, alice, bob), (1, bob, alice)] {
+            RandomSeed::set(seed);
+            assert_ok!(Chess::create_match(
+                RuntimeOrigin::signed(alice),
+                Some(bob),
+                MatchStyle::Bullet,
+                bet_asset_id,
+                bet_amount,
+                MatchOptions {
+                    color: ColorPreference::Random,
+                    ..Default::default()
+                }
+            ));
+            let match_id = Chess::chess_match_id_from_nonce(Chess::next_nonce() - 1).unwrap();
+            assert_ok!(Chess::join_match(RuntimeOrigin::signed(bob), match_id));
+
+            let chess_match = Chess::chess_matches(match_id).unwrap();
+            assert_eq!(chess_match.white, Some(white));
+            assert_eq!(chess_match.black, Some(black));
+        }
+    });
+}
 
-        // --------------------------------------
-        // test MatchDrawn
-        let initial_balance_a = Assets::balance(bet_asset_id, alice);
-        let initial_balance_b = Assets::balance(bet_asset_id, bob);
+#[test]
+fn chess960_works() {
+    new_test_ext().execute_with(|| {
+        let alice = account("Alice", 0, 0);
+        let bob = account("Bob", 0, 1);
+
+        let bet_asset_id = AssetId::get();
+        let bet_amount = AssetMinBalance::get() * 5; // assuming T::IncentiveShare is 10%
+
+        // every byte of the random output is 1, so the start position is 0x0101 % 960 = 257
+        RandomSeed::set(1);
 
         assert_ok!(Chess::create_match(
             RuntimeOrigin::signed(alice),
-            bob,
+            Some(bob),
             MatchStyle::Bullet,
             bet_asset_id,
-            bet_amount
+            bet_amount,
+            MatchOptions {
+                variant: Variant::Chess960,
+                rated: false,
+                ..Default::default()
+            }
         ));
+        let match_id = Chess::chess_match_id_from_nonce(0).unwrap();
 
-        let match_id = Chess::chess_match_id_from_nonce(1).unwrap();
+        // start position is unknown until bob joins
+        let chess_match = Chess::chess_matches(match_id).unwrap();
+        assert_eq!(chess_match.board, Board::default().to_string().as_bytes());
 
         assert_ok!(Chess::join_match(RuntimeOrigin::signed(bob), match_id));
 
+        let chess_match = Chess::chess_matches(match_id).unwrap();
+        let start_position = Board::chess960_startpos(257);
+        let board = Board::from_fen(from_utf8(&chess_match.board).unwrap(), true).unwrap();
+        assert_eq!(board, start_position);
+        assert_eq!(chess_match.start_board, chess_match.board);
+        assert_eq!(Chess::position_history(match_id)[0], start_position.hash());
+        System::assert_last_event(
+            Event::MatchStarted {
+                0: match_id,
+                1: alice,
+                2: bob,
+                3: chess_match.start_board.clone(),
+            }
+            .into(),
+        );
+
         assert_ok!(Chess::make_move(
             RuntimeOrigin::signed(alice),
             match_id,
-            "c2c4".into()
-        ));
-        assert_ok!(Chess::make_move(
-            RuntimeOrigin::signed(bob),
-            match_id,
-            "h7h5".into()
+            "e2e4".into()
         ));
-        assert_ok!(Chess::make_move(
+
+        // castling rights survive the encoding roundtrip
+        let chess_match = Chess::chess_matches(match_id).unwrap();
+        let board = Board::from_fen(from_utf8(&chess_match.board).unwrap(), true).unwrap();
+        assert_eq!(
+            board.castle_rights(cozy_chess::Color::White),
+            start_position.castle_rights(cozy_chess::Color::White)
+        );
+        assert_eq!(
+            board.castle_rights(cozy_chess::Color::Black),
+            start_position.castle_rights(cozy_chess::Color::Black)
+        );
+
+        let pgn = pgn::match_to_pgn(&chess_match, &Chess::match_moves(match_id)).unwrap();
+        assert!(pgn.contains("[Variant \"Chess960\"]"));
+        assert!(pgn.contains("[SetUp \"1\"]"));
+        assert!(pgn.contains(&format!(
+            "[FEN \"{}\"]",
+            from_utf8(&chess_match.start_board).unwrap()
+        )));
+        assert!(pgn.contains("1. e4 *"));
+    });
+}
+
+#[test]
+fn custom_start_position_works() {
+    new_test_ext().execute_with(|| {
+        let alice = account("Alice", 0, 0);
+        let bob = account("Bob", 0, 1);
+
+        let bet_asset_id = AssetId::get();
+        let bet_amount = AssetMinBalance::get() * 5; // assuming T::IncentiveShare is 10%
+
+        // assert InvalidBoardEncoding error
+        assert_noop!(
+            Chess::create_match(
+                RuntimeOrigin::signed(alice),
+                Some(bob),
+                MatchStyle::Bullet,
+                bet_asset_id,
+                bet_amount,
+                MatchOptions {
+                    start_fen: Some("not a fen".into()),
+                    rated: false,
+                    ..Default::default()
+                }
+            ),
+            Error::<Test>::InvalidBoardEncoding
+        );
+
+        // assert InvalidBoardEncoding error, Shredder-FEN castling rights are only for Chess960
+        assert_noop!(
+            Chess::create_match(
+                RuntimeOrigin::signed(alice),
+                Some(bob),
+                MatchStyle::Bullet,
+                bet_asset_id,
+                bet_amount,
+                MatchOptions {
+                    start_fen: Some(
+                        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w HAha - 0 1".into()
+                    ),
+                    rated: false,
+                    ..Default::default()
+                }
+            ),
+            Error::<Test>::InvalidBoardEncoding
+        );
+
+        // assert InvalidStartPosition error, whites are already checkmated
+        assert_noop!(
+            Chess::create_match(
+                RuntimeOrigin::signed(alice),
+                Some(bob),
+                MatchStyle::Bullet,
+                bet_asset_id,
+                bet_amount,
+                MatchOptions {
+                    start_fen: Some(
+                        "rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3".into()
+                    ),
+                    rated: false,
+                    ..Default::default()
+                }
+            ),
+            Error::<Test>::InvalidStartPosition
+        );
+
+        // assert InvalidStartPosition error, Chess960 start positions are drawn by the pallet
+        assert_noop!(
+            Chess::create_match(
+                RuntimeOrigin::signed(alice),
+                Some(bob),
+                MatchStyle::Bullet,
+                bet_asset_id,
+                bet_amount,
+                MatchOptions {
+                    variant: Variant::Chess960,
+                    start_fen: Some(
+                        "rnb1kbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1".into()
+                    ),
+                    rated: false,
+                    ..Default::default()
+                }
+            ),
+            Error::<Test>::InvalidStartPosition
+        );
+
+        // mate in one drill, with blacks to move
+        let start_board: Vec<u8> = "r5k1/5ppp/8/8/8/8/5PPP/6K1 b - - 0 1".into();
+        assert_ok!(Chess::create_match(
+            RuntimeOrigin::signed(alice),
+            Some(bob),
+            MatchStyle::Bullet,
+            bet_asset_id,
+            bet_amount,
+            MatchOptions {
+                start_fen: Some(start_board.clone()),
+                rated: false,
+                ..Default::default()
+            }
+        ));
+        let match_id = Chess::chess_match_id_from_nonce(0).unwrap();
+
+        let chess_match = Chess::chess_matches(match_id).unwrap();
+        assert_eq!(chess_match.board, start_board);
+        assert_eq!(chess_match.start_board, start_board);
+        assert!(!chess_match.rated);
+
+        assert_ok!(Chess::join_match(RuntimeOrigin::signed(bob), match_id));
+        System::assert_last_event(
+            Event::MatchStarted {
+                0: match_id,
+                1: alice,
+                2: bob,
+                3: start_board.clone(),
+            }
+            .into(),
+        );
+        let chess_match = Chess::chess_matches(match_id).unwrap();
+        assert_eq!(chess_match.state, MatchState::OnGoing(NextMove::Blacks));
+
+        let pgn = pgn::match_to_pgn(&chess_match, &[]).unwrap();
+        assert!(pgn.contains("[SetUp \"1\"]"));
+        assert!(pgn.contains("[FEN \"r5k1/5ppp/8/8/8/8/5PPP/6K1 b - - 0 1\"]"));
+
+        // whites can't move first
+        assert_noop!(
+            Chess::make_move(RuntimeOrigin::signed(alice), match_id, "g1f1".into()),
+            Error::<Test>::NotYourTurn
+        );
+
+        let initial_balance_b = Assets::balance(bet_asset_id, bob);
+        assert_ok!(Chess::make_move(
+            RuntimeOrigin::signed(bob),
+            match_id,
+            "a8a1".into()
+        ));
+        System::assert_has_event(
+            Event::MatchWon {
+                0: match_id,
+                1: bob,
+                2: "6k1/5ppp/8/8/8/8/5PPP/r5K1 w - - 1 2".into(),
+                3: Termination::Checkmate,
+            }
+            .into(),
+        );
+
+        // bets are settled, but elo ratings are left untouched
+        assert_eq!(
+            Assets::balance(bet_asset_id, bob),
+            initial_balance_b + bet_amount * 2
+        );
+        assert_eq!(Chess::player_rating(alice, MatchStyle::Bullet), 2000);
+        assert_eq!(Chess::player_rating(bob, MatchStyle::Bullet), 2400);
+    });
+}
+
+#[test]
+fn make_move_works() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+
+        let alice = account("Alice", 0, 0);
+        let bob = account("Bob", 0, 1);
+
+        let bet_asset_id = AssetId::get();
+
+        let initial_balance_a = Assets::balance(bet_asset_id, alice);
+        let initial_balance_b = Assets::balance(bet_asset_id, bob);
+
+        let bet_amount = AssetMinBalance::get() * 5; // assuming T::IncentiveShare is 10%
+
+        assert_ok!(Chess::create_match(
+            RuntimeOrigin::signed(alice),
+            Some(bob),
+            MatchStyle::Bullet,
+            bet_asset_id,
+            bet_amount,
+            MatchOptions::default()
+        ));
+
+        let match_id = Chess::chess_match_id_from_nonce(0).unwrap();
+
+        assert_ok!(Chess::join_match(RuntimeOrigin::signed(bob), match_id));
+
+        // test successful make_move
+        assert_ok!(Chess::make_move(
+            RuntimeOrigin::signed(alice),
+            match_id,
+            "e2e4".into()
+        ));
+        System::assert_last_event(
+            Event::MoveExecuted {
+                0: match_id,
+                1: alice,
+                2: "e2e4".into(),
+                3: None,
+            }
+            .into(),
+        );
+
+        // test NotYourTurn error
+        assert_noop!(
+            Chess::make_move(RuntimeOrigin::signed(alice), match_id, "e7e5".into()),
+            Error::<Test>::NotYourTurn
+        );
+
+        // test IllegalMove error
+        assert_noop!(
+            Chess::make_move(RuntimeOrigin::signed(bob), match_id, "e2e4".into()),
+            Error::<Test>::IllegalMove
+        );
+
+        // test InvalidMoveEncoding
+        assert_noop!(
+            Chess::make_move(RuntimeOrigin::signed(bob), match_id, "1234".into()),
+            Error::<Test>::InvalidMoveEncoding
+        );
+
+        // test InvalidMoveEncoding
+        assert_noop!(
+            Chess::make_move(RuntimeOrigin::signed(bob), match_id, "e1e2e3".into()),
+            Error::<Test>::InvalidMoveEncoding
+        );
+
+        // test InvalidMoveEncoding
+        assert_noop!(
+            Chess::make_move(RuntimeOrigin::signed(bob), match_id, "1".into()),
+            Error::<Test>::InvalidMoveEncoding
+        );
+
+        // test MatchWon
+        assert_ok!(Chess::make_move(
+            RuntimeOrigin::signed(bob),
+            match_id,
+            "e7e5".into()
+        ));
+        assert_ok!(Chess::make_move(
+            RuntimeOrigin::signed(alice),
+            match_id,
+            "g1f3".into()
+        ));
+        assert_ok!(Chess::make_move(
+            RuntimeOrigin::signed(bob),
+            match_id,
+            "b8c6".into()
+        ));
+        assert_ok!(Chess::make_move(
+            RuntimeOrigin::signed(alice),
+            match_id,
+            "d2d4".into()
+        ));
+        assert_ok!(Chess::make_move(
+            RuntimeOrigin::signed(bob),
+            match_id,
+            "e5d4".into()
+        ));
+        assert_ok!(Chess::make_move(
+            RuntimeOrigin::signed(alice),
+            match_id,
+            "f3d4".into()
+        ));
+        assert_ok!(Chess::make_move(
+            RuntimeOrigin::signed(bob),
+            match_id,
+            "f8c5".into()
+        ));
+        assert_ok!(Chess::make_move(
+            RuntimeOrigin::signed(alice),
+            match_id,
+            "c2c3".into()
+        ));
+        assert_ok!(Chess::make_move(
+            RuntimeOrigin::signed(bob),
+            match_id,
+            "d8f6".into()
+        ));
+        // moves are refunded down to the actual length of the move history, and moves that don't
+        // end the match are refunded the weight of settling it
+        let post_info =
+            Chess::make_move(RuntimeOrigin::signed(alice), match_id, "d4c6".into()).unwrap();
+        assert_eq!(
+            post_info.actual_weight,
+            Some(<Test as Config>::WeightInfo::make_move(11))
+        );
+        let post_info =
+            Chess::make_move(RuntimeOrigin::signed(bob), match_id, "f6f2".into()).unwrap();
+        assert_eq!(
+            post_info.actual_weight,
+            Some(<Test as Config>::WeightInfo::make_move_checkmate(12))
+        );
+        System::assert_has_event(
+            Event::MatchWon {
+                0: match_id,
+                1: bob,
+                2: "r1b1k1nr/pppp1ppp/2N5/2b5/4P3/2P5/PP3qPP/RNBQKB1R w KQkq - 0 7".into(),
+                3: Termination::Checkmate,
+            }
+            .into(),
+        );
+        assert_eq!(Chess::chess_matches(match_id), None);
+
+        let final_balance_a = Assets::balance(bet_asset_id, alice);
+        let final_balance_b = Assets::balance(bet_asset_id, bob);
+        assert_eq!(final_balance_a, initial_balance_a - bet_amount);
+        assert_eq!(final_balance_b, initial_balance_b + bet_amount);
+
+        // --------------------------------------
+        // test MatchDrawn
+        let initial_balance_a = Assets::balance(bet_asset_id, alice);
+        let initial_balance_b = Assets::balance(bet_asset_id, bob);
+
+        assert_ok!(Chess::create_match(
+            RuntimeOrigin::signed(alice),
+            Some(bob),
+            MatchStyle::Bullet,
+            bet_asset_id,
+            bet_amount,
+            MatchOptions::default()
+        ));
+
+        let match_id = Chess::chess_match_id_from_nonce(1).unwrap();
+
+        assert_ok!(Chess::join_match(RuntimeOrigin::signed(bob), match_id));
+
+        assert_ok!(Chess::make_move(
+            RuntimeOrigin::signed(alice),
+            match_id,
+            "c2c4".into()
+        ));
+        assert_ok!(Chess::make_move(
+            RuntimeOrigin::signed(bob),
+            match_id,
+            "h7h5".into()
+        ));
+        assert_ok!(Chess::make_move(
             RuntimeOrigin::signed(alice),
             match_id,
             "h2h4".into()
@@ -409,6 +1000,7 @@ fn make_move_works() {
             Event::MatchDrawn {
                 0: match_id,
                 1: "5bnr/4p1pq/4Qpkr/7p/2P4P/8/PP1PPPP1/RNB1KBNR b KQ - 2 10".into(),
+                2: Termination::Stalemate,
             }
             .into(),
         );
@@ -422,7 +1014,7 @@ fn make_move_works() {
 }
 
 #[test]
-fn check_elo_stronger_wins() {
+fn insufficient_material_draw_works() {
     new_test_ext().execute_with(|| {
         System::set_block_number(1);
 
@@ -432,12 +1024,80 @@ fn check_elo_stronger_wins() {
         let bet_asset_id = AssetId::get();
         let bet_amount = AssetMinBalance::get() * 5;
 
-        assert_ok!(Chess::create_match(
-            RuntimeOrigin::signed(alice),
-            bob,
+        let initial_balance_a = Assets::balance(bet_asset_id, alice);
+        let initial_balance_b = Assets::balance(bet_asset_id, bob);
+
+        // assert InvalidStartPosition error, king and knight against king is already a draw
+        assert_noop!(
+            Chess::create_match(
+                RuntimeOrigin::signed(alice),
+                Some(bob),
+                MatchStyle::Bullet,
+                bet_asset_id,
+                bet_amount,
+                MatchOptions {
+                    start_fen: Some("8/8/8/8/8/2k5/8/KN6 w - - 0 1".into()),
+                    rated: false,
+                    ..Default::default()
+                }
+            ),
+            Error::<Test>::InvalidStartPosition
+        );
+
+        // whites are in check, and can only take the rook
+        assert_ok!(Chess::create_match(
+            RuntimeOrigin::signed(alice),
+            Some(bob),
             MatchStyle::Bullet,
             bet_asset_id,
-            bet_amount
+            bet_amount,
+            MatchOptions {
+                start_fen: Some("8/8/8/8/8/2k5/8/Kr6 w - - 0 1".into()),
+                rated: false,
+                ..Default::default()
+            }
+        ));
+        let match_id = Chess::chess_match_id_from_nonce(0).unwrap();
+        assert_ok!(Chess::join_match(RuntimeOrigin::signed(bob), match_id));
+
+        assert_ok!(Chess::make_move(
+            RuntimeOrigin::signed(alice),
+            match_id,
+            "a1b1".into()
+        ));
+        System::assert_has_event(
+            Event::MatchDrawn {
+                0: match_id,
+                1: "8/8/8/8/8/2k5/8/1K6 b - - 0 1".into(),
+                2: Termination::InsufficientMaterial,
+            }
+            .into(),
+        );
+        assert_eq!(Chess::chess_matches(match_id), None);
+
+        assert_eq!(Assets::balance(bet_asset_id, alice), initial_balance_a);
+        assert_eq!(Assets::balance(bet_asset_id, bob), initial_balance_b);
+    });
+}
+
+#[test]
+fn check_elo_stronger_wins() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+
+        let alice = account("Alice", 0, 0);
+        let bob = account("Bob", 0, 1);
+
+        let bet_asset_id = AssetId::get();
+        let bet_amount = AssetMinBalance::get() * 5;
+
+        assert_ok!(Chess::create_match(
+            RuntimeOrigin::signed(alice),
+            Some(bob),
+            MatchStyle::Bullet,
+            bet_asset_id,
+            bet_amount,
+            MatchOptions::default()
         ));
 
         let match_id = Chess::chess_match_id_from_nonce(0).unwrap();
@@ -445,8 +1105,8 @@ fn check_elo_stronger_wins() {
         assert_ok!(Chess::join_match(RuntimeOrigin::signed(bob), match_id));
 
         // check elo before the match finishes
-        assert_eq!(Chess::player_elo(alice), 2000);
-        assert_eq!(Chess::player_elo(bob), 2400);
+        assert_eq!(Chess::player_rating(alice, MatchStyle::Bullet), 2000);
+        assert_eq!(Chess::player_rating(bob, MatchStyle::Bullet), 2400);
 
         assert_ok!(Chess::make_move(
             RuntimeOrigin::signed(alice),
@@ -472,8 +1132,8 @@ fn check_elo_stronger_wins() {
         assert_eq!(Chess::chess_matches(match_id), None);
 
         // check the elo after match is complete
-        assert_eq!(Chess::player_elo(alice), 1997);
-        assert_eq!(Chess::player_elo(bob), 2403);
+        assert_eq!(Chess::player_rating(alice, MatchStyle::Bullet), 1997);
+        assert_eq!(Chess::player_rating(bob, MatchStyle::Bullet), 2403);
     });
 }
 
@@ -490,10 +1150,11 @@ fn check_elo_stronger_looses() {
 
         assert_ok!(Chess::create_match(
             RuntimeOrigin::signed(bob),
-            alice,
+            Some(alice),
             MatchStyle::Bullet,
             bet_asset_id,
-            bet_amount
+            bet_amount,
+            MatchOptions::default()
         ));
 
         let match_id = Chess::chess_match_id_from_nonce(0).unwrap();
@@ -501,8 +1162,8 @@ fn check_elo_stronger_looses() {
         assert_ok!(Chess::join_match(RuntimeOrigin::signed(alice), match_id));
 
         // check elo before the match finishes
-        assert_eq!(Chess::player_elo(alice), 2000);
-        assert_eq!(Chess::player_elo(bob), 2400);
+        assert_eq!(Chess::player_rating(alice, MatchStyle::Bullet), 2000);
+        assert_eq!(Chess::player_rating(bob, MatchStyle::Bullet), 2400);
 
         assert_ok!(Chess::make_move(
             RuntimeOrigin::signed(bob),
@@ -521,22 +1182,2088 @@ fn check_elo_stronger_looses() {
         ));
         assert_ok!(Chess::make_move(
             RuntimeOrigin::signed(alice),
-            match_id,
-            "d8h4".into()
+            match_id,
+            "d8h4".into()
+        ));
+
+        assert_eq!(Chess::chess_matches(match_id), None);
+
+        // check the elo after match is complete
+        assert_eq!(Chess::player_rating(alice, MatchStyle::Bullet), 2029);
+        assert_eq!(Chess::player_rating(bob, MatchStyle::Bullet), 2371);
+    });
+}
+
+#[test]
+fn elo_per_style_works() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+
+        let alice = account("Alice", 0, 0);
+        let bob = account("Bob", 0, 1);
+
+        let bet_asset_id = AssetId::get();
+        let bet_amount = AssetMinBalance::get() * 5;
+
+        assert_ok!(Chess::create_match(
+            RuntimeOrigin::signed(alice),
+            Some(bob),
+            MatchStyle::Blitz,
+            bet_asset_id,
+            bet_amount,
+            MatchOptions::default()
+        ));
+        let match_id = Chess::chess_match_id_from_nonce(0).unwrap();
+        assert_ok!(Chess::join_match(RuntimeOrigin::signed(bob), match_id));
+        assert_ok!(Chess::resign(RuntimeOrigin::signed(alice), match_id));
+
+        // only the blitz ratings change
+        assert_eq!(Chess::player_rating(alice, MatchStyle::Blitz), 1997);
+        assert_eq!(Chess::player_rating(bob, MatchStyle::Blitz), 2403);
+        assert_eq!(Chess::player_rating(alice, MatchStyle::Bullet), 2000);
+        assert_eq!(Chess::player_rating(bob, MatchStyle::Bullet), 2400);
+
+        // custom time controls have no rating to seek on
+        let custom = MatchStyle::Custom {
+            per_move: 10,
+            base: 20,
+            increment: 1,
+        };
+        assert_noop!(
+            Chess::create_match(
+                RuntimeOrigin::signed(alice),
+                Some(bob),
+                custom.clone(),
+                bet_asset_id,
+                bet_amount,
+                MatchOptions {
+                    rated: false,
+                    min_rating: Some(1500),
+                    ..Default::default()
+                }
+            ),
+            Error::<Test>::InvalidRatingBounds
+        );
+
+        // and they are never rated
+        assert_noop!(
+            Chess::create_match(
+                RuntimeOrigin::signed(alice),
+                Some(bob),
+                custom.clone(),
+                bet_asset_id,
+                bet_amount,
+                MatchOptions::default()
+            ),
+            Error::<Test>::InvalidRatedMatch
+        );
+        assert_ok!(Chess::create_match(
+            RuntimeOrigin::signed(alice),
+            Some(bob),
+            custom.clone(),
+            bet_asset_id,
+            bet_amount,
+            MatchOptions {
+                rated: false,
+                ..Default::default()
+            }
+        ));
+        let match_id = Chess::chess_match_id_from_nonce(1).unwrap();
+        assert!(!Chess::chess_matches(match_id).unwrap().rated);
+        assert_ok!(Chess::join_match(RuntimeOrigin::signed(bob), match_id));
+        assert_ok!(Chess::resign(RuntimeOrigin::signed(alice), match_id));
+
+        assert_eq!(Chess::player_rating(alice, MatchStyle::Blitz), 1997);
+        assert_eq!(Chess::player_rating(bob, MatchStyle::Blitz), 2403);
+        assert_eq!(Chess::player_rating(alice, custom.clone()), 1600);
+        assert_eq!(Chess::player_rating(bob, custom), 1600);
+    });
+}
+
+#[test]
+fn casual_matches_work() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+
+        let alice = account("Alice", 0, 0);
+        let bob = account("Bob", 0, 1);
+
+        let bet_asset_id = AssetId::get();
+        let bet_amount = AssetMinBalance::get() * 5;
+
+        // rated matches start from the standard position
+        for (variant, start_fen) in [
+            (Variant::Chess960, None),
+            (
+                Variant::Standard,
+                Some("rnb1kbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1".into()),
+            ),
+        ] {
+            assert_noop!(
+                Chess::create_match(
+                    RuntimeOrigin::signed(alice),
+                    Some(bob),
+                    MatchStyle::Bullet,
+                    bet_asset_id,
+                    bet_amount,
+                    MatchOptions {
+                        variant,
+                        start_fen,
+                        ..Default::default()
+                    }
+                ),
+                Error::<Test>::InvalidRatedMatch
+            );
+        }
+
+        assert_ok!(Chess::create_match(
+            RuntimeOrigin::signed(alice),
+            Some(bob),
+            MatchStyle::Bullet,
+            bet_asset_id,
+            bet_amount,
+            MatchOptions {
+                rated: false,
+                ..Default::default()
+            }
+        ));
+        let match_id = Chess::chess_match_id_from_nonce(0).unwrap();
+        assert!(!Chess::chess_matches(match_id).unwrap().rated);
+        assert_ok!(Chess::join_match(RuntimeOrigin::signed(bob), match_id));
+        assert_ok!(Chess::resign(RuntimeOrigin::signed(bob), match_id));
+
+        // the result only shows in the statistics
+        assert_eq!(Chess::player_rating(alice, MatchStyle::Bullet), 2000);
+        assert_eq!(Chess::player_rating(bob, MatchStyle::Bullet), 2400);
+        assert_eq!(Chess::rated_games(alice, MatchStyle::Bullet), 0);
+        assert!(Chess::rating_history(alice, MatchStyle::Bullet).is_empty());
+        assert_eq!(Chess::player_statistics(alice).wins, 1);
+        assert_eq!(Chess::player_statistics(bob).losses, 1);
+    });
+}
+
+#[test]
+fn provisional_ratings_work() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+
+        let alice = account("Alice", 0, 0);
+        let charlie = account("Charlie", 0, 2);
+
+        let bet_asset_id = AssetId::get();
+        let bet_amount = AssetMinBalance::get() * 5;
+
+        // ratings set at genesis are established without any games played, new accounts are provisional
+        assert!(!Chess::is_provisional(&alice, &MatchStyle::Bullet));
+        assert_eq!(Chess::rated_games(alice, MatchStyle::Bullet), 0);
+        assert!(Chess::is_provisional(&charlie, &MatchStyle::Bullet));
+        assert_eq!(Chess::player_statistics(charlie), Default::default());
+
+        let play = |style: MatchStyle, resigning| {
+            let rated = style.is_rated();
+            assert_ok!(Chess::create_match(
+                RuntimeOrigin::signed(alice),
+                Some(charlie),
+                style,
+                bet_asset_id,
+                bet_amount,
+                MatchOptions {
+                    rated,
+                    ..Default::default()
+                }
+            ));
+            let match_id = Chess::chess_match_id_from_nonce(Chess::next_nonce() - 1).unwrap();
+            assert_ok!(Chess::join_match(RuntimeOrigin::signed(charlie), match_id));
+            assert_ok!(Chess::resign(RuntimeOrigin::signed(resigning), match_id));
+        };
+
+        // charlie moves with ProvisionalK, alice with K
+        play(MatchStyle::Bullet, alice);
+        assert_eq!(Chess::player_rating(charlie, MatchStyle::Bullet), 1658);
+        assert_eq!(Chess::player_rating(alice, MatchStyle::Bullet), 1971);
+        assert_eq!(Chess::rated_games(charlie, MatchStyle::Bullet), 1);
+        assert_eq!(Chess::rated_games(alice, MatchStyle::Bullet), 1);
+
+        // unrated matches are counted in the statistics only
+        play(
+            MatchStyle::Custom {
+                per_move: 10,
+                base: 20,
+                increment: 1,
+            },
+            charlie,
+        );
+        assert_eq!(Chess::rated_games(charlie, MatchStyle::Bullet), 1);
+        assert!(Chess::is_provisional(&charlie, &MatchStyle::Bullet));
+
+        // established after ProvisionalGames rated matches, in that style only
+        play(MatchStyle::Bullet, charlie);
+        assert!(!Chess::is_provisional(&charlie, &MatchStyle::Bullet));
+        assert!(Chess::is_provisional(&charlie, &MatchStyle::Blitz));
+
+        let stats = Chess::player_statistics(charlie);
+        assert_eq!((stats.wins, stats.losses, stats.draws), (1, 2, 0));
+        assert_eq!(stats.games_played(), 3);
+        let stats = Chess::player_statistics(alice);
+        assert_eq!((stats.wins, stats.losses, stats.draws), (2, 1, 0));
+    });
+}
+
+#[test]
+fn leaderboard_works() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+
+        let alice = account("Alice", 0, 0);
+        let bob = account("Bob", 0, 1);
+        let charlie = account("Charlie", 0, 2);
+        let dave = account("Dave", 0, 3);
+
+        let bet_asset_id = AssetId::get();
+        let bet_amount = AssetMinBalance::get() * 5;
+
+        let ranked = |style| {
+            Chess::leaderboard(style)
+                .iter()
+                .map(|(player, _)| *player)
+                .collect::<Vec<_>>()
+        };
+        let play = |challenger, opponent, resigning| {
+            assert_ok!(Chess::create_match(
+                RuntimeOrigin::signed(challenger),
+                Some(opponent),
+                MatchStyle::Bullet,
+                bet_asset_id,
+                bet_amount,
+                MatchOptions::default()
+            ));
+            let match_id = Chess::chess_match_id_from_nonce(Chess::next_nonce() - 1).unwrap();
+            assert_ok!(Chess::join_match(RuntimeOrigin::signed(opponent), match_id));
+            assert_ok!(Chess::resign(RuntimeOrigin::signed(resigning), match_id));
+        };
+
+        // ratings set at genesis are established, but unranked until MinLeaderboardGames rated matches
+        assert!(Chess::leaderboard(MatchStyle::Bullet).is_empty());
+        play(alice, bob, bob);
+        assert!(Chess::leaderboard(MatchStyle::Bullet).is_empty());
+        play(alice, bob, bob);
+        assert_eq!(ranked(MatchStyle::Bullet), vec![bob, alice]);
+        play(alice, dave, dave);
+        play(alice, dave, dave);
+        assert_eq!(ranked(MatchStyle::Bullet), vec![bob, dave, alice]);
+
+        // ratings are kept up to date, but the ranked players didn't change
+        System::reset_events();
+        let rating_a = Chess::rating(&alice, &MatchStyle::Bullet);
+        play(alice, bob, bob);
+        assert_eq!(ranked(MatchStyle::Bullet), vec![bob, dave, alice]);
+        assert_eq!(
+            Chess::leaderboard(MatchStyle::Bullet)[2],
+            (alice, Chess::rating(&alice, &MatchStyle::Bullet))
+        );
+        assert!(Chess::rating(&alice, &MatchStyle::Bullet) > rating_a);
+        assert!(!System::events().iter().any(|record| matches!(
+            record.event,
+            RuntimeEvent::Chess(Event::LeaderboardChanged(..))
+        )));
+
+        // charlie needs MinLeaderboardGames rated matches and a better rating than the last one
+        let mut games = 0;
+        while Chess::rating(&charlie, &MatchStyle::Bullet)
+            <= Chess::rating(&alice, &MatchStyle::Bullet)
+        {
+            assert_eq!(ranked(MatchStyle::Bullet), vec![bob, dave, alice]);
+            play(alice, charlie, alice);
+            games += 1;
+            assert!(games < 20);
+        }
+        assert_eq!(ranked(MatchStyle::Bullet), vec![bob, dave, charlie]);
+        System::assert_last_event(
+            Event::LeaderboardChanged {
+                0: MatchStyle::Bullet,
+                1: vec![bob, dave, charlie],
+            }
+            .into(),
+        );
+
+        // rankings are exact, so charlie drops out as soon as their rating falls below alice's,
+        // even though alice didn't play meanwhile
+        let mut games = 0;
+        while Chess::rating(&charlie, &MatchStyle::Bullet)
+            >= Chess::rating(&alice, &MatchStyle::Bullet)
+        {
+            assert_eq!(ranked(MatchStyle::Bullet), vec![bob, dave, charlie]);
+            play(bob, charlie, charlie);
+            games += 1;
+            assert!(games < 20);
+        }
+        assert_eq!(ranked(MatchStyle::Bullet), vec![bob, dave, alice]);
+        System::assert_last_event(
+            Event::LeaderboardChanged {
+                0: MatchStyle::Bullet,
+                1: vec![bob, dave, alice],
+            }
+            .into(),
+        );
+
+        // players who are cut are still indexed, from the highest rating to the lowest
+        let ratings: Vec<_> = crate::RankedPlayers::<Test>::iter_key_prefix(MatchStyle::Bullet)
+            .map(|(_, player)| Chess::rating(&player, &MatchStyle::Bullet))
+            .collect();
+        assert_eq!(ratings.len(), 4);
+        assert!(ratings.windows(2).all(|pair| pair[0] >= pair[1]));
+
+        // other styles are left untouched
+        assert!(ranked(MatchStyle::Blitz).is_empty());
+    });
+}
+
+#[test]
+fn rating_history_works() {
+    new_test_ext().execute_with(|| {
+        let alice = account("Alice", 0, 0);
+        let bob = account("Bob", 0, 1);
+
+        let bet_asset_id = AssetId::get();
+        let bet_amount = AssetMinBalance::get() * 5;
+
+        let mut match_ids = vec![];
+        for block in 1..=MaxRatingHistory::get() as u64 + 1 {
+            System::set_block_number(block);
+            assert_ok!(Chess::create_match(
+                RuntimeOrigin::signed(alice),
+                Some(bob),
+                MatchStyle::Bullet,
+                bet_asset_id,
+                bet_amount,
+                MatchOptions::default()
+            ));
+            let match_id = Chess::chess_match_id_from_nonce(Chess::next_nonce() - 1).unwrap();
+            assert_ok!(Chess::join_match(RuntimeOrigin::signed(bob), match_id));
+            assert_ok!(Chess::resign(RuntimeOrigin::signed(bob), match_id));
+            match_ids.push(match_id);
+
+            if block == 1 {
+                assert_eq!(
+                    Chess::rating_history(alice, MatchStyle::Bullet).into_inner(),
+                    vec![RatingChange {
+                        block: 1,
+                        match_id,
+                        old_rating: 2000,
+                        new_rating: 2029,
+                    }]
+                );
+                assert_eq!(
+                    Chess::rating_history(bob, MatchStyle::Bullet)[0].new_rating,
+                    2371
+                );
+            }
+        }
+
+        // the oldest change made room for the latest one
+        let history = Chess::rating_history(alice, MatchStyle::Bullet);
+        assert_eq!(history.len() as u32, MaxRatingHistory::get());
+        assert_eq!(
+            history
+                .iter()
+                .map(|change| change.match_id)
+                .collect::<Vec<_>>(),
+            match_ids[1..]
+        );
+        assert_eq!(history[0].block, 2);
+        for (previous, next) in history.iter().zip(history.iter().skip(1)) {
+            assert_eq!(previous.new_rating, next.old_rating);
+        }
+        assert_eq!(
+            history.last().unwrap().new_rating,
+            Chess::rating(&alice, &MatchStyle::Bullet)
+        );
+
+        // other styles have their own history
+        assert!(Chess::rating_history(alice, MatchStyle::Blitz).is_empty());
+    });
+}
+
+#[test]
+fn check_elo_player_aborts() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+
+        let alice = account("Alice", 0, 0);
+        let bob = account("Bob", 0, 1);
+
+        let bet_asset_id = AssetId::get();
+        let bet_amount = AssetMinBalance::get() * 5;
+
+        assert_ok!(Chess::create_match(
+            RuntimeOrigin::signed(bob),
+            Some(alice),
+            MatchStyle::Bullet,
+            bet_asset_id,
+            bet_amount,
+            MatchOptions::default()
+        ));
+
+        let match_id = Chess::chess_match_id_from_nonce(0).unwrap();
+
+        assert_ok!(Chess::join_match(RuntimeOrigin::signed(alice), match_id));
+
+        // check elo before the match finishes
+        assert_eq!(Chess::player_rating(alice, MatchStyle::Bullet), 2000);
+        assert_eq!(Chess::player_rating(bob, MatchStyle::Bullet), 2400);
+
+        assert_ok!(Chess::force_board_state(
+            match_id,
+            "8/8/8/8/8/5K2/Q7/7k w - - 1 68".into()
+        ));
+        // this move forces draws
+        assert_ok!(Chess::make_move(
+            RuntimeOrigin::signed(bob),
+            match_id,
+            "a2f2".into()
+        ));
+        assert_eq!(Chess::chess_matches(match_id), None);
+
+        // check the elo after the match is complete
+        assert_eq!(Chess::player_rating(alice, MatchStyle::Bullet), 2013);
+        assert_eq!(Chess::player_rating(bob, MatchStyle::Bullet), 2387);
+    });
+}
+
+#[test]
+fn make_move_under_promotion_works() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+
+        let alice = account("Alice", 0, 0);
+        let bob = account("Bob", 0, 1);
+
+        let bet_asset_id = AssetId::get();
+        let bet_amount = AssetMinBalance::get() * 5; // assuming T::IncentiveShare is 10%
+
+        assert_ok!(Chess::create_match(
+            RuntimeOrigin::signed(alice),
+            Some(bob),
+            MatchStyle::Bullet,
+            bet_asset_id,
+            bet_amount,
+            MatchOptions::default()
+        ));
+
+        let match_id = Chess::chess_match_id_from_nonce(0).unwrap();
+
+        assert_ok!(Chess::join_match(RuntimeOrigin::signed(bob), match_id));
+
+        assert_ok!(Chess::force_board_state(
+            match_id,
+            "8/P5kp/8/8/8/8/7P/K7 w - - 0 1".into()
+        ));
+
+        // test InvalidMoveEncoding for pieces a pawn cannot be promoted to
+        assert_noop!(
+            Chess::make_move(RuntimeOrigin::signed(alice), match_id, "a7a8k".into()),
+            Error::<Test>::InvalidMoveEncoding
+        );
+        assert_noop!(
+            Chess::make_move(RuntimeOrigin::signed(alice), match_id, "a7a8p".into()),
+            Error::<Test>::InvalidMoveEncoding
+        );
+
+        // test IllegalMove for a promotion piece on a non-promoting move
+        assert_noop!(
+            Chess::make_move(RuntimeOrigin::signed(alice), match_id, "h2h3q".into()),
+            Error::<Test>::IllegalMove
+        );
+
+        // test successful under-promotion
+        assert_ok!(Chess::make_move(
+            RuntimeOrigin::signed(alice),
+            match_id,
+            "a7a8n".into()
+        ));
+        System::assert_last_event(
+            Event::MoveExecuted {
+                0: match_id,
+                1: alice,
+                2: "a7a8n".into(),
+                3: Some(PromotionPiece::Knight),
+            }
+            .into(),
+        );
+
+        let chess_match = Chess::chess_matches(match_id).unwrap();
+        assert_eq!(chess_match.state, MatchState::OnGoing(NextMove::Blacks));
+        assert_eq!(
+            chess_match.board,
+            "N7/6kp/8/8/8/8/7P/K7 b - - 0 1".as_bytes().to_vec()
+        );
+    });
+}
+
+#[test]
+fn make_move_promotion_checkmate_works() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+
+        let alice = account("Alice", 0, 0);
+        let bob = account("Bob", 0, 1);
+
+        let bet_asset_id = AssetId::get();
+
+        let initial_balance_a = Assets::balance(bet_asset_id, alice);
+        let initial_balance_b = Assets::balance(bet_asset_id, bob);
+
+        let bet_amount = AssetMinBalance::get() * 5; // assuming T::IncentiveShare is 10%
+
+        assert_ok!(Chess::create_match(
+            RuntimeOrigin::signed(alice),
+            Some(bob),
+            MatchStyle::Bullet,
+            bet_asset_id,
+            bet_amount,
+            MatchOptions::default()
+        ));
+
+        let match_id = Chess::chess_match_id_from_nonce(0).unwrap();
+
+        assert_ok!(Chess::join_match(RuntimeOrigin::signed(bob), match_id));
+
+        assert_ok!(Chess::force_board_state(
+            match_id,
+            "k7/2P5/1K6/8/8/8/8/8 w - - 0 1".into()
+        ));
+
+        // promoting to a queen delivers checkmate
+        assert_ok!(Chess::make_move(
+            RuntimeOrigin::signed(alice),
+            match_id,
+            "c7c8q".into()
+        ));
+        System::assert_has_event(
+            Event::MoveExecuted {
+                0: match_id,
+                1: alice,
+                2: "c7c8q".into(),
+                3: Some(PromotionPiece::Queen),
+            }
+            .into(),
+        );
+        System::assert_has_event(
+            Event::MatchWon {
+                0: match_id,
+                1: alice,
+                2: "k1Q5/8/1K6/8/8/8/8/8 b - - 0 1".into(),
+                3: Termination::Checkmate,
+            }
+            .into(),
+        );
+        assert_eq!(Chess::chess_matches(match_id), None);
+
+        let final_balance_a = Assets::balance(bet_asset_id, alice);
+        let final_balance_b = Assets::balance(bet_asset_id, bob);
+        assert_eq!(final_balance_a, initial_balance_a + bet_amount);
+        assert_eq!(final_balance_b, initial_balance_b - bet_amount);
+    });
+}
+
+const BOARD_STATE: &str = "Q7/5Q2/8/8/3k4/6P1/6BP/7K b - - 0 67";
+
+#[test]
+fn force_board_state_works() {
+    new_test_ext().execute_with(|| {
+        let alice = account("Alice", 0, 0);
+        let bob = account("Bob", 0, 1);
+
+        let bet_asset_id = AssetId::get();
+        let bet_amount = AssetMinBalance::get() * 5; // assuming T::IncentiveShare is 10%
+
+        assert_ok!(Chess::create_match(
+            RuntimeOrigin::signed(alice),
+            Some(bob),
+            MatchStyle::Bullet,
+            bet_asset_id,
+            bet_amount,
+            MatchOptions::default()
+        ));
+
+        let match_id = Chess::chess_match_id_from_nonce(0).unwrap();
+
+        assert_ok!(Chess::join_match(RuntimeOrigin::signed(bob), match_id));
+
+        assert_ok!(Chess::force_board_state(match_id, BOARD_STATE.into()));
+
+        let chess_match = Chess::chess_matches(match_id).unwrap();
+        assert_eq!(chess_match.board, BOARD_STATE.as_bytes());
+    });
+}
+
+#[test]
+fn claim_victory_works() {
+    new_test_ext().execute_with(|| {
+        let alice = account("Alice", 0, 0);
+        let bob = account("Bob", 0, 1);
+
+        let bet_asset_id = AssetId::get();
+        let bet_amount = AssetMinBalance::get() * 5; // assuming T::IncentiveShare is 10%
+
+        let initial_balance_a = Assets::balance(bet_asset_id, alice);
+        let initial_balance_b = Assets::balance(bet_asset_id, bob);
+
+        assert_ok!(Chess::create_match(
+            RuntimeOrigin::signed(alice),
+            Some(bob),
+            MatchStyle::Bullet,
+            bet_asset_id,
+            bet_amount,
+            MatchOptions::default()
+        ));
+
+        let match_id = Chess::chess_match_id_from_nonce(0).unwrap();
+
+        assert_ok!(Chess::join_match(RuntimeOrigin::signed(bob), match_id));
+        assert_ok!(Chess::make_move(
+            RuntimeOrigin::signed(alice),
+            match_id,
+            "e2e4".into()
+        ));
+
+        // advance the block number to the point where bob's time-to-move is expired
+        System::set_block_number(
+            System::block_number() + <Test as Config>::BulletPeriod::get() + 1,
+        );
+
+        // alice claims victory
+        assert_ok!(Chess::clear_abandoned_match(
+            RuntimeOrigin::signed(alice),
+            match_id
+        ));
+
+        System::assert_has_event(
+            Event::MatchWon {
+                0: match_id,
+                1: alice,
+                2: "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1".into(),
+                3: Termination::Timeout,
+            }
+            .into(),
+        );
+
+        assert_eq!(Chess::chess_matches(match_id), None);
+        assert_eq!(Chess::chess_match_id_from_nonce(0), None);
+
+        let final_balance_a = Assets::balance(bet_asset_id, alice);
+        let final_balance_b = Assets::balance(bet_asset_id, bob);
+        assert_eq!(final_balance_a, initial_balance_a + bet_amount);
+        assert_eq!(final_balance_b, initial_balance_b - bet_amount);
+
+        let finished_match = Chess::finished_matches(match_id).unwrap();
+        assert_eq!(finished_match.winner, Some(alice));
+        assert_eq!(finished_match.termination, Termination::Timeout);
+        assert_eq!(finished_match.payout, bet_amount * 2);
+    });
+}
+
+#[test]
+fn clocks_work() {
+    new_test_ext().execute_with(|| {
+        let alice = account("Alice", 0, 0);
+        let bob = account("Bob", 0, 1);
+
+        let bet_asset_id = AssetId::get();
+        let bet_amount = AssetMinBalance::get() * 5; // assuming T::IncentiveShare is 10%
+        let (per_move, base, increment) = (8, 10, 2);
+
+        assert_ok!(Chess::create_match(
+            RuntimeOrigin::signed(alice),
+            Some(bob),
+            MatchStyle::Custom {
+                per_move,
+                base,
+                increment
+            },
+            bet_asset_id,
+            bet_amount,
+            MatchOptions {
+                rated: false,
+                ..Default::default()
+            }
+        ));
+        let match_id = Chess::chess_match_id_from_nonce(0).unwrap();
+
+        // both players start with a full time bank
+        let chess_match = Chess::chess_matches(match_id).unwrap();
+        assert_eq!(chess_match.white_clock, base as u64);
+        assert_eq!(chess_match.black_clock, base as u64);
+
+        // alice's clock starts as soon as bob joins
+        System::set_block_number(1);
+        assert_ok!(Chess::join_match(RuntimeOrigin::signed(bob), match_id));
+        assert_eq!(Chess::chess_matches(match_id).unwrap().last_move, 1);
+
+        // elapsed blocks are subtracted from the clock of whoever moved, then the increment is added
+        System::set_block_number(4);
+        assert_ok!(Chess::make_move(
+            RuntimeOrigin::signed(alice),
+            match_id,
+            "e2e4".into()
+        ));
+        let chess_match = Chess::chess_matches(match_id).unwrap();
+        assert_eq!(chess_match.white_clock, (base - 3 + increment) as u64);
+        assert_eq!(chess_match.black_clock, base as u64);
+
+        System::set_block_number(12);
+        assert_ok!(Chess::make_move(
+            RuntimeOrigin::signed(bob),
+            match_id,
+            "e7e5".into()
+        ));
+        let chess_match = Chess::chess_matches(match_id).unwrap();
+        assert_eq!(chess_match.white_clock, (base - 3 + increment) as u64);
+        assert_eq!(chess_match.black_clock, (base - 8 + increment) as u64);
+
+        System::set_block_number(20);
+        assert_ok!(Chess::make_move(
+            RuntimeOrigin::signed(alice),
+            match_id,
+            "g1f3".into()
+        ));
+
+        // bob has 4 blocks left
+        System::set_block_number(24);
+        assert_noop!(
+            Chess::clear_abandoned_match(RuntimeOrigin::signed(alice), match_id),
+            Error::<Test>::MoveNotExpired
+        );
+
+        // bob's flag falls, so moving is no longer possible
+        System::set_block_number(25);
+        assert_noop!(
+            Chess::make_move(RuntimeOrigin::signed(bob), match_id, "b8c6".into()),
+            Error::<Test>::OutOfTime
+        );
+
+        assert_ok!(Chess::clear_abandoned_match(
+            RuntimeOrigin::signed(alice),
+            match_id
+        ));
+        System::assert_has_event(
+            Event::MatchWon {
+                0: match_id,
+                1: alice,
+                2: "rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2".into(),
+                3: Termination::Timeout,
+            }
+            .into(),
+        );
+
+        // presets give their period as a bank, plus their increment
+        for (style, period, increment) in [
+            (
+                MatchStyle::Bullet,
+                BulletPeriod::get(),
+                BulletIncrement::get(),
+            ),
+            (MatchStyle::Blitz, BlitzPeriod::get(), BlitzIncrement::get()),
+            (MatchStyle::Rapid, RapidPeriod::get(), RapidIncrement::get()),
+        ] {
+            assert_eq!(
+                Chess::time_control(&style),
+                TimeControl {
+                    per_move: period,
+                    base: period,
+                    increment,
+                }
+            );
+        }
+    });
+}
+
+#[test]
+fn daily_time_control_works() {
+    new_test_ext().execute_with(|| {
+        let alice = account("Alice", 0, 0);
+        let bob = account("Bob", 0, 1);
+
+        let bet_asset_id = AssetId::get();
+        let bet_amount = AssetMinBalance::get() * 5; // assuming T::IncentiveShare is 10%
+        let period = DailyPeriod::get();
+
+        assert_ok!(Chess::create_match(
+            RuntimeOrigin::signed(alice),
+            Some(bob),
+            MatchStyle::Daily,
+            bet_asset_id,
+            bet_amount,
+            MatchOptions::default()
+        ));
+        let match_id = Chess::chess_match_id_from_nonce(0).unwrap();
+        assert_eq!(
+            Chess::chess_matches(match_id).unwrap().time_control,
+            TimeControl {
+                per_move: period,
+                base: period,
+                increment: period,
+            }
+        );
+
+        System::set_block_number(1);
+        assert_ok!(Chess::join_match(RuntimeOrigin::signed(bob), match_id));
+
+        // every move may take the whole period, even once the match lasted longer than a period
+        for (who, move_fen, elapsed) in [
+            (alice, "e2e4", period),
+            (bob, "e7e5", 1),
+            (alice, "g1f3", period),
+        ] {
+            System::set_block_number(System::block_number() + elapsed);
+            assert_ok!(Chess::make_move(
+                RuntimeOrigin::signed(who),
+                match_id,
+                move_fen.into()
+            ));
+        }
+
+        // but not more than that, even though bob's quick move left more than that on their clock
+        assert_eq!(
+            Chess::chess_matches(match_id).unwrap().black_clock,
+            period * 2 - 1
+        );
+        System::set_block_number(System::block_number() + period);
+        assert_noop!(
+            Chess::clear_abandoned_match(RuntimeOrigin::signed(alice), match_id),
+            Error::<Test>::MoveNotExpired
+        );
+        System::set_block_number(System::block_number() + 1);
+        assert_noop!(
+            Chess::make_move(RuntimeOrigin::signed(bob), match_id, "b8c6".into()),
+            Error::<Test>::OutOfTime
+        );
+        assert_ok!(Chess::clear_abandoned_match(
+            RuntimeOrigin::signed(alice),
+            match_id
+        ));
+    });
+}
+
+#[test]
+fn custom_time_control_works() {
+    new_test_ext().execute_with(|| {
+        let alice = account("Alice", 0, 0);
+        let bob = account("Bob", 0, 1);
+
+        let bet_asset_id = AssetId::get();
+        let bet_amount = AssetMinBalance::get() * 5; // assuming T::IncentiveShare is 10%
+        let (min, max) = (MinTimeControl::get() as u32, MaxTimeControl::get() as u32);
+
+        // assert InvalidTimeControl error
+        for (per_move, base, increment) in [
+            (min - 1, min, 0),
+            (min, min - 1, 0),
+            (max + 1, max, 0),
+            (max, max + 1, 0),
+            (min, min, max + 1),
+        ] {
+            assert_noop!(
+                Chess::create_match(
+                    RuntimeOrigin::signed(alice),
+                    Some(bob),
+                    MatchStyle::Custom {
+                        per_move,
+                        base,
+                        increment
+                    },
+                    bet_asset_id,
+                    bet_amount,
+                    MatchOptions {
+                        rated: false,
+                        ..Default::default()
+                    }
+                ),
+                Error::<Test>::InvalidTimeControl
+            );
+            assert_noop!(
+                Chess::enter_queue(
+                    RuntimeOrigin::signed(alice),
+                    MatchStyle::Custom {
+                        per_move,
+                        base,
+                        increment
+                    },
+                    bet_asset_id,
+                    bet_amount
+                ),
+                Error::<Test>::InvalidTimeControl
+            );
+        }
+
+        // plenty of time in the bank, but only 5 blocks per move
+        assert_ok!(Chess::create_match(
+            RuntimeOrigin::signed(alice),
+            Some(bob),
+            MatchStyle::Custom {
+                per_move: 5,
+                base: 100,
+                increment: 0
+            },
+            bet_asset_id,
+            bet_amount,
+            MatchOptions {
+                rated: false,
+                ..Default::default()
+            }
+        ));
+        let match_id = Chess::chess_match_id_from_nonce(0).unwrap();
+
+        System::set_block_number(1);
+        assert_ok!(Chess::join_match(RuntimeOrigin::signed(bob), match_id));
+
+        System::set_block_number(6);
+        assert_noop!(
+            Chess::clear_abandoned_match(RuntimeOrigin::signed(bob), match_id),
+            Error::<Test>::MoveNotExpired
+        );
+
+        System::set_block_number(7);
+        assert_noop!(
+            Chess::make_move(RuntimeOrigin::signed(alice), match_id, "e2e4".into()),
+            Error::<Test>::OutOfTime
+        );
+        assert_ok!(Chess::clear_abandoned_match(
+            RuntimeOrigin::signed(bob),
+            match_id
+        ));
+        System::assert_has_event(
+            Event::MatchWon {
+                0: match_id,
+                1: bob,
+                2: Board::default().to_string().into(),
+                3: Termination::Timeout,
+            }
+            .into(),
+        );
+    });
+}
+
+#[test]
+fn janitor_incentive_works() {
+    new_test_ext().execute_with(|| {
+        let alice = account("Alice", 0, 0);
+        let bob = account("Bob", 0, 1);
+        let charlie = account("Charlie", 0, 2);
+
+        let bet_asset_id = AssetId::get();
+        let bet_amount = AssetMinBalance::get() * 5; // assuming T::IncentiveShare is 10%
+
+        let initial_balance_a = Assets::balance(bet_asset_id, alice);
+        let initial_balance_b = Assets::balance(bet_asset_id, bob);
+        let initial_balance_c = Assets::balance(bet_asset_id, charlie);
+
+        assert_ok!(Chess::create_match(
+            RuntimeOrigin::signed(alice),
+            Some(bob),
+            MatchStyle::Bullet,
+            bet_asset_id,
+            bet_amount,
+            MatchOptions::default()
+        ));
+
+        let match_id = Chess::chess_match_id_from_nonce(0).unwrap();
+
+        assert_ok!(Chess::join_match(RuntimeOrigin::signed(bob), match_id));
+        assert_ok!(Chess::make_move(
+            RuntimeOrigin::signed(alice),
+            match_id,
+            "e2e4".into()
+        ));
+
+        let chess_match = Chess::chess_matches(match_id).unwrap();
+        let (janitor_incentive, actual_prize) = chess_match.janitor_incentive();
+
+        // advance the block number to the point where bob's clock is expired (1 period)
+        // and alice's time to claim victory is also expired (10 more periods)
+        System::set_block_number(
+            System::block_number() + <Test as Config>::BulletPeriod::get() * 11 + 1,
+        );
+
+        // charlie cleans abandoned match
+        assert_ok!(Chess::clear_abandoned_match(
+            RuntimeOrigin::signed(charlie),
+            match_id
+        ));
+
+        System::assert_has_event(
+            Event::MatchWon {
+                0: match_id,
+                1: alice,
+                2: "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1".into(),
+                3: Termination::Abandonment,
+            }
+            .into(),
+        );
+
+        assert_eq!(Chess::chess_matches(match_id), None);
+        assert_eq!(Chess::chess_match_id_from_nonce(0), None);
+
+        let final_balance_a = Assets::balance(bet_asset_id, alice);
+        let final_balance_b = Assets::balance(bet_asset_id, bob);
+        let final_balance_c = Assets::balance(bet_asset_id, charlie);
+        assert_eq!(
+            final_balance_a,
+            initial_balance_a - bet_amount + actual_prize
+        );
+        assert_eq!(final_balance_b, initial_balance_b - bet_amount);
+        assert_eq!(final_balance_c, initial_balance_c + janitor_incentive);
+
+        let finished_match = Chess::finished_matches(match_id).unwrap();
+        assert_eq!(finished_match.winner, Some(alice));
+        assert_eq!(finished_match.termination, Termination::Abandonment);
+        assert_eq!(finished_match.payout, actual_prize);
+    });
+}
+
+#[test]
+fn pallet_account_stakes_work() {
+    new_test_ext().execute_with(|| {
+        let alice = account("Alice", 0, 0);
+        let bob = account("Bob", 0, 1);
+        let pallet_account = <Test as ConfigHelper>::pallet_account();
+
+        let bet_asset_id = AssetId::get();
+        let bet_amount = AssetMinBalance::get() * 5;
+
+        assert_ok!(Chess::create_match(
+            RuntimeOrigin::signed(alice),
+            Some(bob),
+            MatchStyle::Bullet,
+            bet_asset_id,
+            bet_amount,
+            MatchOptions::default()
+        ));
+        let match_id = Chess::chess_match_id_from_nonce(0).unwrap();
+        assert_ok!(Chess::join_match(RuntimeOrigin::signed(bob), match_id));
+
+        // both stakes are escrowed in the pallet account
+        assert_eq!(
+            Assets::balance(bet_asset_id, pallet_account),
+            bet_amount * 2
+        );
+        assert_eq!(
+            AssetsWithHolds::total_balance_on_hold(bet_asset_id, &alice),
+            0
+        );
+
+        assert_ok!(Chess::resign(RuntimeOrigin::signed(bob), match_id));
+        assert_eq!(Assets::balance(bet_asset_id, pallet_account), 0);
+    });
+}
+
+#[test]
+fn hold_stakes_work() {
+    new_test_ext().execute_with(|| {
+        UseHolds::set(true);
+
+        let alice = account("Alice", 0, 0);
+        let bob = account("Bob", 0, 1);
+        let charlie = account("Charlie", 0, 2);
+        let pallet_account = <Test as ConfigHelper>::pallet_account();
+
+        let bet_asset_id = AssetId::get();
+        let bet_amount = AssetMinBalance::get() * 5;
+        let held = |who: &u64| AssetsWithHolds::total_balance_on_hold(bet_asset_id, who);
+
+        let initial_balance_a = Assets::balance(bet_asset_id, alice);
+        let initial_balance_b = Assets::balance(bet_asset_id, bob);
+        let initial_balance_c = Assets::balance(bet_asset_id, charlie);
+
+        let start_match = |nonce: u128| {
+            assert_ok!(Chess::create_match(
+                RuntimeOrigin::signed(alice),
+                Some(bob),
+                MatchStyle::Bullet,
+                bet_asset_id,
+                bet_amount,
+                MatchOptions::default()
+            ));
+            let match_id = Chess::chess_match_id_from_nonce(nonce).unwrap();
+            assert_ok!(Chess::join_match(RuntimeOrigin::signed(bob), match_id));
+            match_id
+        };
+        let match_id = start_match(0);
+
+        // stakes stay in the accounts of the players, nothing goes to the pallet account
+        assert_eq!(held(&alice), bet_amount);
+        assert_eq!(held(&bob), bet_amount);
+        assert_eq!(
+            Assets::balance(bet_asset_id, alice),
+            initial_balance_a - bet_amount
+        );
+        assert_eq!(Assets::balance(bet_asset_id, pallet_account), 0);
+
+        // the stake of a player in the queue is held too
+        assert_ok!(Chess::enter_queue(
+            RuntimeOrigin::signed(charlie),
+            MatchStyle::Bullet,
+            bet_asset_id,
+            bet_amount
+        ));
+        assert_eq!(held(&charlie), bet_amount);
+        assert_ok!(Chess::leave_queue(RuntimeOrigin::signed(charlie)));
+        assert_eq!(held(&charlie), 0);
+
+        assert_ok!(Chess::resign(RuntimeOrigin::signed(bob), match_id));
+
+        // the winner gets their stake back, plus the stake of the loser
+        assert_eq!(held(&alice), 0);
+        assert_eq!(held(&bob), 0);
+        assert_eq!(
+            Assets::balance(bet_asset_id, alice),
+            initial_balance_a + bet_amount
+        );
+        assert_eq!(
+            Assets::balance(bet_asset_id, bob),
+            initial_balance_b - bet_amount
+        );
+
+        // a janitor is paid out of the stake of the loser
+        let match_id = start_match(1);
+        assert_ok!(Chess::make_move(
+            RuntimeOrigin::signed(alice),
+            match_id,
+            "e2e4".into()
+        ));
+        let (janitor_incentive, actual_prize) =
+            Chess::chess_matches(match_id).unwrap().janitor_incentive();
+        System::set_block_number(
+            System::block_number() + <Test as Config>::BulletPeriod::get() * 11 + 1,
+        );
+
+        // a settlement that fails halfway reverts the whole call, and the match is kept
+        AssetHolds::insert((bet_asset_id, bob, MatchStakeReason::get()), bet_amount - 1);
+        assert!(Chess::clear_abandoned_match(RuntimeOrigin::signed(charlie), match_id).is_err());
+        assert!(Chess::chess_matches(match_id).is_some());
+        assert_eq!(held(&alice), bet_amount);
+        assert_eq!(Assets::balance(bet_asset_id, charlie), initial_balance_c);
+        AssetHolds::insert((bet_asset_id, bob, MatchStakeReason::get()), bet_amount);
+
+        assert_ok!(Chess::clear_abandoned_match(
+            RuntimeOrigin::signed(charlie),
+            match_id
+        ));
+        assert_eq!(held(&alice), 0);
+        assert_eq!(held(&bob), 0);
+        assert_eq!(
+            Assets::balance(bet_asset_id, alice),
+            initial_balance_a + actual_prize
+        );
+        assert_eq!(
+            Assets::balance(bet_asset_id, bob),
+            initial_balance_b - bet_amount * 2
+        );
+        assert_eq!(
+            Assets::balance(bet_asset_id, charlie),
+            initial_balance_c + janitor_incentive
+        );
+        assert_eq!(Assets::balance(bet_asset_id, pallet_account), 0);
+    });
+}
+
+#[test]
+fn friendly_matches_work() {
+    new_test_ext().execute_with(|| {
+        let alice = account("Alice", 0, 0);
+        // eve holds no tokens at all
+        let eve = account("Eve", 0, 4);
+        let charlie = account("Charlie", 0, 2);
+
+        let bet_asset_id = AssetId::get();
+        let initial_balance_a = Assets::balance(bet_asset_id, alice);
+        let initial_balance_c = Assets::balance(bet_asset_id, charlie);
+        assert_eq!(Assets::balance(bet_asset_id, eve), 0);
+
+        // friendly challenges can be aborted
+        assert_ok!(Chess::create_match(
+            RuntimeOrigin::signed(eve),
+            Some(alice),
+            MatchStyle::Bullet,
+            bet_asset_id,
+            0,
+            MatchOptions {
+                rated: false,
+                ..Default::default()
+            }
+        ));
+        let match_id = Chess::chess_match_id_from_nonce(0).unwrap();
+        assert_ok!(Chess::abort_match(RuntimeOrigin::signed(eve), match_id));
+
+        assert_ok!(Chess::create_match(
+            RuntimeOrigin::signed(eve),
+            Some(alice),
+            MatchStyle::Bullet,
+            bet_asset_id,
+            0,
+            MatchOptions {
+                rated: false,
+                ..Default::default()
+            }
+        ));
+        let match_id = Chess::chess_match_id_from_nonce(1).unwrap();
+        assert_ok!(Chess::join_match(RuntimeOrigin::signed(alice), match_id));
+        assert_ok!(Chess::make_move(
+            RuntimeOrigin::signed(eve),
+            match_id,
+            "e2e4".into()
+        ));
+
+        // alice abandons the match, and so does eve
+        System::set_block_number(
+            System::block_number() + <Test as Config>::BulletPeriod::get() * 11 + 1,
+        );
+
+        // charlie can still clean the abandoned match, but there is no incentive to take
+        assert_ok!(Chess::clear_abandoned_match(
+            RuntimeOrigin::signed(charlie),
+            match_id
+        ));
+        System::assert_has_event(
+            Event::MatchWon {
+                0: match_id,
+                1: eve,
+                2: "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1".into(),
+                3: Termination::Abandonment,
+            }
+            .into(),
+        );
+
+        assert_eq!(Assets::balance(bet_asset_id, eve), 0);
+        assert_eq!(Assets::balance(bet_asset_id, alice), initial_balance_a);
+        assert_eq!(Assets::balance(bet_asset_id, charlie), initial_balance_c);
+
+        let finished_match = Chess::finished_matches(match_id).unwrap();
+        assert_eq!(finished_match.winner, Some(eve));
+        assert_eq!(finished_match.payout, 0);
+
+        // friendly matches can't be rated, otherwise ratings could be farmed for free
+        assert_noop!(
+            Chess::create_match(
+                RuntimeOrigin::signed(eve),
+                Some(alice),
+                MatchStyle::Bullet,
+                bet_asset_id,
+                0,
+                MatchOptions::default()
+            ),
+            Error::<Test>::InvalidRatedMatch
+        );
+
+        // friendly matches can be found through the queue too
+        assert_ok!(Chess::enter_queue(
+            RuntimeOrigin::signed(eve),
+            MatchStyle::Bullet,
+            bet_asset_id,
+            0
+        ));
+        assert_ok!(Chess::leave_queue(RuntimeOrigin::signed(eve)));
+        assert_eq!(Assets::balance(bet_asset_id, eve), 0);
+
+        // but they are unrated, even when their style is rated
+        for player in [eve, charlie] {
+            assert_ok!(Chess::enter_queue(
+                RuntimeOrigin::signed(player),
+                MatchStyle::Bullet,
+                bet_asset_id,
+                0
+            ));
+        }
+        Chess::on_idle(System::block_number(), Weight::MAX);
+        let match_id = Chess::chess_match_id_from_nonce(Chess::next_nonce() - 1).unwrap();
+        let chess_match = Chess::chess_matches(match_id).unwrap();
+        assert_eq!(chess_match.white, Some(eve));
+        assert!(!chess_match.rated);
+
+        assert_ok!(Chess::resign(RuntimeOrigin::signed(eve), match_id));
+        assert_eq!(Chess::player_rating(charlie, MatchStyle::Bullet), 1600);
+        assert_eq!(Chess::rated_games(charlie, MatchStyle::Bullet), 0);
+    });
+}
+
+#[test]
+fn resign_works() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+
+        let alice = account("Alice", 0, 0);
+        let bob = account("Bob", 0, 1);
+        let charlie = account("Charlie", 0, 2);
+
+        let bet_asset_id = AssetId::get();
+        let bet_amount = AssetMinBalance::get() * 5; // assuming T::IncentiveShare is 10%
+
+        let initial_balance_a = Assets::balance(bet_asset_id, alice);
+        let initial_balance_b = Assets::balance(bet_asset_id, bob);
+
+        assert_ok!(Chess::create_match(
+            RuntimeOrigin::signed(alice),
+            Some(bob),
+            MatchStyle::Bullet,
+            bet_asset_id,
+            bet_amount,
+            MatchOptions::default()
+        ));
+
+        let match_id = Chess::chess_match_id_from_nonce(0).unwrap();
+
+        // test StillAwaitingOpponent error
+        assert_noop!(
+            Chess::resign(RuntimeOrigin::signed(alice), match_id),
+            Error::<Test>::StillAwaitingOpponent
+        );
+
+        assert_ok!(Chess::join_match(RuntimeOrigin::signed(bob), match_id));
+        assert_ok!(Chess::make_move(
+            RuntimeOrigin::signed(alice),
+            match_id,
+            "e2e4".into()
+        ));
+
+        // test NotMatchPlayer error
+        assert_noop!(
+            Chess::resign(RuntimeOrigin::signed(charlie), match_id),
+            Error::<Test>::NotMatchPlayer
+        );
+
+        // bob resigns instead of replying to e2e4
+        assert_ok!(Chess::resign(RuntimeOrigin::signed(bob), match_id));
+        System::assert_last_event(
+            Event::MatchResigned {
+                0: match_id,
+                1: bob,
+                2: "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1".into(),
+            }
+            .into(),
+        );
+
+        assert_eq!(Chess::chess_matches(match_id), None);
+        assert_eq!(Chess::chess_match_id_from_nonce(0), None);
+        assert_eq!(PlayerMatches::<Test>::iter_key_prefix(alice).count(), 0);
+        assert_eq!(PlayerMatches::<Test>::iter_key_prefix(bob).count(), 0);
+
+        let final_balance_a = Assets::balance(bet_asset_id, alice);
+        let final_balance_b = Assets::balance(bet_asset_id, bob);
+        assert_eq!(final_balance_a, initial_balance_a + bet_amount);
+        assert_eq!(final_balance_b, initial_balance_b - bet_amount);
+
+        // check the elo after the match is complete
+        assert_eq!(Chess::player_rating(alice, MatchStyle::Bullet), 2029);
+        assert_eq!(Chess::player_rating(bob, MatchStyle::Bullet), 2371);
+    });
+}
+
+#[test]
+fn draw_offer_works() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+
+        let alice = account("Alice", 0, 0);
+        let bob = account("Bob", 0, 1);
+        let charlie = account("Charlie", 0, 2);
+
+        let bet_asset_id = AssetId::get();
+        let bet_amount = AssetMinBalance::get() * 5; // assuming T::IncentiveShare is 10%
+
+        assert_ok!(Chess::create_match(
+            RuntimeOrigin::signed(alice),
+            Some(bob),
+            MatchStyle::Bullet,
+            bet_asset_id,
+            bet_amount,
+            MatchOptions::default()
+        ));
+
+        let match_id = Chess::chess_match_id_from_nonce(0).unwrap();
+
+        // test StillAwaitingOpponent error
+        assert_noop!(
+            Chess::offer_draw(RuntimeOrigin::signed(alice), match_id),
+            Error::<Test>::StillAwaitingOpponent
+        );
+
+        assert_ok!(Chess::join_match(RuntimeOrigin::signed(bob), match_id));
+
+        // test NotMatchPlayer error
+        assert_noop!(
+            Chess::offer_draw(RuntimeOrigin::signed(charlie), match_id),
+            Error::<Test>::NotMatchPlayer
+        );
+
+        // test NoDrawOffer error
+        assert_noop!(
+            Chess::accept_draw(RuntimeOrigin::signed(bob), match_id),
+            Error::<Test>::NoDrawOffer
+        );
+
+        assert_ok!(Chess::offer_draw(RuntimeOrigin::signed(alice), match_id));
+        System::assert_last_event(
+            Event::DrawOffered {
+                0: match_id,
+                1: alice,
+            }
+            .into(),
+        );
+        assert_eq!(
+            Chess::chess_matches(match_id).unwrap().draw_offer,
+            Some(alice)
+        );
+
+        // test DrawAlreadyOffered error
+        assert_noop!(
+            Chess::offer_draw(RuntimeOrigin::signed(bob), match_id),
+            Error::<Test>::DrawAlreadyOffered
+        );
+
+        // the offerer cannot accept or decline their own offer
+        assert_noop!(
+            Chess::accept_draw(RuntimeOrigin::signed(alice), match_id),
+            Error::<Test>::NoDrawOffer
+        );
+        assert_noop!(
+            Chess::decline_draw(RuntimeOrigin::signed(alice), match_id),
+            Error::<Test>::NoDrawOffer
+        );
+
+        // bob declines the offer
+        assert_ok!(Chess::decline_draw(RuntimeOrigin::signed(bob), match_id));
+        System::assert_last_event(
+            Event::DrawDeclined {
+                0: match_id,
+                1: bob,
+            }
+            .into(),
+        );
+        assert_eq!(Chess::chess_matches(match_id).unwrap().draw_offer, None);
+
+        // the offerer moving keeps the offer alive
+        assert_ok!(Chess::offer_draw(RuntimeOrigin::signed(alice), match_id));
+        assert_ok!(Chess::make_move(
+            RuntimeOrigin::signed(alice),
+            match_id,
+            "e2e4".into()
+        ));
+        assert_eq!(
+            Chess::chess_matches(match_id).unwrap().draw_offer,
+            Some(alice)
+        );
+
+        // the offer expires once bob moves
+        assert_ok!(Chess::make_move(
+            RuntimeOrigin::signed(bob),
+            match_id,
+            "e7e5".into()
+        ));
+        assert_eq!(Chess::chess_matches(match_id).unwrap().draw_offer, None);
+        assert_noop!(
+            Chess::accept_draw(RuntimeOrigin::signed(bob), match_id),
+            Error::<Test>::NoDrawOffer
+        );
+    });
+}
+
+#[test]
+fn accept_draw_works() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+
+        let alice = account("Alice", 0, 0);
+        let bob = account("Bob", 0, 1);
+
+        let bet_asset_id = AssetId::get();
+        let bet_amount = AssetMinBalance::get() * 5; // assuming T::IncentiveShare is 10%
+
+        let initial_balance_a = Assets::balance(bet_asset_id, alice);
+        let initial_balance_b = Assets::balance(bet_asset_id, bob);
+
+        assert_ok!(Chess::create_match(
+            RuntimeOrigin::signed(alice),
+            Some(bob),
+            MatchStyle::Bullet,
+            bet_asset_id,
+            bet_amount,
+            MatchOptions::default()
+        ));
+
+        let match_id = Chess::chess_match_id_from_nonce(0).unwrap();
+
+        assert_ok!(Chess::join_match(RuntimeOrigin::signed(bob), match_id));
+        assert_ok!(Chess::make_move(
+            RuntimeOrigin::signed(alice),
+            match_id,
+            "e2e4".into()
+        ));
+
+        assert_ok!(Chess::offer_draw(RuntimeOrigin::signed(bob), match_id));
+        assert_ok!(Chess::accept_draw(RuntimeOrigin::signed(alice), match_id));
+        System::assert_last_event(
+            Event::MatchDrawn {
+                0: match_id,
+                1: "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1".into(),
+                2: Termination::Agreement,
+            }
+            .into(),
+        );
+
+        assert_eq!(Chess::chess_matches(match_id), None);
+        assert_eq!(Chess::chess_match_id_from_nonce(0), None);
+
+        let final_balance_a = Assets::balance(bet_asset_id, alice);
+        let final_balance_b = Assets::balance(bet_asset_id, bob);
+        assert_eq!(final_balance_a, initial_balance_a);
+        assert_eq!(final_balance_b, initial_balance_b);
+
+        // check the elo after the match is complete
+        assert_eq!(Chess::player_rating(alice, MatchStyle::Bullet), 2013);
+        assert_eq!(Chess::player_rating(bob, MatchStyle::Bullet), 2387);
+    });
+}
+
+#[test]
+fn claim_draw_threefold_repetition_works() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+
+        let alice = account("Alice", 0, 0);
+        let bob = account("Bob", 0, 1);
+        let charlie = account("Charlie", 0, 2);
+
+        let bet_asset_id = AssetId::get();
+        let bet_amount = AssetMinBalance::get() * 5; // assuming T::IncentiveShare is 10%
+
+        let initial_balance_a = Assets::balance(bet_asset_id, alice);
+        let initial_balance_b = Assets::balance(bet_asset_id, bob);
+
+        assert_ok!(Chess::create_match(
+            RuntimeOrigin::signed(alice),
+            Some(bob),
+            MatchStyle::Bullet,
+            bet_asset_id,
+            bet_amount,
+            MatchOptions::default()
+        ));
+
+        let match_id = Chess::chess_match_id_from_nonce(0).unwrap();
+
+        assert_ok!(Chess::join_match(RuntimeOrigin::signed(bob), match_id));
+        assert_eq!(Chess::position_history(match_id).len(), 1);
+
+        let knight_shuffle = ["g1f3", "g8f6", "f3g1", "f6g8"];
+
+        // initial position occurs for the second time
+        for (i, m) in knight_shuffle.iter().enumerate() {
+            let player = if i % 2 == 0 { alice } else { bob };
+            assert_ok!(Chess::make_move(
+                RuntimeOrigin::signed(player),
+                match_id,
+                (*m).into()
+            ));
+        }
+
+        // test InvalidDrawClaim error
+        assert_noop!(
+            Chess::claim_draw(RuntimeOrigin::signed(alice), match_id),
+            Error::<Test>::InvalidDrawClaim
+        );
+
+        // initial position occurs for the third time
+        for (i, m) in knight_shuffle.iter().enumerate() {
+            let player = if i % 2 == 0 { alice } else { bob };
+            assert_ok!(Chess::make_move(
+                RuntimeOrigin::signed(player),
+                match_id,
+                (*m).into()
+            ));
+        }
+        assert_eq!(Chess::position_history(match_id).len(), 9);
+
+        // test NotMatchPlayer error
+        assert_noop!(
+            Chess::claim_draw(RuntimeOrigin::signed(charlie), match_id),
+            Error::<Test>::NotMatchPlayer
+        );
+
+        assert_ok!(Chess::claim_draw(RuntimeOrigin::signed(bob), match_id));
+        System::assert_last_event(
+            Event::MatchDrawn {
+                0: match_id,
+                1: "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 8 5".into(),
+                2: Termination::Repetition,
+            }
+            .into(),
+        );
+
+        assert_eq!(Chess::chess_matches(match_id), None);
+        assert_eq!(Chess::position_history(match_id).len(), 0);
+
+        let final_balance_a = Assets::balance(bet_asset_id, alice);
+        let final_balance_b = Assets::balance(bet_asset_id, bob);
+        assert_eq!(final_balance_a, initial_balance_a);
+        assert_eq!(final_balance_b, initial_balance_b);
+    });
+}
+
+#[test]
+fn claim_draw_fifty_move_rule_works() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+
+        let alice = account("Alice", 0, 0);
+        let bob = account("Bob", 0, 1);
+
+        let bet_asset_id = AssetId::get();
+        let bet_amount = AssetMinBalance::get() * 5; // assuming T::IncentiveShare is 10%
+
+        let initial_balance_a = Assets::balance(bet_asset_id, alice);
+        let initial_balance_b = Assets::balance(bet_asset_id, bob);
+
+        assert_ok!(Chess::create_match(
+            RuntimeOrigin::signed(alice),
+            Some(bob),
+            MatchStyle::Bullet,
+            bet_asset_id,
+            bet_amount,
+            MatchOptions::default()
+        ));
+
+        let match_id = Chess::chess_match_id_from_nonce(0).unwrap();
+
+        assert_ok!(Chess::join_match(RuntimeOrigin::signed(bob), match_id));
+
+        assert_ok!(Chess::force_board_state(
+            match_id,
+            "8/8/8/4k3/8/8/4K3/R7 w - - 98 80".into()
+        ));
+        assert_eq!(Chess::position_history(match_id).len(), 1);
+
+        // test InvalidDrawClaim error
+        assert_noop!(
+            Chess::claim_draw(RuntimeOrigin::signed(alice), match_id),
+            Error::<Test>::InvalidDrawClaim
+        );
+
+        assert_ok!(Chess::make_move(
+            RuntimeOrigin::signed(alice),
+            match_id,
+            "a1a2".into()
+        ));
+
+        // still one halfmove away from the fifty-move rule
+        assert_noop!(
+            Chess::claim_draw(RuntimeOrigin::signed(bob), match_id),
+            Error::<Test>::InvalidDrawClaim
+        );
+
+        // a pawn move or capture resets the position history
+        assert_ok!(Chess::force_board_state(
+            match_id,
+            "8/8/8/4k3/8/8/P3K3/8 b - - 98 80".into()
+        ));
+        assert_ok!(Chess::make_move(
+            RuntimeOrigin::signed(bob),
+            match_id,
+            "e5d5".into()
+        ));
+        assert_eq!(Chess::position_history(match_id).len(), 2);
+        assert_ok!(Chess::make_move(
+            RuntimeOrigin::signed(alice),
+            match_id,
+            "a2a3".into()
+        ));
+        assert_eq!(Chess::position_history(match_id).len(), 1);
+
+        // fifty moves without captures or pawn moves don't end the match on their own
+        assert_ok!(Chess::force_board_state(
+            match_id,
+            "8/8/8/4k3/8/8/4K3/R7 b - - 98 80".into()
+        ));
+        assert_ok!(Chess::make_move(
+            RuntimeOrigin::signed(bob),
+            match_id,
+            "e5d5".into()
+        ));
+        assert_ok!(Chess::make_move(
+            RuntimeOrigin::signed(alice),
+            match_id,
+            "a1a2".into()
+        ));
+        assert_eq!(
+            Chess::chess_matches(match_id).unwrap().state,
+            MatchState::OnGoing(NextMove::Blacks)
+        );
+
+        // but either player can claim the draw
+        assert_ok!(Chess::claim_draw(RuntimeOrigin::signed(bob), match_id));
+        System::assert_last_event(
+            Event::MatchDrawn {
+                0: match_id,
+                1: "8/8/8/3k4/8/8/R3K3/8 b - - 100 81".into(),
+                2: Termination::FiftyMoveRule,
+            }
+            .into(),
+        );
+        assert_eq!(Chess::chess_matches(match_id), None);
+        let finished_match = Chess::finished_matches(match_id).unwrap();
+        assert_eq!(finished_match.termination, Termination::FiftyMoveRule);
+        assert_eq!(finished_match.winner, None);
+        assert_eq!(Assets::balance(bet_asset_id, alice), initial_balance_a);
+        assert_eq!(Assets::balance(bet_asset_id, bob), initial_balance_b);
+    });
+}
+
+#[test]
+fn move_history_works() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+
+        let alice = account("Alice", 0, 0);
+        let bob = account("Bob", 0, 1);
+
+        let bet_asset_id = AssetId::get();
+        let bet_amount = AssetMinBalance::get() * 5; // assuming T::IncentiveShare is 10%
+
+        assert_ok!(Chess::create_match(
+            RuntimeOrigin::signed(alice),
+            Some(bob),
+            MatchStyle::Bullet,
+            bet_asset_id,
+            bet_amount,
+            MatchOptions::default()
+        ));
+
+        let match_id = Chess::chess_match_id_from_nonce(0).unwrap();
+
+        assert_ok!(Chess::join_match(RuntimeOrigin::signed(bob), match_id));
+
+        let moves = ["e2e4", "e7e5", "g1f3", "b8c6"];
+        for (i, m) in moves.iter().enumerate() {
+            let player = if i % 2 == 0 { alice } else { bob };
+            assert_ok!(Chess::make_move(
+                RuntimeOrigin::signed(player),
+                match_id,
+                (*m).into()
+            ));
+        }
+
+        let match_moves = Chess::match_moves(match_id);
+        assert_eq!(match_moves.len(), moves.len());
+        for (compact_move, m) in match_moves.iter().zip(moves) {
+            assert_eq!(compact_move.to_move().unwrap().to_string(), m);
+        }
+
+        // promotion pieces survive the compact encoding
+        let promotion: cozy_chess::Move = "a7a8n".parse().unwrap();
+        assert_eq!(CompactMove::from_move(promotion).to_move(), Some(promotion));
+
+        let chess_match = Chess::chess_matches(match_id).unwrap();
+        assert_eq!(
+            pgn::match_to_pgn(&chess_match, &match_moves),
+            Ok(format!(
+                "[Event \"Substrate Chess\"]\n\
+                 [Site \"?\"]\n\
+                 [Date \"????.??.??\"]\n\
+                 [Round \"?\"]\n\
+                 [White \"{:?}\"]\n\
+                 [Black \"{:?}\"]\n\
+                 [Result \"*\"]\n\
+                 [MatchStyle \"Bullet\"]\n\
+                 [BetAsset \"{:?}\"]\n\
+                 [BetAmount \"{:?}\"]\n\
+                 \n\
+                 1. e4 e5 2. Nf3 Nc6 *\n",
+                alice, bob, bet_asset_id, bet_amount
+            ))
+        );
+
+        // moves that don't lead to the current board can't be exported
+        assert_eq!(
+            pgn::match_to_pgn(&chess_match, &match_moves[..2]),
+            Err(pgn::PgnError::IncompleteMoveHistory)
+        );
+
+        // move history is cleaned up once the match is over
+        assert_ok!(Chess::resign(RuntimeOrigin::signed(alice), match_id));
+        assert_eq!(Chess::match_moves(match_id).len(), 0);
+    });
+}
+
+#[test]
+fn move_history_truncation_works() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+
+        let alice = account("Alice", 0, 0);
+        let bob = account("Bob", 0, 1);
+
+        let bet_asset_id = AssetId::get();
+        let bet_amount = AssetMinBalance::get() * 5; // assuming T::IncentiveShare is 10%
+
+        assert_ok!(Chess::create_match(
+            RuntimeOrigin::signed(alice),
+            Some(bob),
+            MatchStyle::Bullet,
+            bet_asset_id,
+            bet_amount,
+            MatchOptions::default()
+        ));
+
+        let match_id = Chess::chess_match_id_from_nonce(0).unwrap();
+
+        assert_ok!(Chess::join_match(RuntimeOrigin::signed(bob), match_id));
+
+        // fill the move history up to MaxMoves
+        let filler = CompactMove::from_move("e2e4".parse().unwrap());
+        crate::MatchMoves::<Test>::insert(
+            match_id,
+            BoundedVec::truncate_from(vec![filler; MaxMoves::get() as usize]),
+        );
+        assert!(!Chess::move_history_truncated(match_id));
+
+        // moves are still played once the history is full, but not recorded
+        assert_ok!(Chess::make_move(
+            RuntimeOrigin::signed(alice),
+            match_id,
+            "e2e4".into()
+        ));
+        assert_eq!(Chess::match_moves(match_id).len(), MaxMoves::get() as usize);
+        assert!(Chess::move_history_truncated(match_id));
+        assert_eq!(
+            Chess::chess_matches(match_id).unwrap().state,
+            MatchState::OnGoing(NextMove::Blacks)
+        );
+
+        assert_ok!(Chess::resign(RuntimeOrigin::signed(alice), match_id));
+        assert!(!Chess::move_history_truncated(match_id));
+    });
+}
+
+#[test]
+fn events_to_pgn_works() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+
+        let alice = account("Alice", 0, 0);
+        let bob = account("Bob", 0, 1);
+
+        let bet_asset_id = AssetId::get();
+        let bet_amount = AssetMinBalance::get() * 5; // assuming T::IncentiveShare is 10%
+
+        assert_ok!(Chess::create_match(
+            RuntimeOrigin::signed(alice),
+            Some(bob),
+            MatchStyle::Bullet,
+            bet_asset_id,
+            bet_amount,
+            MatchOptions::default()
+        ));
+
+        let match_id = Chess::chess_match_id_from_nonce(0).unwrap();
+
+        assert_ok!(Chess::join_match(RuntimeOrigin::signed(bob), match_id));
+
+        let moves = ["f2f3", "e7e5", "g2g4", "d8h4"];
+        for (i, m) in moves.iter().enumerate() {
+            let player = if i % 2 == 0 { alice } else { bob };
+            assert_ok!(Chess::make_move(
+                RuntimeOrigin::signed(player),
+                match_id,
+                (*m).into()
+            ));
+        }
+        assert_eq!(Chess::chess_matches(match_id), None);
+
+        let events = System::events().into_iter().filter_map(|r| match r.event {
+            RuntimeEvent::Chess(e) => Some(e),
+            _ => None,
+        });
+        assert_eq!(
+            pgn::events_to_pgn::<Test>(match_id, events),
+            Ok(format!(
+                "[Event \"Substrate Chess\"]\n\
+                 [Site \"?\"]\n\
+                 [Date \"????.??.??\"]\n\
+                 [Round \"?\"]\n\
+                 [White \"{:?}\"]\n\
+                 [Black \"{:?}\"]\n\
+                 [Result \"0-1\"]\n\
+                 [MatchStyle \"Bullet\"]\n\
+                 [BetAsset \"{:?}\"]\n\
+                 [BetAmount \"{:?}\"]\n\
+                 \n\
+                 1. f3 e5 2. g4 Qh4# 0-1\n",
+                alice, bob, bet_asset_id, bet_amount
+            ))
+        );
+    });
+}
+
+#[test]
+fn finished_matches_works() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+
+        let alice = account("Alice", 0, 0);
+        let bob = account("Bob", 0, 1);
+        let charlie = account("Charlie", 0, 2);
+
+        let bet_asset_id = AssetId::get();
+        let bet_amount = AssetMinBalance::get() * 5; // assuming T::IncentiveShare is 10%
+
+        assert_ok!(Chess::create_match(
+            RuntimeOrigin::signed(alice),
+            Some(bob),
+            MatchStyle::Bullet,
+            bet_asset_id,
+            bet_amount,
+            MatchOptions::default()
+        ));
+
+        let match_id = Chess::chess_match_id_from_nonce(0).unwrap();
+
+        assert_ok!(Chess::join_match(RuntimeOrigin::signed(bob), match_id));
+
+        let moves = ["f2f3", "e7e5", "g2g4", "d8h4"];
+        for (i, m) in moves.iter().enumerate() {
+            let player = if i % 2 == 0 { alice } else { bob };
+            assert_ok!(Chess::make_move(
+                RuntimeOrigin::signed(player),
+                match_id,
+                (*m).into()
+            ));
+        }
+        assert_eq!(Chess::chess_matches(match_id), None);
+
+        let finished_match = Chess::finished_matches(match_id).unwrap();
+        assert_eq!(finished_match.challenger, alice);
+        assert_eq!(finished_match.opponent, bob);
+        assert_eq!(finished_match.winner, Some(bob));
+        assert_eq!(finished_match.termination, Termination::Checkmate);
+        assert_eq!(
+            finished_match.board,
+            "rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3".as_bytes()
+        );
+        assert_eq!(finished_match.end, 1);
+        assert_eq!(finished_match.bet_asset_id, bet_asset_id);
+        assert_eq!(finished_match.payout, bet_amount * 2);
+
+        assert_eq!(
+            PlayerFinishedMatches::<Test>::iter_key_prefix(alice).collect::<Vec<_>>(),
+            vec![match_id]
+        );
+        assert_eq!(
+            PlayerFinishedMatches::<Test>::iter_key_prefix(bob).collect::<Vec<_>>(),
+            vec![match_id]
+        );
+
+        // test FinishedMatchNotExpired error
+        System::set_block_number(1 + <Test as Config>::FinishedMatchRetention::get());
+        assert_noop!(
+            Chess::prune_finished_match(RuntimeOrigin::signed(charlie), match_id),
+            Error::<Test>::FinishedMatchNotExpired
+        );
+
+        // anyone can prune a finished match once the retention period is over
+        System::set_block_number(2 + <Test as Config>::FinishedMatchRetention::get());
+        assert_ok!(Chess::prune_finished_match(
+            RuntimeOrigin::signed(charlie),
+            match_id
+        ));
+        System::assert_last_event(Event::FinishedMatchPruned { 0: match_id }.into());
+
+        assert_eq!(Chess::finished_matches(match_id), None);
+        assert_eq!(
+            PlayerFinishedMatches::<Test>::iter_key_prefix(alice).count(),
+            0
+        );
+        assert_eq!(
+            PlayerFinishedMatches::<Test>::iter_key_prefix(bob).count(),
+            0
+        );
+
+        // test NonExistentFinishedMatch error
+        assert_noop!(
+            Chess::prune_finished_match(RuntimeOrigin::signed(charlie), match_id),
+            Error::<Test>::NonExistentFinishedMatch
+        );
+    });
+}
+
+#[test]
+fn get_player_matches_works() {
+    new_test_ext().execute_with(|| {
+        let alice = account("Alice", 0, 0);
+        let bob = account("Bob", 0, 1);
+        let charlie = account("Charlie", 0, 2);
+
+        let bet_asset_id = AssetId::get();
+        let bet_amount = AssetMinBalance::get() * 5; // assuming T::IncentiveShare is 10%
+
+        assert_ok!(Chess::create_match(
+            RuntimeOrigin::signed(alice),
+            Some(bob),
+            MatchStyle::Bullet,
+            bet_asset_id,
+            bet_amount,
+            MatchOptions::default()
+        ));
+
+        let match_id = Chess::chess_match_id_from_nonce(0).unwrap();
+        let mut alice_matches = PlayerMatches::<Test>::iter_key_prefix(alice).collect::<Vec<_>>();
+        let mut bob_matches = PlayerMatches::<Test>::iter_key_prefix(bob).collect::<Vec<_>>();
+
+        assert_eq!(alice_matches[0], match_id);
+        assert_eq!(bob_matches[0], match_id);
+
+        assert_ok!(Chess::join_match(RuntimeOrigin::signed(bob), match_id));
+        assert_ok!(Chess::create_match(
+            RuntimeOrigin::signed(alice),
+            Some(charlie),
+            MatchStyle::Bullet,
+            bet_asset_id,
+            bet_amount,
+            MatchOptions::default()
+        ));
+        let new_match_id = Chess::chess_match_id_from_nonce(1).unwrap();
+
+        alice_matches = PlayerMatches::<Test>::iter_key_prefix(alice).collect::<Vec<_>>();
+        bob_matches = PlayerMatches::<Test>::iter_key_prefix(bob).collect::<Vec<_>>();
+
+        assert_eq!(alice_matches[0], match_id);
+        assert_eq!(alice_matches[1], new_match_id);
+        assert_eq!(bob_matches[0], match_id);
+
+        // advance the block number to the point where bob's time-to-move is expired
+        System::set_block_number(
+            System::block_number() + <Test as Config>::BulletPeriod::get() + 1,
+        );
+
+        assert_ok!(Chess::clear_abandoned_match(
+            RuntimeOrigin::signed(alice),
+            match_id
         ));
 
-        assert_eq!(Chess::chess_matches(match_id), None);
-
-        // check the elo after match is complete
-        assert_eq!(Chess::player_elo(alice), 2029);
-        assert_eq!(Chess::player_elo(bob), 2371);
+        alice_matches = PlayerMatches::<Test>::iter_key_prefix(alice).collect::<Vec<_>>();
+        bob_matches = PlayerMatches::<Test>::iter_key_prefix(bob).collect::<Vec<_>>();
+        assert_eq!(alice_matches[0], new_match_id);
+        assert_eq!(bob_matches.len(), 0);
     });
 }
 
 #[test]
-fn check_elo_player_aborts() {
+fn migrations_work() {
     new_test_ext().execute_with(|| {
-        System::set_block_number(1);
+        use migrations::{
+            v1::{MigrateToV1, OldMatch, OldMatchState},
+            v2::MigrateToV2,
+            v3::MigrateToV3,
+            v4::MigrateToV4,
+            v5::MigrateToV5,
+        };
 
         let alice = account("Alice", 0, 0);
         let bob = account("Bob", 0, 1);
@@ -545,200 +3272,386 @@ fn check_elo_player_aborts() {
         let bet_amount = AssetMinBalance::get() * 5;
 
         assert_ok!(Chess::create_match(
-            RuntimeOrigin::signed(bob),
-            alice,
+            RuntimeOrigin::signed(alice),
+            Some(bob),
             MatchStyle::Bullet,
             bet_asset_id,
-            bet_amount
-        ));
-
-        let match_id = Chess::chess_match_id_from_nonce(0).unwrap();
-
-        assert_ok!(Chess::join_match(RuntimeOrigin::signed(alice), match_id));
-
-        // check elo before the match finishes
-        assert_eq!(Chess::player_elo(alice), 2000);
-        assert_eq!(Chess::player_elo(bob), 2400);
-
-        assert_ok!(Chess::force_board_state(
-            match_id,
-            "8/8/8/8/8/5K2/Q7/7k w - - 1 68".into()
+            bet_amount,
+            MatchOptions::default()
         ));
-        // this move forces draws
-        assert_ok!(Chess::make_move(
+        assert_ok!(Chess::create_match(
             RuntimeOrigin::signed(bob),
-            match_id,
-            "a2f2".into()
+            Some(alice),
+            MatchStyle::Blitz,
+            bet_asset_id,
+            bet_amount,
+            MatchOptions::default()
         ));
-        assert_eq!(Chess::chess_matches(match_id), None);
+        let ongoing_id = Chess::chess_match_id_from_nonce(0).unwrap();
+        let finished_id = Chess::chess_match_id_from_nonce(1).unwrap();
+        assert_ok!(Chess::join_match(RuntimeOrigin::signed(bob), ongoing_id));
+        // migrated matches keep their deadline per move
+        let mut expected = Chess::chess_matches(ongoing_id).unwrap();
+        expected.time_control.increment = BulletPeriod::get();
+
+        // overwrite both matches with their pre-v1 encoding
+        for (match_id, state) in [
+            (ongoing_id, OldMatchState::OnGoing(NextMove::Whites)),
+            (finished_id, OldMatchState::Won),
+        ] {
+            let m = Chess::chess_matches(match_id).unwrap();
+            let old = OldMatch::<Test> {
+                challenger: m.challenger,
+                opponent: m.opponent.unwrap(),
+                board: m.board,
+                state,
+                nonce: m.nonce,
+                style: m.style,
+                last_move: m.last_move,
+                start: m.start,
+                bet_asset_id: m.bet_asset_id,
+                bet_amount: m.bet_amount,
+            };
+            unhashed::put_raw(
+                &crate::Matches::<Test>::hashed_key_for(match_id),
+                &old.encode(),
+            );
+        }
+        StorageVersion::new(0).put::<Chess>();
+
+        MigrateToV1::<Test>::on_runtime_upgrade();
+        assert_eq!(Chess::on_chain_storage_version(), 1);
+
+        MigrateToV2::<Test>::on_runtime_upgrade();
+        assert_eq!(Chess::on_chain_storage_version(), 2);
+
+        assert_eq!(Chess::chess_matches(ongoing_id), Some(expected));
+        assert_eq!(Chess::chess_matches(finished_id), None);
+
+        // overwrite the ratings with their single, pre-v3 value
+        let _ = crate::PlayerRatings::<Test>::clear(u32::MAX, None);
+        let _ = crate::EstablishedRatings::<Test>::clear(u32::MAX, None);
+        migrations::v3::PlayerElo::<Test>::insert(alice, 2000);
+        migrations::v3::PlayerElo::<Test>::insert(bob, 2400);
+
+        MigrateToV3::<Test>::on_runtime_upgrade();
+        assert_eq!(Chess::on_chain_storage_version(), 3);
+
+        // ratings are copied lazily, but they are visible and established right away
+        assert_eq!(crate::PlayerRatings::<Test>::iter().count(), 0);
+        for style in MatchStyle::RATED {
+            assert_eq!(Chess::rating(&alice, &style), 2000);
+            assert_eq!(Chess::rating(&bob, &style), 2400);
+            assert!(!Chess::is_provisional(&alice, &style));
+        }
+
+        // before their ratings are updated, or with the weight left at the end of a block
+        migrations::v3::migrate_player::<Test>(&alice);
+        assert_eq!(migrations::v3::PlayerElo::<Test>::get(alice), None);
+        assert_eq!(crate::PlayerRatings::<Test>::iter().count(), 4);
+        Chess::on_idle(System::block_number(), Weight::MAX);
+        assert_eq!(migrations::v3::PlayerElo::<Test>::iter().count(), 0);
+        assert_eq!(crate::PlayerRatings::<Test>::iter().count(), 8);
+        for style in MatchStyle::RATED {
+            assert_eq!(Chess::player_rating(alice, style.clone()), 2000);
+            assert_eq!(Chess::player_rating(bob, style.clone()), 2400);
+            assert!(Chess::established_rating(alice, style));
+        }
+
+        // games weren't counted before v4
+        let _ = crate::RatedGames::<Test>::clear(u32::MAX, None);
+        let _ = crate::EstablishedRatings::<Test>::clear(u32::MAX, None);
+        assert!(Chess::is_provisional(&alice, &MatchStyle::Bullet));
+
+        MigrateToV4::<Test>::on_runtime_upgrade();
+        assert_eq!(Chess::on_chain_storage_version(), 4);
+
+        // established, while games are only counted from now on
+        for style in MatchStyle::RATED {
+            assert!(!Chess::is_provisional(&alice, &style));
+            assert!(!Chess::is_provisional(&bob, &style));
+            assert_eq!(Chess::rated_games(alice, style), 0);
+        }
+
+        // stakes of the ongoing match are escrowed in the pallet account, switch to holds
+        let pallet_account = <Test as ConfigHelper>::pallet_account();
+        let balance_a = Assets::balance(bet_asset_id, alice);
+        let balance_pallet = Assets::balance(bet_asset_id, pallet_account);
+        UseHolds::set(true);
+
+        MigrateToV5::<Test>::on_runtime_upgrade();
+        assert_eq!(Chess::on_chain_storage_version(), 5);
 
-        // check the elo after the match is complete
-        assert_eq!(Chess::player_elo(alice), 2013);
-        assert_eq!(Chess::player_elo(bob), 2387);
+        assert_eq!(
+            Assets::balance(bet_asset_id, pallet_account),
+            balance_pallet - bet_amount * 2
+        );
+        assert_eq!(Assets::balance(bet_asset_id, alice), balance_a);
+        assert_eq!(
+            AssetsWithHolds::total_balance_on_hold(bet_asset_id, &alice),
+            bet_amount
+        );
+        assert_eq!(
+            AssetsWithHolds::total_balance_on_hold(bet_asset_id, &bob),
+            bet_amount
+        );
     });
 }
 
-const BOARD_STATE: &str = "Q7/5Q2/8/8/3k4/6P1/6BP/7K b - - 0 67";
-
 #[test]
-fn force_board_state_works() {
+fn migrate_to_v5_cancels_matches_it_cannot_hold() {
     new_test_ext().execute_with(|| {
+        use migrations::v5::MigrateToV5;
+
         let alice = account("Alice", 0, 0);
         let bob = account("Bob", 0, 1);
+        let charlie = account("Charlie", 0, 2);
+        let dave = account("Dave", 0, 3);
+        let pallet_account = <Test as ConfigHelper>::pallet_account();
 
         let bet_asset_id = AssetId::get();
-        let bet_amount = AssetMinBalance::get() * 5; // assuming T::IncentiveShare is 10%
+        let bet_amount = AssetMinBalance::get() * 5;
+        let held = |who: &u64| AssetsWithHolds::total_balance_on_hold(bet_asset_id, who);
 
-        assert_ok!(Chess::create_match(
-            RuntimeOrigin::signed(alice),
-            bob,
-            MatchStyle::Bullet,
+        let initial_balance_a = Assets::balance(bet_asset_id, alice);
+        let initial_balance_b = Assets::balance(bet_asset_id, bob);
+        let initial_balance_c = Assets::balance(bet_asset_id, charlie);
+        let initial_balance_d = Assets::balance(bet_asset_id, dave);
+
+        // an open challenge, a match in progress and a queued player, escrowed in the pallet
+        // account
+        for (challenger, opponent) in [(alice, bob), (charlie, dave)] {
+            assert_ok!(Chess::create_match(
+                RuntimeOrigin::signed(challenger),
+                Some(opponent),
+                MatchStyle::Bullet,
+                bet_asset_id,
+                bet_amount,
+                MatchOptions::default()
+            ));
+        }
+        let challenge_id = Chess::chess_match_id_from_nonce(0).unwrap();
+        let ongoing_id = Chess::chess_match_id_from_nonce(1).unwrap();
+        assert_ok!(Chess::join_match(RuntimeOrigin::signed(dave), ongoing_id));
+        assert_ok!(Chess::enter_queue(
+            RuntimeOrigin::signed(bob),
+            MatchStyle::Blitz,
             bet_asset_id,
             bet_amount
         ));
+        assert_eq!(
+            Assets::balance(bet_asset_id, pallet_account),
+            bet_amount * 4
+        );
+        StorageVersion::new(4).put::<Chess>();
 
-        let match_id = Chess::chess_match_id_from_nonce(0).unwrap();
+        // nothing to move while stakes are kept in the pallet account
+        MigrateToV5::<Test>::on_runtime_upgrade();
+        assert_eq!(Chess::on_chain_storage_version(), 5);
+        assert_eq!(
+            Assets::balance(bet_asset_id, pallet_account),
+            bet_amount * 4
+        );
+        StorageVersion::new(4).put::<Chess>();
 
-        assert_ok!(Chess::join_match(RuntimeOrigin::signed(bob), match_id));
+        // switch to holds, while dave's stake can't be put on hold anymore and bob's stake is
+        // missing from the pallet account, so it can't be refunded either
+        UseHolds::set(true);
+        assert_ok!(Assets::freeze(RuntimeOrigin::signed(0), bet_asset_id, dave));
+        assert_ok!(<Assets as Mutate<u64>>::burn_from(
+            bet_asset_id,
+            &pallet_account,
+            bet_amount,
+            Precision::Exact,
+            Fortitude::Force
+        ));
 
-        assert_ok!(Chess::force_board_state(match_id, BOARD_STATE.into()));
+        // every match and queue entry is migrated on its own
+        MigrateToV5::<Test>::on_runtime_upgrade();
+        assert_eq!(Chess::on_chain_storage_version(), 5);
+        assert_eq!(Assets::balance(bet_asset_id, pallet_account), 0);
+
+        // the match of dave is cancelled, and both stakes are refunded
+        assert_eq!(Chess::chess_matches(ongoing_id), None);
+        assert_eq!(Chess::player_matches(charlie, ongoing_id), None);
+        assert_eq!(Assets::balance(bet_asset_id, charlie), initial_balance_c);
+        assert_eq!(Assets::balance(bet_asset_id, dave), initial_balance_d);
+        assert_eq!(held(&charlie), 0);
+        assert_eq!(held(&dave), 0);
+
+        // bob is left in the queue, with his stake stuck
+        assert_eq!(Chess::matchmaking_queue()[0].player, bob);
+        assert_eq!(held(&bob), 0);
+        assert_eq!(
+            Assets::balance(bet_asset_id, bob),
+            initial_balance_b - bet_amount
+        );
 
-        let chess_match = Chess::chess_matches(match_id).unwrap();
-        assert_eq!(chess_match.board, BOARD_STATE.as_bytes());
+        // the stake of alice is on hold
+        assert_eq!(held(&alice), bet_amount);
+        assert_ok!(Chess::abort_match(
+            RuntimeOrigin::signed(alice),
+            challenge_id
+        ));
+        assert_eq!(held(&alice), 0);
+        assert_eq!(Assets::balance(bet_asset_id, alice), initial_balance_a);
     });
 }
 
 #[test]
-fn claim_victory_works() {
+fn matchmaking_queue_works() {
     new_test_ext().execute_with(|| {
         let alice = account("Alice", 0, 0);
-        let bob = account("Bob", 0, 1);
 
         let bet_asset_id = AssetId::get();
-        let bet_amount = AssetMinBalance::get() * 5; // assuming T::IncentiveShare is 10%
+        let bet_amount_low = AssetMinBalance::get() * 4; // assuming T::IncentiveShare is 10%
+        let bet_amount = AssetMinBalance::get() * 5;
 
         let initial_balance_a = Assets::balance(bet_asset_id, alice);
-        let initial_balance_b = Assets::balance(bet_asset_id, bob);
 
-        assert_ok!(Chess::create_match(
+        assert_noop!(
+            Chess::enter_queue(
+                RuntimeOrigin::signed(alice),
+                MatchStyle::Bullet,
+                bet_asset_id,
+                bet_amount_low
+            ),
+            Error::<Test>::BetTooLow
+        );
+
+        assert_ok!(Chess::enter_queue(
             RuntimeOrigin::signed(alice),
-            bob,
             MatchStyle::Bullet,
             bet_asset_id,
             bet_amount
         ));
-
-        let match_id = Chess::chess_match_id_from_nonce(0).unwrap();
-
-        assert_ok!(Chess::join_match(RuntimeOrigin::signed(bob), match_id));
-        assert_ok!(Chess::make_move(
-            RuntimeOrigin::signed(alice),
-            match_id,
-            "e2e4".into()
-        ));
-
-        // advance the block number to the point where bob's time-to-move is expired
-        System::set_block_number(
-            System::block_number() + <Test as Config>::BulletPeriod::get() + 1,
+        System::assert_last_event(
+            Event::QueueEntered {
+                0: alice,
+                1: MatchStyle::Bullet,
+                2: bet_asset_id,
+                3: bet_amount,
+            }
+            .into(),
+        );
+        assert_eq!(
+            Assets::balance(bet_asset_id, alice),
+            initial_balance_a - bet_amount
         );
 
-        // alice claims victory
-        assert_ok!(Chess::clear_abandoned_match(
-            RuntimeOrigin::signed(alice),
-            match_id
-        ));
+        let queue = Chess::matchmaking_queue();
+        assert_eq!(queue.len(), 1);
+        assert_eq!(queue[0].player, alice);
+        assert_eq!(queue[0].rating, 2000);
 
-        System::assert_has_event(
-            Event::MatchWon {
-                0: match_id,
-                1: alice,
-                2: "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1".into(),
-            }
-            .into(),
+        assert_noop!(
+            Chess::enter_queue(
+                RuntimeOrigin::signed(alice),
+                MatchStyle::Blitz,
+                bet_asset_id,
+                bet_amount
+            ),
+            Error::<Test>::AlreadyInQueue
         );
 
-        assert_eq!(Chess::chess_matches(match_id), None);
+        // nobody to be paired with
+        Chess::on_idle(System::block_number(), Weight::MAX);
+        assert_eq!(Chess::matchmaking_queue().len(), 1);
         assert_eq!(Chess::chess_match_id_from_nonce(0), None);
 
-        let final_balance_a = Assets::balance(bet_asset_id, alice);
-        let final_balance_b = Assets::balance(bet_asset_id, bob);
-        assert_eq!(final_balance_a, initial_balance_a + bet_amount);
-        assert_eq!(final_balance_b, initial_balance_b - bet_amount);
+        assert_ok!(Chess::leave_queue(RuntimeOrigin::signed(alice)));
+        System::assert_last_event(Event::QueueLeft { 0: alice }.into());
+        assert_eq!(Assets::balance(bet_asset_id, alice), initial_balance_a);
+        assert!(Chess::matchmaking_queue().is_empty());
+
+        assert_noop!(
+            Chess::leave_queue(RuntimeOrigin::signed(alice)),
+            Error::<Test>::NotInQueue
+        );
     });
 }
 
 #[test]
-fn janitor_incentive_works() {
+fn matchmaking_pairs_closest_ratings() {
     new_test_ext().execute_with(|| {
-        let alice = account("Alice", 0, 0);
-        let bob = account("Bob", 0, 1);
-        let charlie = account("Charlie", 0, 2);
+        let alice = account("Alice", 0, 0); // elo 2000
+        let bob = account("Bob", 0, 1); // elo 2400
+        let charlie = account("Charlie", 0, 2); // elo 1600
+        let dave = account("Dave", 0, 3); // elo 2300
 
         let bet_asset_id = AssetId::get();
         let bet_amount = AssetMinBalance::get() * 5; // assuming T::IncentiveShare is 10%
 
-        let initial_balance_a = Assets::balance(bet_asset_id, alice);
-        let initial_balance_b = Assets::balance(bet_asset_id, bob);
-        let initial_balance_c = Assets::balance(bet_asset_id, charlie);
+        for player in [alice, charlie, bob, dave] {
+            assert_ok!(Chess::enter_queue(
+                RuntimeOrigin::signed(player),
+                MatchStyle::Bullet,
+                bet_asset_id,
+                bet_amount
+            ));
+        }
+        System::set_block_number(5);
+
+        // not enough weight left for a single pair, scanning the queue of 4 entries included
+        let pair_weight = <Test as Config>::WeightInfo::skip_queued_entry(4)
+            + <Test as Config>::WeightInfo::start_queued_match(4);
+        Chess::on_idle(
+            System::block_number(),
+            pair_weight.saturating_sub(Weight::from_parts(1, 0)),
+        );
+        assert_eq!(Chess::matchmaking_queue().len(), 4);
 
-        assert_ok!(Chess::create_match(
-            RuntimeOrigin::signed(alice),
-            bob,
-            MatchStyle::Bullet,
-            bet_asset_id,
-            bet_amount
-        ));
+        // only enough weight left for a single pair
+        assert_eq!(
+            Chess::on_idle(System::block_number(), pair_weight),
+            pair_weight
+        );
+        assert_eq!(Chess::matchmaking_queue().len(), 2);
 
+        // alice entered the queue first and dave is the closest rated player
         let match_id = Chess::chess_match_id_from_nonce(0).unwrap();
-
-        assert_ok!(Chess::join_match(RuntimeOrigin::signed(bob), match_id));
-        assert_ok!(Chess::make_move(
-            RuntimeOrigin::signed(alice),
-            match_id,
-            "e2e4".into()
-        ));
-
         let chess_match = Chess::chess_matches(match_id).unwrap();
-        let (janitor_incentive, actual_prize) = chess_match.janitor_incentive();
-
-        // advance the block number to the point where bob's time-to-move is expired
-        // and alice's time to claim victory is also expired
-        System::set_block_number(
-            System::block_number() + <Test as Config>::BulletPeriod::get() * 10 + 1,
-        );
-
-        // charlie cleans abandoned match
-        assert_ok!(Chess::clear_abandoned_match(
-            RuntimeOrigin::signed(charlie),
-            match_id
-        ));
-
+        assert_eq!(chess_match.challenger, alice);
+        assert_eq!(chess_match.opponent, Some(dave));
+        assert_eq!(chess_match.state, MatchState::OnGoing(NextMove::Whites));
+        assert_eq!(chess_match.start, 5);
+        assert_eq!(chess_match.last_move, 5);
+        assert_eq!(Chess::player_matches(alice, match_id), Some(()));
+        assert_eq!(Chess::player_matches(dave, match_id), Some(()));
         System::assert_has_event(
-            Event::MatchWon {
+            Event::MatchStarted {
                 0: match_id,
                 1: alice,
-                2: "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1".into(),
+                2: dave,
+                3: Board::default().to_string().into(),
             }
             .into(),
         );
 
-        assert_eq!(Chess::chess_matches(match_id), None);
-        assert_eq!(Chess::chess_match_id_from_nonce(0), None);
+        Chess::on_idle(System::block_number(), Weight::MAX);
+        assert!(Chess::matchmaking_queue().is_empty());
 
-        let final_balance_a = Assets::balance(bet_asset_id, alice);
-        let final_balance_b = Assets::balance(bet_asset_id, bob);
-        let final_balance_c = Assets::balance(bet_asset_id, charlie);
+        let match_id = Chess::chess_match_id_from_nonce(1).unwrap();
+        let chess_match = Chess::chess_matches(match_id).unwrap();
+        assert_eq!(chess_match.challenger, charlie);
+        assert_eq!(chess_match.opponent, Some(bob));
+
+        // stakes were already in escrow, so the match plays out like any other
+        assert_ok!(Chess::make_move(
+            RuntimeOrigin::signed(charlie),
+            match_id,
+            "e2e4".into()
+        ));
+        assert_ok!(Chess::resign(RuntimeOrigin::signed(bob), match_id));
         assert_eq!(
-            final_balance_a,
-            initial_balance_a - bet_amount + actual_prize
+            Assets::balance(bet_asset_id, charlie),
+            AssetMinBalance::get() * 100 + bet_amount
         );
-        assert_eq!(final_balance_b, initial_balance_b - bet_amount);
-        assert_eq!(final_balance_c, initial_balance_c + janitor_incentive);
     });
 }
 
 #[test]
-fn get_player_matches_works() {
+fn set_parameters_works() {
     new_test_ext().execute_with(|| {
         let alice = account("Alice", 0, 0);
         let bob = account("Bob", 0, 1);
@@ -747,51 +3660,257 @@ fn get_player_matches_works() {
         let bet_asset_id = AssetId::get();
         let bet_amount = AssetMinBalance::get() * 5; // assuming T::IncentiveShare is 10%
 
+        // defaults are taken from Config
+        let defaults = Chess::chess_parameters();
+        assert_eq!(defaults.bullet_period, BulletPeriod::get());
+        assert_eq!(defaults.incentive_share, IncentiveShare::get());
+        assert_eq!(defaults.k, 32);
+
         assert_ok!(Chess::create_match(
             RuntimeOrigin::signed(alice),
-            bob,
+            Some(bob),
             MatchStyle::Bullet,
             bet_asset_id,
-            bet_amount
+            bet_amount,
+            MatchOptions::default()
         ));
+        let old_match_id = Chess::chess_match_id_from_nonce(0).unwrap();
+
+        let parameters = Parameters {
+            bullet_period: 20,
+            blitz_period: 100,
+            rapid_period: 300,
+            daily_period: 28800,
+            incentive_share: 20,
+            k: 16,
+        };
+
+        // assert BadOrigin error
+        assert_noop!(
+            Chess::set_parameters(RuntimeOrigin::signed(alice), parameters.clone()),
+            DispatchError::BadOrigin
+        );
 
-        let match_id = Chess::chess_match_id_from_nonce(0).unwrap();
-        let mut alice_matches = PlayerMatches::<Test>::iter_key_prefix(alice).collect::<Vec<_>>();
-        let mut bob_matches = PlayerMatches::<Test>::iter_key_prefix(bob).collect::<Vec<_>>();
-
-        assert_eq!(alice_matches[0], match_id);
-        assert_eq!(bob_matches[0], match_id);
+        // assert InvalidParameters error
+        for invalid in [
+            Parameters {
+                bullet_period: 0,
+                ..parameters.clone()
+            },
+            Parameters {
+                incentive_share: 101,
+                ..parameters.clone()
+            },
+        ] {
+            assert_noop!(
+                Chess::set_parameters(RuntimeOrigin::root(), invalid),
+                Error::<Test>::InvalidParameters
+            );
+        }
+
+        assert_ok!(Chess::set_parameters(
+            RuntimeOrigin::root(),
+            parameters.clone()
+        ));
+        assert_eq!(Chess::chess_parameters(), parameters);
+        System::assert_last_event(Event::ParametersSet(parameters.clone()).into());
 
-        assert_ok!(Chess::join_match(RuntimeOrigin::signed(bob), match_id));
+        // new matches use the new parameters, a 20% incentive share allows lower bets
         assert_ok!(Chess::create_match(
-            RuntimeOrigin::signed(alice),
-            charlie,
+            RuntimeOrigin::signed(charlie),
+            Some(bob),
             MatchStyle::Bullet,
             bet_asset_id,
-            bet_amount
+            AssetMinBalance::get() * 3,
+            MatchOptions::default()
         ));
-        let new_match_id = Chess::chess_match_id_from_nonce(1).unwrap();
+        let new_match = Chess::chess_matches(Chess::chess_match_id_from_nonce(1).unwrap()).unwrap();
+        assert_eq!(new_match.time_control.base, 20);
+        assert_eq!(new_match.white_clock, 20);
+        assert_eq!(new_match.incentive_share, 20);
+        assert_eq!(new_match.k, 16);
+
+        // existing matches keep the parameters in force when they were created
+        let old_match = Chess::chess_matches(old_match_id).unwrap();
+        assert_eq!(old_match.time_control.base, BulletPeriod::get());
+        assert_eq!(old_match.incentive_share, IncentiveShare::get());
+        assert_eq!(old_match.k, 32);
+
+        assert_ok!(Chess::join_match(RuntimeOrigin::signed(bob), old_match_id));
+        assert_ok!(Chess::make_move(
+            RuntimeOrigin::signed(alice),
+            old_match_id,
+            "e2e4".into()
+        ));
+        System::set_block_number(System::block_number() + BulletPeriod::get() + 1);
+        assert_ok!(Chess::clear_abandoned_match(
+            RuntimeOrigin::signed(alice),
+            old_match_id
+        ));
+        assert_eq!(Chess::player_rating(alice, MatchStyle::Bullet), 2029);
+        assert_eq!(Chess::player_rating(bob, MatchStyle::Bullet), 2371);
+    });
+}
 
-        alice_matches = PlayerMatches::<Test>::iter_key_prefix(alice).collect::<Vec<_>>();
-        bob_matches = PlayerMatches::<Test>::iter_key_prefix(bob).collect::<Vec<_>>();
+// deterministic sample of rating pairs for the Elo property tests, including the bounds of u16
+fn sample_ratings() -> Vec<(u16, u16)> {
+    let mut seed: u32 = 42;
+    let mut next = move || {
+        seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+        (seed >> 16) as u16
+    };
+    let mut ratings: Vec<(u16, u16)> = (0..2_000).map(|_| (next(), next())).collect();
+    ratings.extend((0..2_000).map(|_| {
+        let rating = 1_000 + next() % 2_000;
+        (rating, rating + next() % 1_200)
+    }));
+    for bound in [0, 1, 1600, u16::MAX - 1, u16::MAX] {
+        ratings.extend([(bound, 0), (bound, 1600), (bound, u16::MAX), (0, bound)]);
+    }
+    ratings
+}
 
-        assert_eq!(alice_matches[0], match_id);
-        assert_eq!(alice_matches[1], new_match_id);
-        assert_eq!(bob_matches[0], match_id);
+#[test]
+fn elo_is_symmetric() {
+    for (rating_1, rating_2) in sample_ratings() {
+        let expected_1 = elo::expected_score(rating_1, rating_2);
+        let expected_2 = elo::expected_score(rating_2, rating_1);
+        assert_eq!(expected_1 + expected_2, elo::WIN);
+        assert!(expected_1 <= elo::WIN);
+        if rating_1 == rating_2 {
+            assert_eq!(expected_1, elo::DRAW);
+        }
+        // the stronger player is always expected to score more
+        if rating_1 > rating_2 {
+            assert!(expected_1 >= expected_2);
+        }
+
+        for k in [16, 32] {
+            for (score_1, score_2) in [
+                (elo::WIN, elo::LOSS),
+                (elo::DRAW, elo::DRAW),
+                (elo::LOSS, elo::WIN),
+            ] {
+                let (new_1, new_2) = elo::new_ratings(k, rating_1, rating_2, score_1);
+                assert_eq!(
+                    elo::new_ratings(k, rating_2, rating_1, score_2),
+                    (new_2, new_1)
+                );
+            }
+        }
+    }
+}
 
-        // advance the block number to the point where bob's time-to-move is expired
-        System::set_block_number(
-            System::block_number() + <Test as Config>::BulletPeriod::get() + 1,
-        );
+#[test]
+fn elo_is_zero_sum() {
+    for (rating_1, rating_2) in sample_ratings() {
+        for k in [16, 32, 64] {
+            for score in [elo::WIN, elo::DRAW, elo::LOSS] {
+                let (new_1, new_2) = elo::new_ratings(k, rating_1, rating_2, score);
+                // points are only lost when a rating saturates
+                if ![0, u16::MAX].contains(&new_1) && ![0, u16::MAX].contains(&new_2) {
+                    assert_eq!(
+                        new_1 as i32 + new_2 as i32,
+                        rating_1 as i32 + rating_2 as i32
+                    );
+                }
+            }
+        }
+    }
+}
 
-        assert_ok!(Chess::clear_abandoned_match(
-            RuntimeOrigin::signed(alice),
-            match_id
-        ));
+#[test]
+fn elo_is_bounded() {
+    for (rating_1, rating_2) in sample_ratings() {
+        for k in [0, 32, u16::MAX] {
+            let (winner, looser) = elo::new_ratings(k, rating_1, rating_2, elo::WIN);
+            // nobody moves more than k points, winners never lose points and losers never gain
+            assert!(winner >= rating_1 && (winner - rating_1) <= k);
+            assert!(looser <= rating_2 && (rating_2 - looser) <= k);
+
+            let (new_1, new_2) = elo::new_ratings(k, rating_1, rating_2, elo::DRAW);
+            assert!(new_1.abs_diff(rating_1) <= k / 2 + 1);
+            assert!(new_2.abs_diff(rating_2) <= k / 2 + 1);
+        }
+    }
+}
 
-        alice_matches = PlayerMatches::<Test>::iter_key_prefix(alice).collect::<Vec<_>>();
-        bob_matches = PlayerMatches::<Test>::iter_key_prefix(bob).collect::<Vec<_>>();
-        assert_eq!(alice_matches[0], new_match_id);
-        assert_eq!(bob_matches.len(), 0);
-    });
+type Glicko2 = glicko2::Glicko2<ConstU64<100>>;
+
+fn glicko2_state(rating: u16, deviation: u16, last_played: u64) -> glicko2::Glicko2State<u64> {
+    glicko2::Glicko2State {
+        rating,
+        deviation,
+        volatility: glicko2::INITIAL_VOLATILITY,
+        last_played,
+    }
+}
+
+#[test]
+fn glicko2_works() {
+    // a single game of the example in Glickman's paper
+    let (winner, looser) = Glicko2::update(
+        32,
+        32,
+        &glicko2_state(1500, 200, 0),
+        &glicko2_state(1400, 30, 0),
+        elo::WIN,
+        0,
+    );
+    assert_eq!((winner.rating, winner.deviation), (1564, 175));
+    assert_eq!((looser.rating, looser.deviation), (1398, 32));
+    assert_eq!(winner.last_played, 0);
+
+    // new accounts move a lot, and become more certain
+    let new = <Glicko2 as RatingSystem<u64>>::new_state(1600);
+    assert_eq!(new.deviation, glicko2::MAX_DEVIATION);
+    let (winner, looser) = Glicko2::update(32, 32, &new, &new, elo::WIN, 0);
+    assert_eq!((winner.rating, winner.deviation), (1762, 290));
+    assert_eq!((looser.rating, looser.deviation), (1438, 290));
+
+    // established ones barely move...
+    let established = glicko2_state(1500, 50, 0);
+    let (winner, _) = Glicko2::update(32, 32, &established, &established, elo::WIN, 0);
+    assert_eq!((winner.rating, winner.deviation), (1507, 51));
+
+    // ...unless they stopped playing for a while (50 rating periods)
+    let (winner, _) = Glicko2::update(32, 32, &established, &established, elo::WIN, 5_000);
+    assert_eq!((winner.rating, winner.deviation), (1521, 87));
+    assert_eq!(winner.last_played, 5_000);
+
+    // uncertainty is capped to that of new accounts
+    let (winner, _) = Glicko2::update(32, 32, &established, &established, elo::WIN, u64::MAX);
+    assert_eq!((winner.rating, winner.deviation), (1662, 290));
+}
+
+#[test]
+fn glicko2_is_symmetric() {
+    for (rating_1, rating_2) in sample_ratings() {
+        for (deviation_1, deviation_2) in [(350, 350), (30, 350), (200, 30)] {
+            let state_1 = glicko2_state(rating_1, deviation_1, 0);
+            let state_2 = glicko2_state(rating_2, deviation_2, 0);
+            for (score_1, score_2) in [
+                (elo::WIN, elo::LOSS),
+                (elo::DRAW, elo::DRAW),
+                (elo::LOSS, elo::WIN),
+            ] {
+                let (new_1, new_2) = Glicko2::update(32, 32, &state_1, &state_2, score_1, 0);
+                assert_eq!(
+                    Glicko2::update(32, 32, &state_2, &state_1, score_2, 0),
+                    (new_2.clone(), new_1.clone())
+                );
+
+                // winners never lose points and losers never gain
+                if score_1 == elo::WIN {
+                    assert!(new_1.rating >= rating_1 && new_2.rating <= rating_2);
+                }
+                assert!(
+                    (glicko2::MIN_DEVIATION..=glicko2::MAX_DEVIATION).contains(&new_1.deviation)
+                );
+                assert!(
+                    (glicko2::MIN_VOLATILITY..=glicko2::MAX_VOLATILITY).contains(&new_1.volatility)
+                );
+            }
+        }
+    }
 }
@@ -26,6 +26,11 @@
 // weight.rs
 // --json-file=benchmarks.json
 
+// Only create_match, abort_match, join_match, make_move and clear_abandoned_match were
+// measured by the command above. Every other weight is an unmeasured estimate derived from
+// them, labelled as such, and every `Storage` line and DB count was counted by hand.
+// Run the benchmarks on reference hardware before relying on these weights.
+
 #![cfg_attr(rustfmt, rustfmt_skip)]
 #![allow(unused_parens)]
 #![allow(unused_imports)]
@@ -37,8 +42,20 @@ pub trait WeightInfo {
 	fn create_match() -> Weight;
 	fn abort_match() -> Weight;
 	fn join_match() -> Weight;
-	fn make_move() -> Weight;
+	fn make_move(m: u32, ) -> Weight;
+	fn make_move_checkmate(m: u32, ) -> Weight;
 	fn clear_abandoned_match() -> Weight;
+	fn resign() -> Weight;
+	fn offer_draw() -> Weight;
+	fn accept_draw() -> Weight;
+	fn decline_draw() -> Weight;
+	fn claim_draw() -> Weight;
+	fn prune_finished_match() -> Weight;
+	fn enter_queue() -> Weight;
+	fn leave_queue() -> Weight;
+	fn start_queued_match(q: u32, ) -> Weight;
+	fn skip_queued_entry(q: u32, ) -> Weight;
+	fn set_parameters() -> Weight;
 }
 
 /// Weight functions for `pallet_chess`.
@@ -50,10 +67,11 @@ impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
 	// Storage: System Account (r:1 w:1)
 	// Storage: Chess Matches (r:0 w:1)
 	// Storage: Chess MatchIdFromNonce (r:0 w:1)
+	// Storage: Chess ChessParameters (r:1 w:0)
 	fn create_match() -> Weight {
 		// Minimum execution time: 88_030 nanoseconds.
 		Weight::from_parts(91_090_000, 0)
-			.saturating_add(T::DbWeight::get().reads(5))
+			.saturating_add(T::DbWeight::get().reads(6))
 			.saturating_add(T::DbWeight::get().writes(7))
 	}
 	// Storage: Chess Matches (r:1 w:1)
@@ -61,39 +79,220 @@ impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
 	// Storage: Assets Account (r:2 w:2)
 	// Storage: System Account (r:1 w:1)
 	// Storage: Chess MatchIdFromNonce (r:0 w:1)
+	// Storage: Chess OpenChallenges (r:0 w:1)
 	fn abort_match() -> Weight {
 		// Minimum execution time: 82_190 nanoseconds.
 		Weight::from_parts(83_430_000, 0)
 			.saturating_add(T::DbWeight::get().reads(5))
-			.saturating_add(T::DbWeight::get().writes(6))
+			.saturating_add(T::DbWeight::get().writes(7))
 	}
 	// Storage: Chess Matches (r:1 w:1)
 	// Storage: Assets Asset (r:1 w:1)
 	// Storage: Assets Account (r:2 w:2)
+	// Storage: Chess PositionHistory (r:1 w:1)
+	// Storage: Chess OpenChallenges (r:0 w:1)
+	// Storage: Chess PlayerMatches (r:0 w:1)
+	// measured at 72_110_000 before open challenges, colors and position history,
+	// raised by an unmeasured estimate for them
 	fn join_match() -> Weight {
-		// Minimum execution time: 70_710 nanoseconds.
-		Weight::from_parts(72_110_000, 0)
-			.saturating_add(T::DbWeight::get().reads(4))
-			.saturating_add(T::DbWeight::get().writes(4))
+		Weight::from_parts(80_450_000, 0)
+			.saturating_add(T::DbWeight::get().reads(5))
+			.saturating_add(T::DbWeight::get().writes(7))
 	}
 	// Storage: Chess Matches (r:1 w:1)
+	// Storage: Chess MatchMoves (r:1 w:1)
+	// Storage: Chess PositionHistory (r:1 w:1)
 	// read `pallet-chess/docs` to understand how this weight was calculated.
-	fn make_move() -> Weight {
+	// the per-move term is an unmeasured estimate, only the base was measured
+	/// The range of component `m` is `[0, 1024]`.
+	fn make_move(m: u32, ) -> Weight {
 		// Minimum execution time: 35_470 nanoseconds.
 		Weight::from_parts(116_079_054, 0)
-			.saturating_add(T::DbWeight::get().reads(1))
-			.saturating_add(T::DbWeight::get().writes(1))
+			.saturating_add(Weight::from_parts(50_000, 0).saturating_mul(m.into()))
+			.saturating_add(T::DbWeight::get().reads(3))
+			.saturating_add(T::DbWeight::get().writes(3))
+	}
+	// Storage: Chess Matches (r:1 w:1)
+	// Storage: Chess MatchMoves (r:1 w:1)
+	// Storage: Chess PositionHistory (r:0 w:1)
+	// Storage: Chess MoveHistoryTruncated (r:0 w:1)
+	// Storage: Assets Asset (r:1 w:1)
+	// Storage: Assets Account (r:2 w:2)
+	// Storage: System Account (r:1 w:1)
+	// Storage: Chess PlayerRatings (r:2 w:2)
+	// Storage: Chess PlayerElo (r:2 w:2)
+	// Storage: Chess PlayerMatches (r:0 w:2)
+	// Storage: Chess MatchIdFromNonce (r:0 w:1)
+	// Storage: Chess PlayerStatistics (r:2 w:2)
+	// Storage: Chess RatedGames (r:2 w:2)
+	// Storage: Chess RatingHistory (r:2 w:2)
+	// Storage: Chess Leaderboards (r:1 w:1)
+	// Storage: Chess RankedPlayers (r:3 w:4)
+	// Storage: Chess FinishedMatches (r:0 w:1)
+	// Storage: Chess PlayerFinishedMatches (r:0 w:2)
+	// unmeasured estimate: make_move() plus clear_abandoned_match(), which settles
+	// the match the same way
+	/// The range of component `m` is `[0, 1024]`.
+	fn make_move_checkmate(m: u32, ) -> Weight {
+		Weight::from_parts(238_689_054, 0)
+			.saturating_add(Weight::from_parts(50_000, 0).saturating_mul(m.into()))
+			.saturating_add(T::DbWeight::get().reads(20))
+			.saturating_add(T::DbWeight::get().writes(29))
 	}
 	// Storage: Chess Matches (r:1 w:1)
 	// Storage: Assets Asset (r:1 w:1)
 	// Storage: Assets Account (r:3 w:3)
 	// Storage: System Account (r:2 w:2)
 	// Storage: Chess MatchIdFromNonce (r:0 w:1)
+	// Storage: Chess PlayerStatistics (r:2 w:2)
+	// Storage: Chess RatedGames (r:2 w:2)
+	// Storage: Chess RatingHistory (r:2 w:2)
+	// Storage: Chess Leaderboards (r:1 w:1)
+	// Storage: Chess RankedPlayers (r:3 w:4)
+	// Storage: Chess FinishedMatches (r:0 w:1)
+	// Storage: Chess PlayerFinishedMatches (r:0 w:2)
 	fn clear_abandoned_match() -> Weight {
 		// Minimum execution time: 120_950 nanoseconds.
 		Weight::from_parts(122_610_000, 0)
-			.saturating_add(T::DbWeight::get().reads(7))
-			.saturating_add(T::DbWeight::get().writes(8))
+			.saturating_add(T::DbWeight::get().reads(17))
+			.saturating_add(T::DbWeight::get().writes(22))
+	}
+	// Storage: Chess Matches (r:1 w:1)
+	// Storage: Assets Asset (r:1 w:1)
+	// Storage: Assets Account (r:2 w:2)
+	// Storage: System Account (r:1 w:1)
+	// Storage: Chess PlayerRatings (r:2 w:2)
+	// Storage: Chess PlayerElo (r:2 w:2)
+	// Storage: Chess PlayerMatches (r:0 w:2)
+	// Storage: Chess MatchIdFromNonce (r:0 w:1)
+	// Storage: Chess PlayerStatistics (r:2 w:2)
+	// Storage: Chess RatedGames (r:2 w:2)
+	// Storage: Chess RatingHistory (r:2 w:2)
+	// Storage: Chess Leaderboards (r:1 w:1)
+	// Storage: Chess RankedPlayers (r:3 w:4)
+	// Storage: Chess FinishedMatches (r:0 w:1)
+	// Storage: Chess PlayerFinishedMatches (r:0 w:2)
+	// unmeasured estimate: settles the match like clear_abandoned_match()
+	fn resign() -> Weight {
+		Weight::from_parts(122_610_000, 0)
+			.saturating_add(T::DbWeight::get().reads(19))
+			.saturating_add(T::DbWeight::get().writes(26))
+	}
+	// Storage: Chess Matches (r:1 w:1)
+	// unmeasured estimate, bounded by abort_match()
+	fn offer_draw() -> Weight {
+		Weight::from_parts(83_430_000, 0)
+			.saturating_add(T::DbWeight::get().reads(1))
+			.saturating_add(T::DbWeight::get().writes(1))
+	}
+	// Storage: Chess Matches (r:1 w:1)
+	// Storage: Assets Asset (r:1 w:1)
+	// Storage: Assets Account (r:3 w:3)
+	// Storage: System Account (r:1 w:1)
+	// Storage: Chess PlayerRatings (r:2 w:2)
+	// Storage: Chess PlayerElo (r:2 w:2)
+	// Storage: Chess PlayerMatches (r:0 w:2)
+	// Storage: Chess MatchIdFromNonce (r:0 w:1)
+	// Storage: Chess PlayerStatistics (r:2 w:2)
+	// Storage: Chess RatedGames (r:2 w:2)
+	// Storage: Chess RatingHistory (r:2 w:2)
+	// Storage: Chess Leaderboards (r:1 w:1)
+	// Storage: Chess RankedPlayers (r:3 w:4)
+	// Storage: Chess FinishedMatches (r:0 w:1)
+	// Storage: Chess PlayerFinishedMatches (r:0 w:2)
+	// unmeasured estimate: settles the match like clear_abandoned_match()
+	fn accept_draw() -> Weight {
+		Weight::from_parts(122_610_000, 0)
+			.saturating_add(T::DbWeight::get().reads(20))
+			.saturating_add(T::DbWeight::get().writes(27))
+	}
+	// Storage: Chess Matches (r:1 w:1)
+	// unmeasured estimate, bounded by abort_match()
+	fn decline_draw() -> Weight {
+		Weight::from_parts(83_430_000, 0)
+			.saturating_add(T::DbWeight::get().reads(1))
+			.saturating_add(T::DbWeight::get().writes(1))
+	}
+	// Storage: Chess Matches (r:1 w:1)
+	// Storage: Chess PositionHistory (r:1 w:1)
+	// Storage: Assets Asset (r:1 w:1)
+	// Storage: Assets Account (r:3 w:3)
+	// Storage: System Account (r:1 w:1)
+	// Storage: Chess PlayerRatings (r:2 w:2)
+	// Storage: Chess PlayerElo (r:2 w:2)
+	// Storage: Chess PlayerMatches (r:0 w:2)
+	// Storage: Chess MatchIdFromNonce (r:0 w:1)
+	// Storage: Chess PlayerStatistics (r:2 w:2)
+	// Storage: Chess RatedGames (r:2 w:2)
+	// Storage: Chess RatingHistory (r:2 w:2)
+	// Storage: Chess Leaderboards (r:1 w:1)
+	// Storage: Chess RankedPlayers (r:3 w:4)
+	// Storage: Chess FinishedMatches (r:0 w:1)
+	// Storage: Chess PlayerFinishedMatches (r:0 w:2)
+	// unmeasured estimate: checks the position like make_move(), then settles the match
+	// like clear_abandoned_match()
+	fn claim_draw() -> Weight {
+		Weight::from_parts(238_689_054, 0)
+			.saturating_add(T::DbWeight::get().reads(21))
+			.saturating_add(T::DbWeight::get().writes(28))
+	}
+	// Storage: Chess FinishedMatches (r:1 w:1)
+	// Storage: Chess PlayerFinishedMatches (r:0 w:2)
+	// unmeasured estimate, bounded by abort_match()
+	fn prune_finished_match() -> Weight {
+		Weight::from_parts(83_430_000, 0)
+			.saturating_add(T::DbWeight::get().reads(1))
+			.saturating_add(T::DbWeight::get().writes(3))
+	}
+	// Storage: Chess MatchmakingQueue (r:1 w:1)
+	// Storage: Chess PlayerRatings (r:1 w:0)
+	// Storage: Chess PlayerElo (r:1 w:0)
+	// Storage: Assets Asset (r:1 w:1)
+	// Storage: Assets Account (r:2 w:2)
+	// Storage: System Account (r:1 w:1)
+	// Storage: Chess ChessParameters (r:1 w:0)
+	// unmeasured estimate: holds a stake like create_match()
+	fn enter_queue() -> Weight {
+		Weight::from_parts(91_090_000, 0)
+			.saturating_add(T::DbWeight::get().reads(8))
+			.saturating_add(T::DbWeight::get().writes(5))
+	}
+	// Storage: Chess MatchmakingQueue (r:1 w:1)
+	// Storage: Assets Asset (r:1 w:1)
+	// Storage: Assets Account (r:2 w:2)
+	// Storage: System Account (r:1 w:1)
+	// unmeasured estimate, bounded by abort_match()
+	fn leave_queue() -> Weight {
+		Weight::from_parts(83_430_000, 0)
+			.saturating_add(T::DbWeight::get().reads(5))
+			.saturating_add(T::DbWeight::get().writes(5))
+	}
+	// Storage: Chess MatchmakingQueue (r:0 w:1)
+	// Storage: Chess NextNonce (r:1 w:1)
+	// Storage: Chess Matches (r:0 w:1)
+	// Storage: Chess PlayerMatches (r:0 w:2)
+	// Storage: Chess MatchIdFromNonce (r:0 w:1)
+	// Storage: Chess PositionHistory (r:1 w:1)
+	// Storage: Chess ChessParameters (r:1 w:0)
+	// unmeasured estimate: join_match() plus an estimated term per queue entry
+	/// The range of component `q` is `[2, 16]`.
+	fn start_queued_match(q: u32, ) -> Weight {
+		Weight::from_parts(80_450_000, 0)
+			.saturating_add(Weight::from_parts(2_000_000, 0).saturating_mul(q.into()))
+			.saturating_add(T::DbWeight::get().reads(3))
+			.saturating_add(T::DbWeight::get().writes(7))
+	}
+	// unmeasured estimate
+	/// The range of component `q` is `[1, 16]`.
+	fn skip_queued_entry(q: u32, ) -> Weight {
+		Weight::from_parts(500_000, 0)
+			.saturating_add(Weight::from_parts(200_000, 0).saturating_mul(q.into()))
+	}
+	// Storage: Chess ChessParameters (r:0 w:1)
+	// unmeasured estimate, bounded by abort_match()
+	fn set_parameters() -> Weight {
+		Weight::from_parts(83_430_000, 0)
+			.saturating_add(T::DbWeight::get().writes(1))
 	}
 }
 
@@ -104,10 +303,11 @@ impl WeightInfo for () {
 	// Storage: System Account (r:1 w:1)
 	// Storage: Chess Matches (r:0 w:1)
 	// Storage: Chess MatchIdFromNonce (r:0 w:1)
+	// Storage: Chess ChessParameters (r:1 w:0)
 	fn create_match() -> Weight {
 		// Minimum execution time: 88_030 nanoseconds.
 		Weight::from_parts(91_090_000, 0)
-			.saturating_add(RocksDbWeight::get().reads(5))
+			.saturating_add(RocksDbWeight::get().reads(6))
 			.saturating_add(RocksDbWeight::get().writes(7))
 	}
 	// Storage: Chess Matches (r:1 w:1)
@@ -115,38 +315,219 @@ impl WeightInfo for () {
 	// Storage: Assets Account (r:2 w:2)
 	// Storage: System Account (r:1 w:1)
 	// Storage: Chess MatchIdFromNonce (r:0 w:1)
+	// Storage: Chess OpenChallenges (r:0 w:1)
 	fn abort_match() -> Weight {
 		// Minimum execution time: 82_190 nanoseconds.
 		Weight::from_parts(83_430_000, 0)
 			.saturating_add(RocksDbWeight::get().reads(5))
-			.saturating_add(RocksDbWeight::get().writes(6))
+			.saturating_add(RocksDbWeight::get().writes(7))
 	}
 	// Storage: Chess Matches (r:1 w:1)
 	// Storage: Assets Asset (r:1 w:1)
 	// Storage: Assets Account (r:2 w:2)
+	// Storage: Chess PositionHistory (r:1 w:1)
+	// Storage: Chess OpenChallenges (r:0 w:1)
+	// Storage: Chess PlayerMatches (r:0 w:1)
+	// measured at 72_110_000 before open challenges, colors and position history,
+	// raised by an unmeasured estimate for them
 	fn join_match() -> Weight {
-		// Minimum execution time: 70_710 nanoseconds.
-		Weight::from_parts(72_110_000, 0)
-			.saturating_add(RocksDbWeight::get().reads(4))
-			.saturating_add(RocksDbWeight::get().writes(4))
+		Weight::from_parts(80_450_000, 0)
+			.saturating_add(RocksDbWeight::get().reads(5))
+			.saturating_add(RocksDbWeight::get().writes(7))
 	}
 	// Storage: Chess Matches (r:1 w:1)
+	// Storage: Chess MatchMoves (r:1 w:1)
+	// Storage: Chess PositionHistory (r:1 w:1)
 	// read `pallet-chess/docs` to understand how this weight was calculated.
-	fn make_move() -> Weight {
+	// the per-move term is an unmeasured estimate, only the base was measured
+	/// The range of component `m` is `[0, 1024]`.
+	fn make_move(m: u32, ) -> Weight {
 		// Minimum execution time: 35_470 nanoseconds.
 		Weight::from_parts(116_079_054, 0)
-			.saturating_add(RocksDbWeight::get().reads(1))
-			.saturating_add(RocksDbWeight::get().writes(1))
+			.saturating_add(Weight::from_parts(50_000, 0).saturating_mul(m.into()))
+			.saturating_add(RocksDbWeight::get().reads(3))
+			.saturating_add(RocksDbWeight::get().writes(3))
+	}
+	// Storage: Chess Matches (r:1 w:1)
+	// Storage: Chess MatchMoves (r:1 w:1)
+	// Storage: Chess PositionHistory (r:0 w:1)
+	// Storage: Chess MoveHistoryTruncated (r:0 w:1)
+	// Storage: Assets Asset (r:1 w:1)
+	// Storage: Assets Account (r:2 w:2)
+	// Storage: System Account (r:1 w:1)
+	// Storage: Chess PlayerRatings (r:2 w:2)
+	// Storage: Chess PlayerElo (r:2 w:2)
+	// Storage: Chess PlayerMatches (r:0 w:2)
+	// Storage: Chess MatchIdFromNonce (r:0 w:1)
+	// Storage: Chess PlayerStatistics (r:2 w:2)
+	// Storage: Chess RatedGames (r:2 w:2)
+	// Storage: Chess RatingHistory (r:2 w:2)
+	// Storage: Chess Leaderboards (r:1 w:1)
+	// Storage: Chess RankedPlayers (r:3 w:4)
+	// Storage: Chess FinishedMatches (r:0 w:1)
+	// Storage: Chess PlayerFinishedMatches (r:0 w:2)
+	// unmeasured estimate: make_move() plus clear_abandoned_match(), which settles
+	// the match the same way
+	/// The range of component `m` is `[0, 1024]`.
+	fn make_move_checkmate(m: u32, ) -> Weight {
+		Weight::from_parts(238_689_054, 0)
+			.saturating_add(Weight::from_parts(50_000, 0).saturating_mul(m.into()))
+			.saturating_add(RocksDbWeight::get().reads(20))
+			.saturating_add(RocksDbWeight::get().writes(29))
 	}
 	// Storage: Chess Matches (r:1 w:1)
 	// Storage: Assets Asset (r:1 w:1)
 	// Storage: Assets Account (r:3 w:3)
 	// Storage: System Account (r:2 w:2)
 	// Storage: Chess MatchIdFromNonce (r:0 w:1)
+	// Storage: Chess PlayerStatistics (r:2 w:2)
+	// Storage: Chess RatedGames (r:2 w:2)
+	// Storage: Chess RatingHistory (r:2 w:2)
+	// Storage: Chess Leaderboards (r:1 w:1)
+	// Storage: Chess RankedPlayers (r:3 w:4)
+	// Storage: Chess FinishedMatches (r:0 w:1)
+	// Storage: Chess PlayerFinishedMatches (r:0 w:2)
 	fn clear_abandoned_match() -> Weight {
 		// Minimum execution time: 120_950 nanoseconds.
 		Weight::from_parts(122_610_000, 0)
-			.saturating_add(RocksDbWeight::get().reads(7))
-			.saturating_add(RocksDbWeight::get().writes(8))
+			.saturating_add(RocksDbWeight::get().reads(17))
+			.saturating_add(RocksDbWeight::get().writes(22))
+	}
+	// Storage: Chess Matches (r:1 w:1)
+	// Storage: Assets Asset (r:1 w:1)
+	// Storage: Assets Account (r:2 w:2)
+	// Storage: System Account (r:1 w:1)
+	// Storage: Chess PlayerRatings (r:2 w:2)
+	// Storage: Chess PlayerElo (r:2 w:2)
+	// Storage: Chess PlayerMatches (r:0 w:2)
+	// Storage: Chess MatchIdFromNonce (r:0 w:1)
+	// Storage: Chess PlayerStatistics (r:2 w:2)
+	// Storage: Chess RatedGames (r:2 w:2)
+	// Storage: Chess RatingHistory (r:2 w:2)
+	// Storage: Chess Leaderboards (r:1 w:1)
+	// Storage: Chess RankedPlayers (r:3 w:4)
+	// Storage: Chess FinishedMatches (r:0 w:1)
+	// Storage: Chess PlayerFinishedMatches (r:0 w:2)
+	// unmeasured estimate: settles the match like clear_abandoned_match()
+	fn resign() -> Weight {
+		Weight::from_parts(122_610_000, 0)
+			.saturating_add(RocksDbWeight::get().reads(19))
+			.saturating_add(RocksDbWeight::get().writes(26))
+	}
+	// Storage: Chess Matches (r:1 w:1)
+	// unmeasured estimate, bounded by abort_match()
+	fn offer_draw() -> Weight {
+		Weight::from_parts(83_430_000, 0)
+			.saturating_add(RocksDbWeight::get().reads(1))
+			.saturating_add(RocksDbWeight::get().writes(1))
+	}
+	// Storage: Chess Matches (r:1 w:1)
+	// Storage: Assets Asset (r:1 w:1)
+	// Storage: Assets Account (r:3 w:3)
+	// Storage: System Account (r:1 w:1)
+	// Storage: Chess PlayerRatings (r:2 w:2)
+	// Storage: Chess PlayerElo (r:2 w:2)
+	// Storage: Chess PlayerMatches (r:0 w:2)
+	// Storage: Chess MatchIdFromNonce (r:0 w:1)
+	// Storage: Chess PlayerStatistics (r:2 w:2)
+	// Storage: Chess RatedGames (r:2 w:2)
+	// Storage: Chess RatingHistory (r:2 w:2)
+	// Storage: Chess Leaderboards (r:1 w:1)
+	// Storage: Chess RankedPlayers (r:3 w:4)
+	// Storage: Chess FinishedMatches (r:0 w:1)
+	// Storage: Chess PlayerFinishedMatches (r:0 w:2)
+	// unmeasured estimate: settles the match like clear_abandoned_match()
+	fn accept_draw() -> Weight {
+		Weight::from_parts(122_610_000, 0)
+			.saturating_add(RocksDbWeight::get().reads(20))
+			.saturating_add(RocksDbWeight::get().writes(27))
+	}
+	// Storage: Chess Matches (r:1 w:1)
+	// unmeasured estimate, bounded by abort_match()
+	fn decline_draw() -> Weight {
+		Weight::from_parts(83_430_000, 0)
+			.saturating_add(RocksDbWeight::get().reads(1))
+			.saturating_add(RocksDbWeight::get().writes(1))
+	}
+	// Storage: Chess Matches (r:1 w:1)
+	// Storage: Chess PositionHistory (r:1 w:1)
+	// Storage: Assets Asset (r:1 w:1)
+	// Storage: Assets Account (r:3 w:3)
+	// Storage: System Account (r:1 w:1)
+	// Storage: Chess PlayerRatings (r:2 w:2)
+	// Storage: Chess PlayerElo (r:2 w:2)
+	// Storage: Chess PlayerMatches (r:0 w:2)
+	// Storage: Chess MatchIdFromNonce (r:0 w:1)
+	// Storage: Chess PlayerStatistics (r:2 w:2)
+	// Storage: Chess RatedGames (r:2 w:2)
+	// Storage: Chess RatingHistory (r:2 w:2)
+	// Storage: Chess Leaderboards (r:1 w:1)
+	// Storage: Chess RankedPlayers (r:3 w:4)
+	// Storage: Chess FinishedMatches (r:0 w:1)
+	// Storage: Chess PlayerFinishedMatches (r:0 w:2)
+	// unmeasured estimate: checks the position like make_move(), then settles the match
+	// like clear_abandoned_match()
+	fn claim_draw() -> Weight {
+		Weight::from_parts(238_689_054, 0)
+			.saturating_add(RocksDbWeight::get().reads(21))
+			.saturating_add(RocksDbWeight::get().writes(28))
+	}
+	// Storage: Chess FinishedMatches (r:1 w:1)
+	// Storage: Chess PlayerFinishedMatches (r:0 w:2)
+	// unmeasured estimate, bounded by abort_match()
+	fn prune_finished_match() -> Weight {
+		Weight::from_parts(83_430_000, 0)
+			.saturating_add(RocksDbWeight::get().reads(1))
+			.saturating_add(RocksDbWeight::get().writes(3))
+	}
+	// Storage: Chess MatchmakingQueue (r:1 w:1)
+	// Storage: Chess PlayerRatings (r:1 w:0)
+	// Storage: Chess PlayerElo (r:1 w:0)
+	// Storage: Assets Asset (r:1 w:1)
+	// Storage: Assets Account (r:2 w:2)
+	// Storage: System Account (r:1 w:1)
+	// Storage: Chess ChessParameters (r:1 w:0)
+	// unmeasured estimate: holds a stake like create_match()
+	fn enter_queue() -> Weight {
+		Weight::from_parts(91_090_000, 0)
+			.saturating_add(RocksDbWeight::get().reads(8))
+			.saturating_add(RocksDbWeight::get().writes(5))
+	}
+	// Storage: Chess MatchmakingQueue (r:1 w:1)
+	// Storage: Assets Asset (r:1 w:1)
+	// Storage: Assets Account (r:2 w:2)
+	// Storage: System Account (r:1 w:1)
+	// unmeasured estimate, bounded by abort_match()
+	fn leave_queue() -> Weight {
+		Weight::from_parts(83_430_000, 0)
+			.saturating_add(RocksDbWeight::get().reads(5))
+			.saturating_add(RocksDbWeight::get().writes(5))
+	}
+	// Storage: Chess MatchmakingQueue (r:0 w:1)
+	// Storage: Chess NextNonce (r:1 w:1)
+	// Storage: Chess Matches (r:0 w:1)
+	// Storage: Chess PlayerMatches (r:0 w:2)
+	// Storage: Chess MatchIdFromNonce (r:0 w:1)
+	// Storage: Chess PositionHistory (r:1 w:1)
+	// Storage: Chess ChessParameters (r:1 w:0)
+	// unmeasured estimate: join_match() plus an estimated term per queue entry
+	/// The range of component `q` is `[2, 16]`.
+	fn start_queued_match(q: u32, ) -> Weight {
+		Weight::from_parts(80_450_000, 0)
+			.saturating_add(Weight::from_parts(2_000_000, 0).saturating_mul(q.into()))
+			.saturating_add(RocksDbWeight::get().reads(3))
+			.saturating_add(RocksDbWeight::get().writes(7))
+	}
+	// unmeasured estimate
+	/// The range of component `q` is `[1, 16]`.
+	fn skip_queued_entry(q: u32, ) -> Weight {
+		Weight::from_parts(500_000, 0)
+			.saturating_add(Weight::from_parts(200_000, 0).saturating_mul(q.into()))
+	}
+	// Storage: Chess ChessParameters (r:0 w:1)
+	// unmeasured estimate, bounded by abort_match()
+	fn set_parameters() -> Weight {
+		Weight::from_parts(83_430_000, 0)
+			.saturating_add(RocksDbWeight::get().writes(1))
 	}
 }